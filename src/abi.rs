@@ -0,0 +1,572 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use crate::keccak256;
+
+/// A Solidity ABI type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamType {
+    Address,
+    Bool,
+    Uint(usize),
+    Int(usize),
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+/// A value ready to be ABI-encoded. Integers are stored as 32-byte
+/// big-endian words, already two's-complemented if negative.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Address([u8; 20]),
+    Bool(bool),
+    Uint([u8; 32]),
+    Int([u8; 32]),
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<Token>),
+    FixedArray(Vec<Token>),
+    Tuple(Vec<Token>),
+}
+
+impl ParamType {
+    /// Parse a canonical type string such as `uint256`, `address[]` or
+    /// `(address,bytes32)[2]`.
+    pub fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
+        let s = s.trim();
+
+        // array suffixes bind last, so peel them off first
+        if let Some(stripped) = s.strip_suffix(']') {
+            let open = stripped.rfind('[').ok_or_else(|| format!("invalid type '{}'", s))?;
+            let inner = ParamType::parse(&stripped[..open])?;
+            let size = &stripped[open + 1..];
+            return if size.is_empty() {
+                Ok(ParamType::Array(Box::new(inner)))
+            } else {
+                let size = size.parse::<usize>().map_err(|_| format!("invalid array size in '{}'", s))?;
+                Ok(ParamType::FixedArray(Box::new(inner), size))
+            };
+        }
+
+        if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            let components = split_top_level(inner)?
+                .iter()
+                .map(|c| ParamType::parse(c))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(ParamType::Tuple(components));
+        }
+
+        match s {
+            "address" => return Ok(ParamType::Address),
+            "bool" => return Ok(ParamType::Bool),
+            "bytes" => return Ok(ParamType::Bytes),
+            "string" => return Ok(ParamType::String),
+            "uint" => return Ok(ParamType::Uint(256)),
+            "int" => return Ok(ParamType::Int(256)),
+            _ => {}
+        }
+
+        let sized = |prefix: &str, valid: &dyn Fn(usize) -> bool| -> Option<usize> {
+            s.strip_prefix(prefix)
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| valid(*n))
+        };
+
+        if let Some(bits) = sized("uint", &|n| n > 0 && n <= 256 && n % 8 == 0) {
+            Ok(ParamType::Uint(bits))
+        } else if let Some(bits) = sized("int", &|n| n > 0 && n <= 256 && n % 8 == 0) {
+            Ok(ParamType::Int(bits))
+        } else if let Some(len) = sized("bytes", &|n| n > 0 && n <= 32) {
+            Ok(ParamType::FixedBytes(len))
+        } else {
+            Err(format!("unsupported ABI type '{}'", s).into())
+        }
+    }
+
+    /// Parse a parameter description from a JSON ABI (an object with `type`
+    /// and, for tuples, `components`).
+    pub fn from_json(param: &Value) -> Result<Self, Box<dyn Error>> {
+        let ty = param["type"].as_str().ok_or("ABI parameter without a type")?;
+
+        match ty.strip_prefix("tuple") {
+            Some(suffix) => {
+                let components = param["components"]
+                    .as_array()
+                    .ok_or("tuple ABI parameter without components")?
+                    .iter()
+                    .map(ParamType::from_json)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let names = components.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                ParamType::parse(&format!("({}){}", names.join(","), suffix))
+            }
+            None => ParamType::parse(ty),
+        }
+    }
+}
+
+impl std::fmt::Display for ParamType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParamType::Address => write!(f, "address"),
+            ParamType::Bool => write!(f, "bool"),
+            ParamType::Uint(bits) => write!(f, "uint{}", bits),
+            ParamType::Int(bits) => write!(f, "int{}", bits),
+            ParamType::FixedBytes(len) => write!(f, "bytes{}", len),
+            ParamType::Bytes => write!(f, "bytes"),
+            ParamType::String => write!(f, "string"),
+            ParamType::Array(inner) => write!(f, "{}[]", inner),
+            ParamType::FixedArray(inner, size) => write!(f, "{}[{}]", inner, size),
+            ParamType::Tuple(components) => {
+                let names = components.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                write!(f, "({})", names.join(","))
+            }
+        }
+    }
+}
+
+impl Token {
+    /// Parse a command-line value for the given type. Arrays are written as
+    /// `[a,b]`, tuples as `(a,b)`, integers in decimal or 0x-prefixed hex.
+    pub fn parse(ty: &ParamType, value: &str) -> Result<Self, Box<dyn Error>> {
+        let value = value.trim();
+
+        match ty {
+            ParamType::Address => {
+                let bytes = decode_hex(value)?;
+                if bytes.len() != 20 {
+                    return Err(format!("'{}' is not a 20-byte address", value).into());
+                }
+                let mut address = [0u8; 20];
+                address.copy_from_slice(&bytes);
+                Ok(Token::Address(address))
+            }
+            ParamType::Bool => match value {
+                "true" => Ok(Token::Bool(true)),
+                "false" => Ok(Token::Bool(false)),
+                _ => Err(format!("'{}' is not a bool", value).into()),
+            },
+            ParamType::Uint(bits) => {
+                if value.starts_with('-') {
+                    return Err(format!("'{}' is negative but the type is uint{}", value, bits).into());
+                }
+                let word = parse_uint(value)?;
+                check_bits(&word, *bits, false, false, value)?;
+                Ok(Token::Uint(word))
+            }
+            ParamType::Int(bits) => {
+                let (negative, magnitude) = match value.strip_prefix('-') {
+                    Some(rest) => (true, rest),
+                    None => (false, value),
+                };
+                let mut word = parse_uint(magnitude)?;
+                check_bits(&word, *bits, true, negative, value)?;
+                if negative {
+                    negate(&mut word);
+                }
+                Ok(Token::Int(word))
+            }
+            ParamType::FixedBytes(len) => {
+                let bytes = decode_hex(value)?;
+                if bytes.len() != *len {
+                    return Err(format!("'{}' is not exactly {} bytes", value, len).into());
+                }
+                Ok(Token::FixedBytes(bytes))
+            }
+            ParamType::Bytes => Ok(Token::Bytes(decode_hex(value)?)),
+            ParamType::String => Ok(Token::String(unquote(value).to_string())),
+            ParamType::Array(inner) => {
+                let items = parse_list(value, '[', ']')?;
+                let tokens = items
+                    .iter()
+                    .map(|item| Token::parse(inner, item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Token::Array(tokens))
+            }
+            ParamType::FixedArray(inner, size) => {
+                let items = parse_list(value, '[', ']')?;
+                if items.len() != *size {
+                    return Err(format!("expected {} array elements in '{}'", size, value).into());
+                }
+                let tokens = items
+                    .iter()
+                    .map(|item| Token::parse(inner, item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Token::FixedArray(tokens))
+            }
+            ParamType::Tuple(components) => {
+                let items = parse_list(value, '(', ')')?;
+                if items.len() != components.len() {
+                    return Err(format!("expected {} tuple fields in '{}'", components.len(), value).into());
+                }
+                let tokens = components
+                    .iter()
+                    .zip(items.iter())
+                    .map(|(c, item)| Token::parse(c, item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Token::Tuple(tokens))
+            }
+        }
+    }
+
+    fn is_dynamic(&self) -> bool {
+        match self {
+            Token::Bytes(_) | Token::String(_) | Token::Array(_) => true,
+            Token::FixedArray(tokens) | Token::Tuple(tokens) => tokens.iter().any(|t| t.is_dynamic()),
+            _ => false,
+        }
+    }
+}
+
+/// ABI-encode a list of values the way `abi.encode` does.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let head_size: usize = tokens
+        .iter()
+        .map(|t| if t.is_dynamic() { 32 } else { encode_token(t).len() })
+        .sum();
+
+    let mut head = Vec::with_capacity(head_size);
+    let mut tail = Vec::new();
+
+    for token in tokens {
+        let encoded = encode_token(token);
+        if token.is_dynamic() {
+            head.extend_from_slice(&uint_word((head_size + tail.len()) as u64));
+            tail.extend_from_slice(&encoded);
+        } else {
+            head.extend_from_slice(&encoded);
+        }
+    }
+
+    head.extend_from_slice(&tail);
+    head
+}
+
+/// Encode a function call: the 4-byte selector of `signature` followed by
+/// the ABI-encoded arguments.
+pub fn encode_call(signature: &str, tokens: &[Token]) -> Vec<u8> {
+    let mut calldata = selector(signature).to_vec();
+    calldata.extend_from_slice(&encode(tokens));
+    calldata
+}
+
+/// The 4-byte selector of a canonical function signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Parse and encode command-line values against a list of types.
+pub fn encode_args(types: &[ParamType], values: &[String]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if types.len() != values.len() {
+        return Err(format!("expected {} arguments, got {}", types.len(), values.len()).into());
+    }
+
+    let tokens = types
+        .iter()
+        .zip(values.iter())
+        .map(|(ty, value)| Token::parse(ty, value))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(encode(&tokens))
+}
+
+/// A 32-byte big-endian word holding `value`.
+pub fn uint_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Format a 32-byte big-endian unsigned integer in decimal.
+pub fn format_uint(word: &[u8; 32]) -> String {
+    let mut value = *word;
+    let mut digits = Vec::new();
+
+    // repeated long division by 10
+    while value.iter().any(|&b| b != 0) {
+        let mut remainder = 0u32;
+        for byte in value.iter_mut() {
+            let current = (remainder << 8) | *byte as u32;
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+
+    if digits.is_empty() {
+        return String::from("0");
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// A 32-byte word holding a left-padded address.
+pub fn address_word(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+fn encode_token(token: &Token) -> Vec<u8> {
+    match token {
+        Token::Address(address) => address_word(address).to_vec(),
+        Token::Bool(value) => uint_word(*value as u64).to_vec(),
+        Token::Uint(word) | Token::Int(word) => word.to_vec(),
+        Token::FixedBytes(bytes) => pad_right(bytes),
+        Token::Bytes(bytes) => {
+            let mut encoded = uint_word(bytes.len() as u64).to_vec();
+            encoded.extend_from_slice(&pad_right(bytes));
+            encoded
+        }
+        Token::String(s) => encode_token(&Token::Bytes(s.as_bytes().to_vec())),
+        Token::Array(tokens) => {
+            let mut encoded = uint_word(tokens.len() as u64).to_vec();
+            encoded.extend_from_slice(&encode(tokens));
+            encoded
+        }
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => encode(tokens),
+    }
+}
+
+fn pad_right(bytes: &[u8]) -> Vec<u8> {
+    let mut padded = bytes.to_vec();
+    let len = bytes.len().div_ceil(32) * 32;
+    padded.resize(len, 0);
+    padded
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(digits).map_err(|e| format!("'{}' is not valid hex: {}", value, e).into())
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Parse a decimal or 0x-prefixed hex integer into a big-endian word.
+fn parse_uint(value: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    let mut word = [0u8; 32];
+
+    if let Some(digits) = value.strip_prefix("0x") {
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(format!("'{}' is not a valid 256-bit hex integer", value).into());
+        }
+        let padded = format!("{:0>64}", digits);
+        let bytes = hex::decode(&padded).map_err(|_| format!("'{}' is not valid hex", value))?;
+        word.copy_from_slice(&bytes);
+        return Ok(word);
+    }
+
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("'{}' is not a valid integer", value).into());
+    }
+
+    // multiply the word by ten and add each digit in turn
+    for digit in value.bytes().map(|b| b - b'0') {
+        let mut carry = digit as u32;
+        for byte in word.iter_mut().rev() {
+            let v = (*byte as u32) * 10 + carry;
+            *byte = v as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            return Err(format!("'{}' does not fit in 256 bits", value).into());
+        }
+    }
+
+    Ok(word)
+}
+
+/// Check that a magnitude fits in an integer of the given width.
+fn check_bits(word: &[u8; 32], bits: usize, signed: bool, negative: bool, value: &str) -> Result<(), Box<dyn Error>> {
+    let used_bits = word
+        .iter()
+        .position(|b| *b != 0)
+        .map(|i| (32 - i) * 8 - word[i].leading_zeros() as usize)
+        .unwrap_or(0);
+
+    let max_bits = if signed { bits - 1 } else { bits };
+    // -2^(bits-1) is representable even though its magnitude needs `bits` bits
+    let is_min_signed = negative && used_bits == bits && {
+        let top = 32 - bits / 8;
+        word[top] == 0x80 && word[top + 1..].iter().all(|b| *b == 0)
+    };
+
+    if used_bits > max_bits && !is_min_signed {
+        let ty = if signed { "int" } else { "uint" };
+        return Err(format!("'{}' does not fit in {}{}", value, ty, bits).into());
+    }
+
+    Ok(())
+}
+
+/// Two's-complement negation of a 256-bit word.
+fn negate(word: &mut [u8; 32]) {
+    let mut carry = 1u16;
+    for byte in word.iter_mut().rev() {
+        let v = (!*byte) as u16 + carry;
+        *byte = v as u8;
+        carry = v >> 8;
+    }
+}
+
+/// Split a bracketed list into its top-level items.
+fn parse_list(value: &str, open: char, close: char) -> Result<Vec<String>, Box<dyn Error>> {
+    let inner = value
+        .strip_prefix(open)
+        .and_then(|v| v.strip_suffix(close))
+        .ok_or_else(|| format!("expected '{}' to be wrapped in {}{}", value, open, close))?;
+
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    split_top_level(inner)
+}
+
+/// Split on commas that are not nested inside brackets, parentheses or
+/// double quotes.
+fn split_top_level(s: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut items = Vec::new();
+    let mut depth = 0i32;
+    let mut in_quotes = false;
+    let mut current = String::new();
+
+    for c in s.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '[' | '(' if !in_quotes => depth += 1,
+            ']' | ')' if !in_quotes => depth -= 1,
+            ',' if !in_quotes && depth == 0 => {
+                items.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        if depth < 0 {
+            return Err(format!("unbalanced brackets in '{}'", s).into());
+        }
+        current.push(c);
+    }
+
+    if depth != 0 || in_quotes {
+        return Err(format!("unbalanced brackets or quotes in '{}'", s).into());
+    }
+
+    items.push(current.trim().to_string());
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A number as a left-padded word, in hex
+    fn n(value: u64) -> String {
+        format!("{:064x}", value)
+    }
+
+    // Bytes as a right-padded word, in hex
+    fn r(bytes: &[u8]) -> String {
+        format!("{:0<64}", hex::encode(bytes))
+    }
+
+    fn call(signature: &str, values: &[&str]) -> String {
+        let open = signature.find('(').unwrap();
+        let types = match ParamType::parse(&signature[open..]).unwrap() {
+            ParamType::Tuple(types) => types,
+            ty => panic!("{} is not a parameter list", ty),
+        };
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        let mut calldata = selector(signature).to_vec();
+        calldata.extend_from_slice(&encode_args(&types, &values).unwrap());
+        hex::encode(calldata)
+    }
+
+    // The examples of the Solidity ABI specification
+    #[test]
+    fn static_arguments() {
+        assert_eq!(call("baz(uint32,bool)", &["69", "true"]), format!("cdcd77c0{}{}", n(69), n(1)));
+        assert_eq!(call("bar(bytes3[2])", &["[0x616263,0x646566]"]), format!("fce353f6{}{}", r(b"abc"), r(b"def")));
+    }
+
+    #[test]
+    fn dynamic_arguments() {
+        assert_eq!(
+            call("sam(bytes,bool,uint256[])", &["0x64617665", "true", "[1,2,3]"]),
+            ["a5643bf2".to_string(), n(0x60), n(1), n(0xa0), n(4), r(b"dave"), n(3), n(1), n(2), n(3)].concat()
+        );
+        assert_eq!(
+            call("f(uint256,uint32[],bytes10,bytes)", &["0x123", "[0x456,0x789]", "0x31323334353637383930", "0x48656c6c6f2c20776f726c6421"]),
+            [
+                "8be65246".to_string(),
+                n(0x123),
+                n(0x80),
+                r(b"1234567890"),
+                n(0xe0),
+                n(2),
+                n(0x456),
+                n(0x789),
+                n(13),
+                r(b"Hello, world!"),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn nested_dynamic_arguments() {
+        let tail = [
+            n(2), n(0x40), n(0xa0), n(2), n(1), n(2), n(1), n(3),
+            n(3), n(0x60), n(0xa0), n(0xe0), n(3), r(b"one"), n(3), r(b"two"), n(5), r(b"three"),
+        ]
+        .concat();
+        assert_eq!(
+            call("g(uint256[][],string[])", &["[[1,2],[3]]", "[one,two,three]"]),
+            format!("2289b18c{}{}{}", n(0x40), n(0x140), tail)
+        );
+
+        // bytes[] is laid out like string[]
+        let types = [ParamType::parse("uint256[][]").unwrap(), ParamType::parse("bytes[]").unwrap()];
+        let values = ["[[1,2],[3]]".to_string(), "[0x6f6e65,0x74776f,0x7468726565]".to_string()];
+        assert_eq!(hex::encode(encode_args(&types, &values).unwrap()), format!("{}{}{}", n(0x40), n(0x140), tail));
+    }
+
+    #[test]
+    fn padding() {
+        let encode_one = |ty: &str, value: &str| hex::encode(encode_args(&[ParamType::parse(ty).unwrap()], &[value.to_string()]).unwrap());
+
+        assert_eq!(encode_one("bytes", "0x"), [n(0x20), n(0)].concat());
+        assert_eq!(encode_one("bytes", &format!("0x{}", "ab".repeat(32))), [n(0x20), n(32), "ab".repeat(32)].concat());
+        assert_eq!(
+            encode_one("bytes", &format!("0x{}", "ab".repeat(33))),
+            [n(0x20), n(33), "ab".repeat(32), r(&[0xab])].concat()
+        );
+        assert_eq!(encode_one("string", "\"a,b\""), [n(0x20), n(3), r(b"a,b")].concat());
+        assert_eq!(encode_one("bytes1", "0xff"), r(&[0xff]));
+        assert_eq!(encode_one("address", "0x00000000000000000000000000000000000000ff"), n(0xff));
+        assert_eq!(encode_one("int8", "-1"), "ff".repeat(32));
+        assert_eq!(encode_one("int8", "-128"), format!("{}80", "ff".repeat(31)));
+        assert_eq!(encode_one("(uint8,bool)", "(7,false)"), [n(7), n(0)].concat());
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        for (ty, value) in [("uint8", "256"), ("int8", "128"), ("int8", "-129"), ("uint256", "-1"), ("bytes2", "0xff"), ("address", "0x1234")] {
+            assert!(Token::parse(&ParamType::parse(ty).unwrap(), value).is_err(), "{} {}", ty, value);
+        }
+        assert!(ParamType::parse("uint7").is_err());
+        assert!(ParamType::parse("bytes33").is_err());
+    }
+}
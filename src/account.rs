@@ -0,0 +1,249 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::abi::{address_word, encode, encode_call, Token};
+use crate::clone::CloneVariant;
+use crate::search::SaltScheme;
+use crate::{create2_address, keccak256};
+
+/// ERC-4337 account factories whose `createAccount` salt can be mined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountFactory {
+    /// eth-infinitism `SimpleAccountFactory.createAccount(owner, salt)`: an
+    /// `ERC1967Proxy` initialized with `initialize(owner)`, deployed with the
+    /// salt as given.
+    SimpleAccount,
+    /// Alchemy `LightAccountFactory` v1: the same layout as `SimpleAccount`.
+    LightAccount,
+    /// Alchemy `LightAccountFactory` v2: a Solady ERC-1967 clone deployed
+    /// with the salt `keccak256(abi.encode(owner, salt))`.
+    LightAccountV2,
+    /// ZeroDev `KernelFactory.createAccount(data, salt)` for Kernel v3: a
+    /// Solady ERC-1967 clone deployed with the salt
+    /// `keccak256(abi.encodePacked(data, salt))`.
+    Kernel,
+}
+
+impl AccountFactory {
+    /// Whether the account's init code is an `ERC1967Proxy` whose creation
+    /// code must be supplied.
+    pub fn needs_proxy_code(&self) -> bool {
+        match self {
+            AccountFactory::SimpleAccount | AccountFactory::LightAccount => true,
+            AccountFactory::LightAccountV2 | AccountFactory::Kernel => false,
+        }
+    }
+}
+
+impl FromStr for AccountFactory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "simple-account" => Ok(AccountFactory::SimpleAccount),
+            "light-account" => Ok(AccountFactory::LightAccount),
+            "light-account-v2" => Ok(AccountFactory::LightAccountV2),
+            "kernel" => Ok(AccountFactory::Kernel),
+            _ => Err(format!(
+                "unknown account factory '{}' (expected simple-account, light-account, light-account-v2 or kernel)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AccountFactory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountFactory::SimpleAccount => write!(f, "simple-account"),
+            AccountFactory::LightAccount => write!(f, "light-account"),
+            AccountFactory::LightAccountV2 => write!(f, "light-account-v2"),
+            AccountFactory::Kernel => write!(f, "kernel"),
+        }
+    }
+}
+
+/// A smart account deployed by an `AccountFactory`.
+#[derive(Clone, Debug)]
+pub struct SmartAccount {
+    pub factory: AccountFactory,
+    /// The account's owner.
+    pub owner: [u8; 20],
+    /// The account implementation the proxy points at, the factory's
+    /// `accountImplementation()` (or `implementation()` for Kernel).
+    pub implementation: [u8; 20],
+    /// `type(ERC1967Proxy).creationCode` as compiled into the factory, for
+    /// factories that deploy an OpenZeppelin proxy.
+    pub proxy_creation_code: Vec<u8>,
+    /// The Kernel `initialize` calldata passed as `data`.
+    pub initializer: Vec<u8>,
+}
+
+impl SmartAccount {
+    /// The init code the factory deploys.
+    pub fn init_code(&self) -> Vec<u8> {
+        match self.factory {
+            AccountFactory::SimpleAccount | AccountFactory::LightAccount => {
+                let initialize = encode_call("initialize(address)", &[Token::Address(self.owner)]);
+                let mut code = self.proxy_creation_code.clone();
+                code.extend_from_slice(&encode(&[Token::Address(self.implementation), Token::Bytes(initialize)]));
+                code
+            }
+            AccountFactory::LightAccountV2 | AccountFactory::Kernel => CloneVariant::Erc1967.init_code(&self.implementation),
+        }
+    }
+
+    /// The keccak-256 hash of the init code.
+    pub fn init_code_hash(&self) -> [u8; 32] {
+        keccak256(&self.init_code())
+    }
+
+    /// How the factory derives the CREATE2 salt from the `salt` argument.
+    pub fn salt_scheme(&self) -> SaltScheme {
+        match self.factory {
+            AccountFactory::SimpleAccount | AccountFactory::LightAccount => SaltScheme::Nonce,
+            AccountFactory::LightAccountV2 => SaltScheme::hashed(address_word(&self.owner).to_vec()),
+            AccountFactory::Kernel => SaltScheme::hashed(self.initializer.clone()),
+        }
+    }
+
+    /// The CREATE2 salt for a `salt` argument, as `createAccount` computes it.
+    pub fn salt(&self, salt: &[u8; 32]) -> [u8; 32] {
+        self.salt_scheme().hash_salt_nonce(salt).unwrap_or(*salt)
+    }
+
+    /// The address `factory` deploys the account to for a `salt` argument.
+    pub fn address(&self, factory: &[u8; 20], salt: &[u8; 32]) -> [u8; 20] {
+        create2_address(factory, &self.salt(salt), &self.init_code_hash())
+    }
+
+    /// The `createAccount` calldata that deploys the account.
+    pub fn create_account_calldata(&self, salt: &[u8; 32]) -> Vec<u8> {
+        match self.factory {
+            AccountFactory::Kernel => encode_call(
+                "createAccount(bytes,bytes32)",
+                &[Token::Bytes(self.initializer.clone()), Token::FixedBytes(salt.to_vec())],
+            ),
+            _ => encode_call("createAccount(address,uint256)", &[Token::Address(self.owner), Token::Uint(*salt)]),
+        }
+    }
+}
+
+/// Kernel v3.1 `initialize` calldata for an owner validated by the ECDSA
+/// validator module at `validator`, with no hook and no extra config.
+pub fn kernel_initializer(validator: &[u8; 20], owner: &[u8; 20]) -> Vec<u8> {
+    // a ValidationId is the validation type (1 for a validator module)
+    // followed by the validator address
+    let mut root_validator = vec![1u8];
+    root_validator.extend_from_slice(validator);
+
+    encode_call(
+        "initialize(bytes21,address,bytes,bytes,bytes[])",
+        &[
+            Token::FixedBytes(root_validator),
+            Token::Address([0u8; 20]),
+            Token::Bytes(owner.to_vec()),
+            Token::Bytes(Vec::new()),
+            Token::Array(Vec::new()),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(factory: AccountFactory) -> SmartAccount {
+        SmartAccount {
+            factory,
+            owner: [0x11; 20],
+            implementation: [0x22; 20],
+            proxy_creation_code: hex::decode("6080604052").unwrap(),
+            initializer: hex::decode("c0ffee").unwrap(),
+        }
+    }
+
+    fn word(bytes: &[u8]) -> String {
+        format!("{:0>64}", hex::encode(bytes))
+    }
+
+    #[test]
+    fn simple_account_deploys_an_initialized_proxy() {
+        let account = account(AccountFactory::SimpleAccount);
+        // creationCode ++ abi.encode(implementation, initialize(owner))
+        assert_eq!(
+            hex::encode(account.init_code()),
+            format!(
+                "6080604052{}{:064x}{:064x}c4d66de8{}{}",
+                word(&[0x22; 20]),
+                0x40,
+                0x24,
+                word(&[0x11; 20]),
+                "00".repeat(28)
+            )
+        );
+
+        let salt = [0x33; 32];
+        assert_eq!(account.salt(&salt), salt);
+        assert_eq!(account.address(&[0x44; 20], &salt), create2_address(&[0x44; 20], &salt, &account.init_code_hash()));
+        assert_eq!(
+            hex::encode(account.create_account_calldata(&salt)),
+            format!("5fbfb9cf{}{}", word(&[0x11; 20]), "33".repeat(32))
+        );
+    }
+
+    #[test]
+    fn light_account_v2_hashes_the_owner_into_the_salt() {
+        let account = account(AccountFactory::LightAccountV2);
+        assert_eq!(account.init_code(), CloneVariant::Erc1967.init_code(&[0x22; 20]));
+        // keccak256(abi.encode(owner, salt))
+        let preimage = format!("{}{}", word(&[0x11; 20]), "33".repeat(32));
+        assert_eq!(account.salt(&[0x33; 32]), keccak256(&hex::decode(preimage).unwrap()));
+    }
+
+    #[test]
+    fn kernel_hashes_the_initializer_into_the_salt() {
+        let account = account(AccountFactory::Kernel);
+        assert_eq!(account.init_code(), CloneVariant::Erc1967.init_code(&[0x22; 20]));
+        // keccak256(abi.encodePacked(data, salt))
+        let preimage = format!("c0ffee{}", "33".repeat(32));
+        assert_eq!(account.salt(&[0x33; 32]), keccak256(&hex::decode(preimage).unwrap()));
+        assert_eq!(
+            hex::encode(account.create_account_calldata(&[0x33; 32])),
+            format!("ea6d13ac{:064x}{}{:064x}{:0<64}", 0x40, "33".repeat(32), 3, "c0ffee")
+        );
+    }
+
+    #[test]
+    fn kernel_initializer_layout() {
+        assert_eq!(
+            hex::encode(kernel_initializer(&[0x55; 20], &[0x11; 20])),
+            format!(
+                "3c3b752b01{}{}{:064x}{:064x}{:064x}{:064x}{:064x}{:0<64}{:064x}{:064x}",
+                "55".repeat(20),
+                "00".repeat(11),
+                0,
+                0xa0,
+                0xe0,
+                0x100,
+                20,
+                "11".repeat(20),
+                0,
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn factories_round_trip_through_their_names() {
+        for factory in &[
+            AccountFactory::SimpleAccount,
+            AccountFactory::LightAccount,
+            AccountFactory::LightAccountV2,
+            AccountFactory::Kernel,
+        ] {
+            assert_eq!(factory.to_string().parse::<AccountFactory>(), Ok(*factory));
+        }
+        assert!("safe".parse::<AccountFactory>().is_err());
+    }
+}
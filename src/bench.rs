@@ -0,0 +1,176 @@
+use std::error::Error;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::cpu::CpuMiner;
+use crate::gpu::{gpu_devices, GpuMiner};
+use crate::output::OutputFormat;
+use crate::search::{AddressScheme, Miner, SaltScheme};
+use crate::tuning::{Tuning, WorkSize, CPU_DEVICE_NAME, CPU_WORK_SIZE};
+use crate::{Config, WORK_SIZE};
+
+/// Global sizes tried for each GPU, around the default `WORK_SIZE`.
+const GLOBAL_SIZES: [u32; 4] = [WORK_SIZE >> 4, WORK_SIZE >> 2, WORK_SIZE, WORK_SIZE << 2];
+/// Work-group sizes tried for each GPU; `None` lets the driver choose.
+const LOCAL_SIZES: [Option<u32>; 4] = [None, Some(64), Some(128), Some(256)];
+/// Nonces per work item tried for each GPU.
+const ITERATIONS: [u32; 3] = [1, 4, 16];
+/// Batch sizes tried for the CPU backend.
+const CPU_GLOBAL_SIZES: [u32; 4] = [CPU_WORK_SIZE >> 2, CPU_WORK_SIZE, CPU_WORK_SIZE << 2, CPU_WORK_SIZE << 4];
+
+/// Measured throughput of one device with one set of launch settings.
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub device: String,
+    pub work_size: WorkSize,
+    /// Hashes per second.
+    pub rate: f64,
+}
+
+/// Benchmark every GPU over the grid of launch settings, or the CPU backend
+/// if there is no GPU, and save the fastest settings per device to
+/// `tuning_file`. Each combination runs for about `duration`.
+pub fn bench(tuning_file: &Path, duration: Duration, format: OutputFormat) -> Result<Vec<BenchResult>, Box<dyn Error>> {
+    let config = bench_config(format);
+    let mut tuning = Tuning::load(tuning_file)?;
+    let mut results = Vec::new();
+
+    let gpus = gpu_devices().unwrap_or_else(|e| {
+        format.info(&format!("No OpenCL GPUs available ({}), benchmarking the CPU backend instead", e));
+        Vec::new()
+    });
+
+    if gpus.is_empty() {
+        let mut device_results = Vec::new();
+        for &global in CPU_GLOBAL_SIZES.iter() {
+            let work_size = WorkSize { global, local: None, iterations: 1 };
+            let mut miner = CpuMiner::new(&config, work_size)?;
+            let rate = measure(&mut miner, duration)?;
+            device_results.push(report(format, CPU_DEVICE_NAME, work_size, rate));
+        }
+        record_best(&mut tuning, &device_results, format);
+        results.extend(device_results);
+    }
+
+    for (index, device) in gpus {
+        let name = device.name()?;
+        let max_local = device.max_wg_size()? as u32;
+        format.info(&format!("Benchmarking GPU {} ({})...", index, name));
+
+        let mut device_results = Vec::new();
+        for &global in GLOBAL_SIZES.iter() {
+            for &local in LOCAL_SIZES.iter() {
+                for &iterations in ITERATIONS.iter() {
+                    let work_size = WorkSize { global, local, iterations };
+                    if work_size.validate().is_err() || local.is_some_and(|l| l > max_local) {
+                        continue;
+                    }
+
+                    // a combination the driver rejects is skipped, not fatal
+                    let rate = GpuMiner::new(&config, device, work_size)
+                        .and_then(|mut miner| measure(&mut miner, duration));
+                    match rate {
+                        Ok(rate) => device_results.push(report(format, &name, work_size, rate)),
+                        Err(e) => format.info(&format!("  {:?} failed: {}", work_size, e)),
+                    }
+                }
+            }
+        }
+        record_best(&mut tuning, &device_results, format);
+        results.extend(device_results);
+    }
+
+    tuning.save(tuning_file)?;
+    format.info(&format!("Saved tuning to {}", tuning_file.display()));
+
+    Ok(results)
+}
+
+/// Run batches for about `duration` after one warm-up batch and return the
+/// hash rate.
+pub fn measure<M: Miner>(miner: &mut M, duration: Duration) -> Result<f64, Box<dyn Error>> {
+    let mut base = 0u64;
+
+    miner.mine(base)?;
+
+    let start = Instant::now();
+    let mut attempts = 0u64;
+    while attempts == 0 || start.elapsed() < duration {
+        base += miner.batch_size();
+        miner.mine(base)?;
+        attempts += miner.batch_size();
+    }
+
+    Ok(attempts as f64 / start.elapsed().as_secs_f64())
+}
+
+/// A search that never matches in practice: all 40 digits are fixed, so the
+/// kernel does the same work as a real search without stopping early.
+fn bench_config(format: OutputFormat) -> Config {
+    Config {
+        factory_address: [0u8; 20],
+        calling_address: [0u8; 20],
+        init_code_hash: [0u8; 32],
+        gpu_device: 0,
+        leading_zeroes_threshold: 0,
+        total_zeroes_threshold: 0,
+        prefix: None,
+        starts_with: "0".repeat(40),
+        ends_with: String::new(),
+        case_sensitive: false,
+        format,
+        tuning_file: None,
+        hook_flags: None,
+        score: None,
+        best_score: Default::default(),
+        stop: Default::default(),
+        paused: Default::default(),
+        attempts: Default::default(),
+        metrics: Default::default(),
+        dashboard: Default::default(),
+        salt_scheme: SaltScheme::Nonce,
+        address_scheme: AddressScheme::Create2,
+    }
+}
+
+fn report(format: OutputFormat, device: &str, work_size: WorkSize, rate: f64) -> BenchResult {
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "event": "bench",
+                "device": device,
+                "global": work_size.global,
+                "local": work_size.local,
+                "iterations": work_size.iterations,
+                "rate": rate,
+            })
+        ),
+        OutputFormat::Text => {
+            let local = work_size.local.map(|l| l.to_string()).unwrap_or_else(|| String::from("auto"));
+            println!(
+                "  {}: global {:>10}  local {:>4}  iterations {:>2}  {:>10.2} MH/s",
+                device,
+                work_size.global,
+                local,
+                work_size.iterations,
+                rate / 1_000_000.0
+            );
+        }
+    }
+    BenchResult { device: device.to_string(), work_size, rate }
+}
+
+fn record_best(tuning: &mut Tuning, results: &[BenchResult], format: OutputFormat) {
+    if let Some(best) = results.iter().max_by(|a, b| a.rate.total_cmp(&b.rate)) {
+        format.info(&format!(
+            "Best for {}: {:?} at {:.2} MH/s",
+            best.device,
+            best.work_size,
+            best.rate / 1_000_000.0
+        ));
+        tuning.devices.insert(best.device.clone(), best.work_size);
+    }
+}
@@ -0,0 +1,207 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::output::{Event, Solution};
+use crate::search::{AddressScheme, SaltScheme};
+use crate::Config;
+
+/// The state of a search saved by `--checkpoint` when it ends: its totals
+/// over every run so far and the best candidate found. The search is
+/// random, so there are no nonces to pick up from; a resumed scoring search
+/// starts from the saved best score and only reports addresses that beat
+/// it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// What was searched for, so that a checkpoint is only resumed by the
+    /// same search.
+    pub search: Value,
+    pub attempts: u64,
+    pub elapsed_seconds: f64,
+    pub best_score: Option<u32>,
+    /// The best candidate, or the solution, as a `solution` event.
+    pub best: Option<Value>,
+}
+
+impl Checkpoint {
+    /// An empty checkpoint for the search in `config`.
+    pub fn new(config: &Config) -> Self {
+        Checkpoint { search: search_of(config), attempts: 0, elapsed_seconds: 0.0, best_score: None, best: None }
+    }
+
+    /// Load the checkpoint of the search in `config`, or start a new one if
+    /// the file does not exist. A checkpoint of another search is an error.
+    pub fn load(path: &Path, config: &Config) -> Result<Self, Box<dyn Error>> {
+        let checkpoint: Checkpoint = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("invalid checkpoint '{}': {}", path.display(), e))?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Checkpoint::new(config)),
+            Err(e) => return Err(format!("could not read checkpoint '{}': {}", path.display(), e).into()),
+        };
+        if checkpoint.search != search_of(config) {
+            return Err(format!(
+                "checkpoint '{}' is for a different search; remove it or pass another --checkpoint",
+                path.display()
+            ).into());
+        }
+        Ok(checkpoint)
+    }
+
+    /// Write the checkpoint, replacing any previous contents.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents + "\n")
+            .map_err(|e| format!("could not write checkpoint '{}': {}", path.display(), e).into())
+    }
+
+    /// Add a run of `attempts` over `elapsed_seconds`, and keep `best` if it
+    /// beats the saved candidate.
+    pub fn record(&mut self, attempts: u64, elapsed_seconds: f64, best: Option<&Solution>) {
+        self.attempts += attempts;
+        self.elapsed_seconds += elapsed_seconds;
+        if let Some(best) = best {
+            if self.best.is_none() || best.score > self.best_score {
+                self.best_score = best.score;
+                self.best = Some(Event::Solution(best).to_json());
+            }
+        }
+    }
+}
+
+// Everything that decides which addresses a search accepts and which salts
+// it tries. The per-device high bytes are left out: they change with the
+// devices and runs, while the salts they fill stay part of the same search
+fn search_of(config: &Config) -> Value {
+    json!({
+        "deployer": format!("0x{}", hex::encode(config.factory_address)),
+        "caller": format!("0x{}", hex::encode(config.calling_address)),
+        "init_code_hash": format!("0x{}", hex::encode(config.init_code_hash)),
+        "starts_with": config.starts_with,
+        "ends_with": config.ends_with,
+        "prefix": config.prefix,
+        "leading_zeroes_threshold": config.leading_zeroes_threshold,
+        "total_zeroes_threshold": config.total_zeroes_threshold,
+        "hook_flags": config.hook_flags,
+        "score": config.score.map(|score| score.to_string()),
+        "salt_scheme": salt_scheme_of(&config.salt_scheme),
+        "address_scheme": address_scheme_of(&config.address_scheme),
+    })
+}
+
+fn salt_scheme_of(salt_scheme: &SaltScheme) -> Value {
+    match salt_scheme {
+        SaltScheme::Nonce => json!({ "kind": "nonce" }),
+        SaltScheme::Caller { caller } => json!({ "kind": "caller", "caller": format!("0x{}", hex::encode(caller)) }),
+        SaltScheme::Head { head } => json!({ "kind": "head", "head": format!("0x{}", hex::encode(head)) }),
+        SaltScheme::Hashed { prefix, head, fixed } => json!({
+            "kind": "hashed",
+            "prefix": format!("0x{}", hex::encode(prefix)),
+            "head": format!("0x{}", hex::encode(head)),
+            "fixed": fixed,
+        }),
+    }
+}
+
+fn address_scheme_of(address_scheme: &AddressScheme) -> Value {
+    match address_scheme {
+        AddressScheme::Create2 => json!({ "kind": "create2" }),
+        AddressScheme::ZkSync { constructor_input_hash } => json!({
+            "kind": "zksync",
+            "constructor_input_hash": format!("0x{}", hex::encode(constructor_input_hash)),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pattern_config;
+    use std::env;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("create2gpu-checkpoint-{}-{}.json", name, process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn solution(address_byte: u8, score: Option<u32>) -> Solution {
+        Solution {
+            device: 0,
+            salt: [address_byte; 32],
+            address: [address_byte; 20],
+            init_code_hash: [0u8; 32],
+            deployer: [0u8; 20],
+            caller: [0u8; 20],
+            attempts: 1,
+            elapsed_seconds: 1.0,
+            score,
+            salt_nonce: None,
+        }
+    }
+
+    #[test]
+    fn missing_file_starts_a_new_checkpoint() {
+        let config = pattern_config("dead", "", None);
+        let checkpoint = Checkpoint::load(&path("missing"), &config).unwrap();
+        assert_eq!(checkpoint.search, search_of(&config));
+        assert_eq!((checkpoint.attempts, checkpoint.elapsed_seconds), (0, 0.0));
+        assert!(checkpoint.best_score.is_none() && checkpoint.best.is_none());
+    }
+
+    #[test]
+    fn checkpoint_of_another_search_is_rejected() {
+        let path = path("other");
+        let mut checkpoint = Checkpoint::new(&pattern_config("dead", "", None));
+        checkpoint.record(100, 2.0, None);
+        checkpoint.save(&path).unwrap();
+
+        assert_eq!(Checkpoint::load(&path, &pattern_config("dead", "", None)).unwrap().attempts, 100);
+        let error = Checkpoint::load(&path, &pattern_config("beef", "", None)).unwrap_err().to_string();
+        assert!(error.contains("is for a different search"), "{}", error);
+        assert!(Checkpoint::load(&path, &pattern_config("dead", "", Some(0x0080))).is_err());
+
+        fs::write(&path, "not json").unwrap();
+        let error = Checkpoint::load(&path, &pattern_config("dead", "", None)).unwrap_err().to_string();
+        assert!(error.starts_with("invalid checkpoint"), "{}", error);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn runs_add_up_and_keep_the_best_score() {
+        let path = path("runs");
+        let config = pattern_config("", "", None);
+        let mut checkpoint = Checkpoint::new(&config);
+        checkpoint.record(1000, 1.5, Some(&solution(1, Some(10))));
+        checkpoint.save(&path).unwrap();
+
+        let mut checkpoint = Checkpoint::load(&path, &config).unwrap();
+        checkpoint.record(500, 0.5, Some(&solution(2, Some(7))));
+        assert_eq!((checkpoint.attempts, checkpoint.elapsed_seconds), (1500, 2.0));
+        assert_eq!(checkpoint.best_score, Some(10));
+        assert_eq!(checkpoint.best.as_ref().unwrap()["address"], format!("0x{}", "01".repeat(20)));
+
+        checkpoint.record(250, 0.25, Some(&solution(3, Some(12))));
+        assert_eq!((checkpoint.attempts, checkpoint.elapsed_seconds), (1750, 2.25));
+        assert_eq!(checkpoint.best_score, Some(12));
+        assert_eq!(checkpoint.best.as_ref().unwrap()["address"], format!("0x{}", "03".repeat(20)));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn first_solution_is_kept_without_a_score() {
+        let mut checkpoint = Checkpoint::new(&pattern_config("dead", "", None));
+        checkpoint.record(10, 0.1, Some(&solution(1, None)));
+        checkpoint.record(10, 0.1, Some(&solution(2, None)));
+        checkpoint.record(10, 0.1, None);
+        assert_eq!(checkpoint.attempts, 30);
+        assert_eq!(checkpoint.best_score, None);
+        assert_eq!(checkpoint.best.as_ref().unwrap()["event"], "solution");
+        assert_eq!(checkpoint.best.as_ref().unwrap()["address"], format!("0x{}", "01".repeat(20)));
+    }
+}
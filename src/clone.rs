@@ -0,0 +1,118 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Minimal proxy layouts deployed by the common clone libraries. Each init
+/// code is a fixed prefix, the implementation address and a fixed suffix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CloneVariant {
+    /// The EIP-1167 minimal proxy deployed by OpenZeppelin
+    /// `Clones.cloneDeterministic` (55-byte init code).
+    #[default]
+    Erc1167,
+    /// The 44-byte runtime minimal proxy by 0age deployed by Solady
+    /// `LibClone.cloneDeterministic` (53-byte init code).
+    Solady,
+    /// The PUSH0 minimal proxy deployed by Solady
+    /// `LibClone.cloneDeterministic_PUSH0` (54-byte init code).
+    Push0,
+    /// The ERC-1967 minimal proxy deployed by Solady
+    /// `LibClone.deployDeterministicERC1967`, which stores the implementation
+    /// in the ERC-1967 slot (95-byte init code).
+    Erc1967,
+}
+
+impl CloneVariant {
+    fn parts(&self) -> (&'static str, &'static str) {
+        match self {
+            CloneVariant::Erc1167 => ("3d602d80600a3d3981f3363d3d373d3d3d363d73", "5af43d82803e903d91602b57fd5bf3"),
+            CloneVariant::Solady => ("602c3d8160093d39f33d3d3d3d363d3d37363d73", "5af43d3d93803e602a57fd5bf3"),
+            CloneVariant::Push0 => ("602d5f8160095f39f35f5f365f5f37365f73", "5af43d5f5f3e6029573d5ffd5b3d5ff3"),
+            CloneVariant::Erc1967 => (
+                "603d3d8160223d3973",
+                "60095155f3363d3d373d3d363d7f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc545af43d6000803e6038573d6000fd5b3d6000f3",
+            ),
+        }
+    }
+
+    /// The init code of a clone of `implementation`.
+    pub fn init_code(&self, implementation: &[u8; 20]) -> Vec<u8> {
+        let (prefix, suffix) = self.parts();
+        let mut code = hex::decode(prefix).unwrap();
+        code.extend_from_slice(implementation);
+        code.extend_from_slice(&hex::decode(suffix).unwrap());
+        code
+    }
+}
+
+impl FromStr for CloneVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "erc1167" => Ok(CloneVariant::Erc1167),
+            "solady" => Ok(CloneVariant::Solady),
+            "push0" => Ok(CloneVariant::Push0),
+            "erc1967" => Ok(CloneVariant::Erc1967),
+            _ => Err(format!("unknown clone variant '{}' (expected erc1167, solady, push0 or erc1967)", s)),
+        }
+    }
+}
+
+impl fmt::Display for CloneVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CloneVariant::Erc1167 => write!(f, "erc1167"),
+            CloneVariant::Solady => write!(f, "solady"),
+            CloneVariant::Push0 => write!(f, "push0"),
+            CloneVariant::Erc1967 => write!(f, "erc1967"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn erc1167_matches_the_eip() {
+        // the runtime code from EIP-1167 for 0xbebe...be behind
+        // OpenZeppelin's creation code
+        assert_eq!(
+            hex::encode(CloneVariant::Erc1167.init_code(&[0xbe; 20])),
+            "3d602d80600a3d3981f3363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3"
+        );
+    }
+
+    #[test]
+    fn creation_code_returns_the_rest_of_the_init_code() {
+        // (init code length, index of the runtime length, runtime offset)
+        for &(variant, len, at, offset) in &[
+            (CloneVariant::Erc1167, 55, 2, 10),
+            (CloneVariant::Solady, 53, 1, 9),
+            (CloneVariant::Push0, 54, 1, 9),
+            (CloneVariant::Erc1967, 95, 1, 34),
+        ] {
+            let code = variant.init_code(&[0xbe; 20]);
+            assert_eq!(code.len(), len, "{}", variant);
+            assert_eq!(code[at] as usize, len - offset, "{}", variant);
+        }
+    }
+
+    #[test]
+    fn erc1967_reads_the_implementation_slot() {
+        // bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)
+        let mut slot = crate::keccak256(b"eip1967.proxy.implementation");
+        assert_eq!(slot[31], 0xbd);
+        slot[31] -= 1;
+        let code = hex::encode(CloneVariant::Erc1967.init_code(&[0xbe; 20]));
+        assert!(code.contains(&format!("7f{}54", hex::encode(slot))));
+    }
+
+    #[test]
+    fn variants_round_trip_through_their_names() {
+        for variant in &[CloneVariant::Erc1167, CloneVariant::Solady, CloneVariant::Push0, CloneVariant::Erc1967] {
+            assert_eq!(variant.to_string().parse::<CloneVariant>(), Ok(*variant));
+        }
+        assert!("minimal".parse::<CloneVariant>().is_err());
+    }
+}
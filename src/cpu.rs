@@ -0,0 +1,260 @@
+use std::error::Error;
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::Config;
+use crate::output::{info, Solution};
+use crate::score::ScoreFunction;
+use crate::identifier::{search_identifiers, IdentifierConfig, IdentifierMatch, IdentifierMiner};
+use crate::search::{
+    matches_criteria, search, search_best, AddressScheme, Hit, Miner, SaltScheme, ScoredHit, Scorer, ShareBatch, ShareMiner,
+};
+use crate::selector::{search_selectors, SelectorConfig, SelectorMiner, SelectorSolution};
+use crate::tuning::{Tuning, WorkSize, CPU_DEVICE_NAME};
+use crate::keccak256;
+
+/// CPU implementation of the CREATE2 address search, for machines without
+/// an OpenCL GPU. Runs until a salt for a matching address is found, or
+/// when scoring until `config.stop` is set.
+pub fn cpu(config: Config) -> Result<Solution, Box<dyn Error>> {
+    info(&config, &format!("Setting up CPU miner using {} threads...", rayon::current_num_threads()));
+
+    let work_size = work_size(config.tuning_file.as_deref())?;
+
+    if let Some(score_function) = config.score {
+        let mut scorer = CpuScorer { miner: CpuMiner::new(&config, work_size)?, score_function };
+        return search_best(&config, &mut scorer, score_function);
+    }
+
+    let mut miner = CpuMiner::new(&config, work_size)?;
+    search(&config, &mut miner)
+}
+
+/// CPU implementation of the function selector search. Returns once
+/// `config.count` signatures have been found.
+pub fn cpu_selectors(config: SelectorConfig) -> Result<Vec<SelectorSolution>, Box<dyn Error>> {
+    config.format.info(&format!("Setting up CPU miner using {} threads...", rayon::current_num_threads()));
+
+    let work_size = work_size(config.tuning_file.as_deref())?;
+    let mut miner = CpuSelectorMiner::new(&config, work_size)?;
+    search_selectors(&config, &mut miner)
+}
+
+/// CPU implementation of the identifier search. Returns the matches once
+/// the search space is exhausted or `config.count` have been found.
+pub fn cpu_identifiers(config: IdentifierConfig) -> Result<Vec<IdentifierMatch>, Box<dyn Error>> {
+    config.format.info(&format!("Setting up CPU miner using {} threads...", rayon::current_num_threads()));
+
+    let work_size = work_size(config.tuning_file.as_deref())?;
+    let mut miner = CpuIdentifierMiner::new(&config, work_size)?;
+    search_identifiers(&config, &mut miner)
+}
+
+/// Set up the CREATE2 address miner on all CPU cores, for callers that
+/// choose the nonces to test themselves, such as a pool worker.
+pub fn cpu_miner(config: &Config) -> Result<CpuMiner, Box<dyn Error>> {
+    info(config, &format!("Setting up CPU miner using {} threads...", rayon::current_num_threads()));
+    CpuMiner::new(config, work_size(config.tuning_file.as_deref())?)
+}
+
+/// Set up a miner on all CPU cores that also collects the near-miss shares
+/// of `share_config`, for a pool worker.
+pub fn cpu_share_miner(config: &Config, share_config: &Config) -> Result<CpuShareMiner, Box<dyn Error>> {
+    Ok(CpuShareMiner { miner: cpu_miner(config)?, share_config: share_config.clone() })
+}
+
+/// The benchmarked CPU launch settings, or the defaults.
+fn work_size(tuning_file: Option<&Path>) -> Result<WorkSize, Box<dyn Error>> {
+    let tuning = match tuning_file {
+        Some(path) => Tuning::load(path)?,
+        None => Tuning::default(),
+    };
+    Ok(tuning.get(CPU_DEVICE_NAME).unwrap_or_else(WorkSize::cpu_default))
+}
+
+/// The Keccak-256 digest of each of `messages`, computed on all CPU cores.
+pub fn keccak256_batch_cpu<const N: usize>(messages: &[[u8; N]]) -> Vec<[u8; 32]> {
+    messages.par_iter().map(|message| keccak256(message)).collect()
+}
+
+/// Tests nonces on all CPU cores with the same salt layout as the kernel.
+pub struct CpuMiner {
+    config: Config,
+    work_size: WorkSize,
+    /// `0xff ++ deployer ++ salt ++ init code hash`, with the salt left
+    /// zero.
+    preimage: [u8; 85],
+}
+
+impl CpuMiner {
+    pub fn new(config: &Config, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+
+        let mut preimage = [0u8; 85];
+        preimage[0] = 0xff;
+        preimage[1..21].copy_from_slice(&config.factory_address);
+        preimage[53..85].copy_from_slice(&config.init_code_hash);
+
+        Ok(CpuMiner { config: config.clone(), work_size, preimage })
+    }
+
+    fn address(&self, nonce: u64) -> [u8; 20] {
+        if self.config.address_scheme != AddressScheme::Create2 {
+            let salt = self.config.salt_scheme.salt(nonce);
+            return self.config.address_scheme.address(&self.config.factory_address, &salt, &self.config.init_code_hash);
+        }
+
+        let mut preimage = self.preimage;
+        match self.config.salt_scheme {
+            SaltScheme::Nonce => preimage[45..53].copy_from_slice(&nonce.to_le_bytes()),
+            SaltScheme::Head { ref head } => {
+                preimage[21..45].copy_from_slice(head);
+                preimage[45..53].copy_from_slice(&nonce.to_le_bytes());
+            }
+            ref scheme => preimage[21..53].copy_from_slice(&scheme.salt(nonce)),
+        }
+
+        let hash = keccak256(&preimage);
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..32]);
+        address
+    }
+}
+
+impl Miner for CpuMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine(&mut self, base: u64) -> Result<Option<Hit>, Box<dyn Error>> {
+        let hit = (0..self.batch_size()).into_par_iter().find_map_any(|offset| {
+            let nonce = base + offset;
+            let address = self.address(nonce);
+            if matches_criteria(&self.config, &address) {
+                Some(Hit { nonce, address })
+            } else {
+                None
+            }
+        });
+
+        Ok(hit)
+    }
+}
+
+/// Tests nonces on all CPU cores for matches and near-miss shares.
+pub struct CpuShareMiner {
+    pub miner: CpuMiner,
+    pub share_config: Config,
+}
+
+impl ShareMiner for CpuShareMiner {
+    fn batch_size(&self) -> u64 {
+        self.miner.batch_size()
+    }
+
+    fn mine_shares(&mut self, base: u64) -> Result<ShareBatch, Box<dyn Error>> {
+        let mut shares: Vec<(u64, bool)> = (0..self.batch_size())
+            .into_par_iter()
+            .filter_map(|offset| {
+                let nonce = base + offset;
+                let address = self.miner.address(nonce);
+                if matches_criteria(&self.share_config, &address) {
+                    Some((nonce, matches_criteria(&self.miner.config, &address)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        shares.sort_unstable();
+
+        Ok(ShareBatch {
+            hit: shares.iter().find(|&&(_, matched)| matched).map(|&(nonce, _)| nonce),
+            shares: shares.into_iter().map(|(nonce, _)| nonce).collect(),
+        })
+    }
+}
+
+/// Scores nonces on all CPU cores.
+pub struct CpuScorer {
+    pub miner: CpuMiner,
+    pub score_function: ScoreFunction,
+}
+
+impl Scorer for CpuScorer {
+    fn batch_size(&self) -> u64 {
+        self.miner.batch_size()
+    }
+
+    fn best(&mut self, base: u64, threshold: u32) -> Result<Option<ScoredHit>, Box<dyn Error>> {
+        let best = (0..self.batch_size())
+            .into_par_iter()
+            .map(|offset| {
+                let nonce = base + offset;
+                (self.score_function.score(&self.miner.address(nonce)), nonce)
+            })
+            .filter(|&(score, _)| score > threshold)
+            .max();
+
+        Ok(best.map(|(score, nonce)| ScoredHit { nonce, score }))
+    }
+}
+
+/// Tests function selector suffixes on all CPU cores.
+pub struct CpuSelectorMiner {
+    config: SelectorConfig,
+    work_size: WorkSize,
+}
+
+impl CpuSelectorMiner {
+    pub fn new(config: &SelectorConfig, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+        Ok(CpuSelectorMiner { config: config.clone(), work_size })
+    }
+}
+
+impl SelectorMiner for CpuSelectorMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine(&mut self, base: u64) -> Result<Option<u64>, Box<dyn Error>> {
+        let hit = (0..self.batch_size())
+            .into_par_iter()
+            .map(|offset| base + offset)
+            .filter(|&nonce| self.config.matches(&self.config.selector(nonce)))
+            .min();
+
+        Ok(hit)
+    }
+}
+
+/// Tests identifiers on all CPU cores.
+pub struct CpuIdentifierMiner {
+    config: IdentifierConfig,
+    work_size: WorkSize,
+}
+
+impl CpuIdentifierMiner {
+    pub fn new(config: &IdentifierConfig, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+        Ok(CpuIdentifierMiner { config: config.clone(), work_size })
+    }
+}
+
+impl IdentifierMiner for CpuIdentifierMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine(&mut self, base: u64) -> Result<Vec<u64>, Box<dyn Error>> {
+        let end = base.saturating_add(self.batch_size()).min(self.config.search_space());
+        let mut hits: Vec<u64> = (base..end)
+            .into_par_iter()
+            .filter(|&nonce| self.config.matched_target(&self.config.hash(nonce)).is_some())
+            .collect();
+        hits.sort_unstable();
+
+        Ok(hits)
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, VecDeque};
+#[cfg(unix)]
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::sync::{Arc, Mutex};
+#[cfg(unix)]
+use std::thread;
+use std::thread::JoinHandle;
+
+use console::Term;
+use separator::Separatable;
+use terminal_size::{terminal_size, Width};
+
+use crate::difficulty::{format_duration, Difficulty};
+use crate::output::{describe_criteria, to_checksum_address, Progress, Solution};
+use crate::Config;
+
+/// Rate samples kept for each device's sparkline.
+const HISTORY_LEN: usize = 120;
+
+/// Solutions listed at the bottom of the dashboard, newest last.
+const FOUND_LINES: usize = 5;
+
+/// Width assumed when the terminal does not report one.
+const DEFAULT_WIDTH: usize = 100;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The terminal settings before `watch_keys` changed them.
+#[cfg(unix)]
+static ORIGINAL_TERMINAL: OnceLock<libc::termios> = OnceLock::new();
+
+/// The latest status of one device.
+#[derive(Clone, Debug, Default)]
+struct DeviceRow {
+    elapsed_seconds: f64,
+    attempts: u64,
+    /// Attempts per second since the previous update.
+    rate: f64,
+    search_space: u32,
+    history: VecDeque<f64>,
+}
+
+#[derive(Default)]
+struct DashboardState {
+    devices: BTreeMap<u32, DeviceRow>,
+    difficulty: Option<Difficulty>,
+    /// The best-scoring address, or the matching one, found so far.
+    best: Option<Solution>,
+    found: Vec<Solution>,
+    /// Whether stdout is a terminal, once the first update has checked.
+    terminal: Option<bool>,
+    /// Whether keys are being read.
+    keys: bool,
+    /// Lines of the dashboard on screen, to clear before redrawing it.
+    drawn_lines: usize,
+}
+
+/// The status screen of a text-mode search, shared by all devices.
+///
+/// On a terminal it redraws a block at the bottom of the output with a rate
+/// table, a sparkline of each device's rate, the expected time to solution,
+/// the best address so far and the solutions found, fitted to the terminal
+/// width. Solutions and improvements are printed above the block, so
+/// scrollback is kept. When stdout is not a terminal, each update is one
+/// plain line instead.
+#[derive(Default)]
+pub struct Dashboard {
+    state: Mutex<DashboardState>,
+}
+
+impl Dashboard {
+    /// Record a progress update from a device and redraw.
+    pub fn progress(&self, config: &Config, p: &Progress) {
+        let mut state = self.state.lock().unwrap();
+        state.difficulty = Some(p.difficulty);
+        if let Some(ref best) = p.best {
+            if state.best.as_ref().is_none_or(|current| current.score < best.score) {
+                state.best = Some(best.clone());
+            }
+        }
+
+        let row = state.devices.entry(p.device).or_default();
+        let seconds = p.elapsed_seconds - row.elapsed_seconds;
+        row.rate = if seconds > 0.0 { p.attempts.saturating_sub(row.attempts) as f64 / seconds } else { p.rate() };
+        row.elapsed_seconds = p.elapsed_seconds;
+        row.attempts = p.attempts;
+        row.search_space = p.search_space;
+        if row.history.len() == HISTORY_LEN {
+            row.history.pop_front();
+        }
+        row.history.push_back(row.rate);
+
+        if *state.terminal.get_or_insert_with(|| Term::stdout().is_term()) {
+            draw(&mut state, config);
+        } else {
+            println!(
+                "device {}: {} elapsed, {} attempts, {:.2} million attempts per second{}",
+                p.device,
+                format_duration(p.elapsed_seconds),
+                p.attempts.separated_string(),
+                p.rate() / 1_000_000.0,
+                match config.score {
+                    Some(_) => String::new(),
+                    None => format!(", expected time to solution: {}", format_duration(p.expected_seconds())),
+                }
+            );
+        }
+    }
+
+    /// Run `print` with the dashboard out of the way, so that its output
+    /// ends up above it.
+    pub fn print_above<F: FnOnce()>(&self, config: &Config, print: F) {
+        let mut state = self.state.lock().unwrap();
+        let drawn = state.drawn_lines;
+        clear(&mut state);
+        print();
+        if drawn > 0 {
+            draw(&mut state, config);
+        }
+    }
+
+    /// Record a solution or a new best score, printed by `print` above the
+    /// dashboard.
+    pub fn found<F: FnOnce()>(&self, config: &Config, solution: &Solution, print: F) {
+        let mut state = self.state.lock().unwrap();
+        let drawn = state.drawn_lines;
+        clear(&mut state);
+        print();
+
+        if state.best.as_ref().is_none_or(|best| best.score < solution.score) {
+            state.best = Some(solution.clone());
+        }
+        // a stopped scoring search reports its last improvement once more
+        if !state.found.iter().any(|found| found.salt == solution.salt) {
+            state.found.push(solution.clone());
+        }
+        if drawn > 0 {
+            draw(&mut state, config);
+        }
+    }
+
+    /// Redraw the dashboard if it is on screen, after the search was paused,
+    /// resumed or stopped.
+    pub fn redraw(&self, config: &Config) {
+        let mut state = self.state.lock().unwrap();
+        if state.drawn_lines > 0 {
+            draw(&mut state, config);
+        }
+    }
+}
+
+// Erase the dashboard, leaving the cursor where it started
+fn clear(state: &mut DashboardState) {
+    if state.drawn_lines > 0 {
+        let _ = Term::stdout().clear_last_lines(state.drawn_lines);
+        state.drawn_lines = 0;
+    }
+}
+
+fn draw(state: &mut DashboardState, config: &Config) {
+    let width = match terminal_size() {
+        Some((Width(width), _)) => width as usize,
+        None => DEFAULT_WIDTH,
+    };
+    let lines = render(state, config, width);
+
+    clear(state);
+    let term = Term::stdout();
+    for line in &lines {
+        let _ = term.write_line(&fit(line, width));
+    }
+    state.drawn_lines = lines.len();
+}
+
+fn render(state: &DashboardState, config: &Config, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let status = if config.stop.load(Ordering::SeqCst) {
+        "stopping"
+    } else if config.paused.load(Ordering::SeqCst) {
+        "paused"
+    } else {
+        "running"
+    };
+    let keys = if state.keys { "  (p pause, r resume, q quit)" } else { "" };
+    let goal = match config.score {
+        Some(score_function) => format!("scoring with {}", score_function),
+        None => format!("searching for {}", describe_criteria(config)),
+    };
+    lines.push(format!("----- create2gpu: {} [{}]{} -----", goal, status, keys));
+
+    let elapsed = state.devices.values().map(|row| row.elapsed_seconds).fold(0.0, f64::max);
+    let attempts: u64 = state.devices.values().map(|row| row.attempts).sum();
+    let rate: f64 = state.devices.values().map(|row| row.rate).sum();
+    let mut summary = format!(
+        "elapsed {}   attempts {}   rate {:.2} million per second",
+        format_duration(elapsed),
+        attempts.separated_string(),
+        rate / 1_000_000.0
+    );
+    if let (None, Some(difficulty)) = (config.score, state.difficulty) {
+        summary.push_str(&format!(
+            "   expected time to solution {}   found by now {:.2}%",
+            format_duration(difficulty.expected_seconds(rate)),
+            difficulty.probability_found(attempts) * 100.0
+        ));
+    }
+    lines.push(summary);
+
+    lines.push(format!("{:>6}  {:>10}  {:>18}  {:<16}  rate history", "device", "M/s", "attempts", "nonces"));
+    let history_width = width.saturating_sub(60).max(8);
+    for (device, row) in &state.devices {
+        lines.push(format!(
+            "{:>6}  {:>10.2}  {:>18}  {:08x}xxxxxxxx  {}",
+            device,
+            row.rate / 1_000_000.0,
+            row.attempts.separated_string(),
+            row.search_space,
+            sparkline(&row.history, history_width)
+        ));
+    }
+
+    lines.push(match state.best {
+        Some(ref best) => format!(
+            "best so far: {}{}  salt 0x{}",
+            best.score.map(|score| format!("score {}  ", score)).unwrap_or_default(),
+            to_checksum_address(&hex::encode(best.address)),
+            hex::encode(best.salt)
+        ),
+        // pattern searches only hear back from a device on a full match
+        None if config.score.is_none() => "best so far: no match yet".to_string(),
+        None => "best so far: none yet".to_string(),
+    });
+
+    lines.push(format!("found: {}", state.found.len()));
+    for solution in state.found.iter().rev().take(FOUND_LINES).rev() {
+        lines.push(format!(
+            "  {:>10}  {}{}  salt 0x{}",
+            format_duration(solution.elapsed_seconds),
+            solution.score.map(|score| format!("score {}  ", score)).unwrap_or_default(),
+            to_checksum_address(&hex::encode(solution.address)),
+            hex::encode(solution.salt)
+        ));
+    }
+
+    lines
+}
+
+// The last `width` samples as a sparkline, scaled to their maximum
+fn sparkline(history: &VecDeque<f64>, width: usize) -> String {
+    let samples: Vec<f64> = history.iter().skip(history.len().saturating_sub(width)).cloned().collect();
+    let max = samples.iter().cloned().fold(0.0, f64::max);
+    samples
+        .iter()
+        .map(|&sample| {
+            let level = if max > 0.0 { (sample / max * (SPARKS.len() - 1) as f64).round() as usize } else { 0 };
+            SPARKS[level.min(SPARKS.len() - 1)]
+        })
+        .collect()
+}
+
+// Cut a line to the terminal width, so that none wraps and clearing the
+// dashboard removes exactly the lines it drew
+fn fit(line: &str, width: usize) -> String {
+    line.chars().take(width.saturating_sub(1)).collect()
+}
+
+/// Reads single keys from the terminal while a search runs: `p` pauses the
+/// devices between batches, `r` resumes them and `q` stops the search.
+/// The terminal is put back as it was when this is dropped.
+pub struct KeyReader {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Start reading keys for the search in `config`, if stdin is a terminal.
+#[cfg(unix)]
+pub fn watch_keys(config: &Config) -> Option<KeyReader> {
+    let fd = libc::STDIN_FILENO;
+    let mut original: libc::termios = unsafe { mem::zeroed() };
+    if unsafe { libc::isatty(fd) } != 1 || unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return None;
+    }
+
+    // no line buffering or echo, and reads that give up after 0.1 seconds
+    // so the thread notices when it is done; output and Ctrl-C are left as
+    // they are
+    let mut keys = original;
+    keys.c_lflag &= !(libc::ICANON | libc::ECHO);
+    keys.c_cc[libc::VMIN] = 0;
+    keys.c_cc[libc::VTIME] = 1;
+    ORIGINAL_TERMINAL.get_or_init(|| original);
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &keys) } != 0 {
+        return None;
+    }
+    config.dashboard.state.lock().unwrap().keys = true;
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reading = done.clone();
+    let config = config.clone();
+    let thread = thread::spawn(move || {
+        while !reading.load(Ordering::SeqCst) {
+            let mut key = 0u8;
+            let read = unsafe { libc::read(fd, &mut key as *mut u8 as *mut libc::c_void, 1) };
+            if read < 0 {
+                return;
+            }
+            if read == 0 {
+                continue;
+            }
+            match key {
+                b'p' | b'P' => config.paused.store(true, Ordering::SeqCst),
+                b'r' | b'R' => config.paused.store(false, Ordering::SeqCst),
+                b'q' | b'Q' => config.stop.store(true, Ordering::SeqCst),
+                _ => continue,
+            }
+            config.dashboard.redraw(&config);
+        }
+    });
+
+    Some(KeyReader { done, thread: Some(thread) })
+}
+
+/// Keys are only read on Unix terminals; elsewhere the dashboard runs
+/// without them.
+#[cfg(not(unix))]
+pub fn watch_keys(_config: &Config) -> Option<KeyReader> {
+    None
+}
+
+/// Put the terminal back as it was before `watch_keys`, if it changed it.
+/// Safe to call from a signal handler.
+#[cfg(unix)]
+pub fn restore_terminal() {
+    if let Some(original) = ORIGINAL_TERMINAL.get() {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn restore_terminal() {}
+
+impl Drop for KeyReader {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        restore_terminal();
+    }
+}
@@ -0,0 +1,94 @@
+use crate::output::to_checksum_address;
+
+/// The transaction that deploys a mined address: a call to the factory.
+#[derive(Clone, Debug)]
+pub struct Deployment {
+    /// What deploys the contract, such as "createx" or "Safe proxy factory".
+    pub via: String,
+    /// The factory to call.
+    pub to: [u8; 20],
+    pub calldata: Vec<u8>,
+    /// The address the call deploys to.
+    pub address: [u8; 20],
+}
+
+impl Deployment {
+    /// A `cast send` command line that sends the deployment. Cast takes the
+    /// RPC URL and signer from its usual options or environment.
+    pub fn cast_command(&self) -> String {
+        format!("cast send {} 0x{}", to_checksum_address(&hex::encode(self.to)), hex::encode(&self.calldata))
+    }
+
+    /// A Foundry script that broadcasts the deployment and checks that code
+    /// landed at the mined address, to run with `forge script --broadcast`.
+    pub fn forge_script(&self) -> String {
+        format!(
+            "// SPDX-License-Identifier: UNLICENSED\n\
+             pragma solidity ^0.8.13;\n\
+             \n\
+             import {{Script}} from \"forge-std/Script.sol\";\n\
+             \n\
+             /// Deploys {address} through {via}, with the salt found by create2gpu.\n\
+             contract DeployCreate2 is Script {{\n\
+             \x20   address constant FACTORY = {to};\n\
+             \x20   address constant EXPECTED = {address};\n\
+             \n\
+             \x20   function run() external {{\n\
+             \x20       bytes memory data = hex\"{calldata}\";\n\
+             \n\
+             \x20       vm.startBroadcast();\n\
+             \x20       (bool success,) = FACTORY.call(data);\n\
+             \x20       vm.stopBroadcast();\n\
+             \n\
+             \x20       require(success, \"factory call reverted\");\n\
+             \x20       require(EXPECTED.code.length > 0, \"nothing deployed at the expected address\");\n\
+             \x20   }}\n\
+             }}\n",
+            address = to_checksum_address(&hex::encode(self.address)),
+            via = self.via,
+            to = to_checksum_address(&hex::encode(self.to)),
+            calldata = hex::encode(&self.calldata),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factory::Factory;
+
+    fn deployment() -> Deployment {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hex::decode("b4e16d0168e52d35cacd2c6185b44281ec28c9dc").unwrap());
+        Deployment {
+            via: "createx".to_string(),
+            to: Factory::CreateX.address(),
+            calldata: Factory::CreateX.calldata(&[0x33; 32], &[0xc0, 0xde]),
+            address,
+        }
+    }
+
+    #[test]
+    fn cast_command_calls_the_factory() {
+        assert_eq!(
+            deployment().cast_command(),
+            format!(
+                "cast send 0xba5Ed099633D3B313e4D5F7bdc1305d3c28ba5Ed 0x26307668{}{:064x}{:064x}{:0<64}",
+                "33".repeat(32),
+                0x40,
+                2,
+                "c0de"
+            )
+        );
+    }
+
+    #[test]
+    fn forge_script_calls_the_factory_and_checks_the_address() {
+        let script = deployment().forge_script();
+        assert!(script.contains("/// Deploys 0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc through createx,"));
+        assert!(script.contains("address constant FACTORY = 0xba5Ed099633D3B313e4D5F7bdc1305d3c28ba5Ed;\n"));
+        assert!(script.contains("address constant EXPECTED = 0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc;\n"));
+        assert!(script.contains(&format!("bytes memory data = hex\"{}\";\n", hex::encode(&deployment().calldata))));
+        assert!(script.contains("    function run() external {\n        bytes memory data"));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::Config;
+use crate::hooks::hook_tail;
+
+/// Patterns fixing more bits than this are reported as infeasible for a
+/// single device (14 hex digits).
+pub const MAX_FEASIBLE_BITS: u32 = 56;
+
+/// How hard the configured search criteria are to satisfy, assuming each
+/// digest bit is uniformly random.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    /// Address bits that must take a fixed value.
+    pub fixed_bits: u32,
+}
+
+impl Difficulty {
+    /// Compute the difficulty of the criteria in `config`.
+    pub fn of(config: &Config) -> Self {
+        let mask = address_mask(config);
+        Difficulty { fixed_bits: mask.iter().map(|b| b.count_ones()).sum() }
+    }
+
+    /// Total number of bits an address must match.
+    pub fn bits(&self) -> u32 {
+        self.fixed_bits
+    }
+
+    /// Chance that a single attempt matches.
+    pub fn probability_per_attempt(&self) -> f64 {
+        0.5f64.powi(self.bits() as i32)
+    }
+
+    /// Mean number of attempts until the first match.
+    pub fn expected_attempts(&self) -> f64 {
+        2f64.powi(self.bits() as i32)
+    }
+
+    /// Mean time to the first match at `rate` attempts per second. Because
+    /// attempts are independent, this is also the expected time remaining at
+    /// any point of the search.
+    pub fn expected_seconds(&self, rate: f64) -> f64 {
+        if rate > 0.0 {
+            self.expected_attempts() / rate
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Probability of having found at least one match after `attempts`.
+    pub fn probability_found(&self, attempts: u64) -> f64 {
+        let p = self.probability_per_attempt();
+        -(attempts as f64 * (-p).ln_1p()).exp_m1()
+    }
+
+    /// Whether the pattern is beyond what one device can be expected to find.
+    pub fn is_feasible(&self) -> bool {
+        self.bits() <= MAX_FEASIBLE_BITS
+    }
+
+    /// A one-line description of the difficulty for display before a search.
+    pub fn describe(&self) -> String {
+        format!(
+            "Difficulty: {} fixed bits ({:.1} hex digits), ~{:.3e} expected attempts",
+            self.bits(),
+            self.bits() as f64 / 4.0,
+            self.expected_attempts()
+        )
+    }
+
+    /// A warning to show before the search if the pattern is infeasible.
+    pub fn warning(&self) -> Option<String> {
+        if self.is_feasible() {
+            None
+        } else {
+            Some(format!(
+                "Warning: the pattern fixes {} bits, more than the {} bits ({} hex digits) one device can be expected to find; \
+                 at 1 billion attempts per second the expected time is {}",
+                self.bits(),
+                MAX_FEASIBLE_BITS,
+                MAX_FEASIBLE_BITS / 4,
+                format_duration(self.expected_seconds(1e9))
+            ))
+        }
+    }
+}
+
+/// The bits of the address fixed by the prefix and suffix, nibble by nibble,
+/// and by the hook flags.
+pub fn address_mask(config: &Config) -> [u8; 20] {
+    let mut mask = [0u8; 20];
+
+    let mut set_nibble = |position: usize| {
+        mask[position / 2] |= if position.is_multiple_of(2) { 0xf0 } else { 0x0f };
+    };
+
+    for position in 0..config.starts_with.len().min(40) {
+        set_nibble(position);
+    }
+    for position in 40 - config.ends_with.len().min(40)..40 {
+        set_nibble(position);
+    }
+    if let Some(flags) = config.hook_flags {
+        let (tail_mask, _) = hook_tail(flags);
+        mask[18] |= tail_mask[0];
+        mask[19] |= tail_mask[1];
+    }
+
+    mask
+}
+
+/// Format a duration in seconds with a unit that suits its magnitude.
+pub fn format_duration(seconds: f64) -> String {
+    const MINUTE: f64 = 60.0;
+    const HOUR: f64 = 60.0 * MINUTE;
+    const DAY: f64 = 24.0 * HOUR;
+    const YEAR: f64 = 365.25 * DAY;
+
+    if !seconds.is_finite() {
+        String::from("forever")
+    } else if seconds < MINUTE {
+        format!("{:.1} seconds", seconds)
+    } else if seconds < HOUR {
+        format!("{:.1} minutes", seconds / MINUTE)
+    } else if seconds < DAY {
+        format!("{:.1} hours", seconds / HOUR)
+    } else if seconds < YEAR {
+        format!("{:.1} days", seconds / DAY)
+    } else if seconds < 1000.0 * YEAR {
+        format!("{:.1} years", seconds / YEAR)
+    } else {
+        format!("{:.2e} years", seconds / YEAR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing::pattern_config;
+
+    #[test]
+    fn fixed_bits_of_patterns_and_flags() {
+        assert_eq!(Difficulty::of(&pattern_config("", "", None)).bits(), 0);
+        assert_eq!(Difficulty::of(&pattern_config("dead", "beef", None)).bits(), 32);
+
+        // a prefix and suffix that overlap fix each digit once
+        assert_eq!(Difficulty::of(&pattern_config(&"a".repeat(38), "aaaa", None)).bits(), 160);
+
+        // the 14 hook flag bits, some of them already fixed by the suffix
+        assert_eq!(Difficulty::of(&pattern_config("00", "", Some(0x0044))).bits(), 8 + 14);
+        assert_eq!(Difficulty::of(&pattern_config("", "44", Some(0x0044))).bits(), 14);
+
+        // the zero thresholds are not matched against, so they fix nothing
+        let mut config = pattern_config("dead", "", None);
+        config.leading_zeroes_threshold = 4;
+        config.total_zeroes_threshold = 8;
+        assert_eq!(Difficulty::of(&config).bits(), 16);
+    }
+
+    #[test]
+    fn address_mask_covers_pattern_nibbles_and_flag_bits() {
+        let mut expected = [0u8; 20];
+        expected[0] = 0xff;
+        expected[1] = 0xf0;
+        expected[19] = 0x0f;
+        assert_eq!(address_mask(&pattern_config("abc", "1", None)), expected);
+
+        expected[18] = 0x3f;
+        expected[19] = 0xff;
+        assert_eq!(address_mask(&pattern_config("abc", "1", Some(0))), expected);
+    }
+
+    #[test]
+    fn probability_found_grows_to_one() {
+        let difficulty = Difficulty::of(&pattern_config("dead", "", None));
+        assert_eq!(difficulty.expected_attempts(), 65536.0);
+        assert_eq!(difficulty.probability_found(0), 0.0);
+        // 1 - 1/e after the expected number of attempts
+        assert!((difficulty.probability_found(65536) - 0.632).abs() < 0.001);
+        assert!(difficulty.probability_found(65536 * 20) > 0.999_999);
+        assert_eq!(difficulty.expected_seconds(0.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn durations_use_a_fitting_unit() {
+        assert_eq!(format_duration(30.0), "30.0 seconds");
+        assert_eq!(format_duration(90.0), "1.5 minutes");
+        assert_eq!(format_duration(5400.0), "1.5 hours");
+        assert_eq!(format_duration(1.5 * 86400.0), "1.5 days");
+        assert_eq!(format_duration(2.0 * 365.25 * 86400.0), "2.0 years");
+        assert_eq!(format_duration(2e6 * 365.25 * 86400.0), "2.00e6 years");
+        assert_eq!(format_duration(f64::INFINITY), "forever");
+    }
+
+    #[test]
+    fn patterns_over_14_digits_are_infeasible() {
+        let feasible = Difficulty::of(&pattern_config(&"0".repeat(14), "", None));
+        assert!(feasible.is_feasible());
+        assert_eq!(feasible.warning(), None);
+
+        let infeasible = Difficulty::of(&pattern_config(&"0".repeat(14), "0", None));
+        assert!(!infeasible.is_feasible());
+        let warning = infeasible.warning().unwrap();
+        assert!(warning.starts_with("Warning: the pattern fixes 60 bits, more than the 56 bits (14 hex digits)"), "{}", warning);
+        assert!(warning.ends_with("the expected time is 36.5 years"), "{}", warning);
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::abi::{address_word, encode_call, Token};
+use crate::keccak256;
+use crate::search::SaltScheme;
+
+/// Widely deployed CREATE2 factories, at the same address on most chains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Factory {
+    /// 0age's `ImmutableCreate2Factory.safeCreate2(salt, initCode)`. The
+    /// first 20 bytes of the salt must be the caller or zero.
+    ImmutableCreate2,
+    /// Arachnid's deterministic deployment proxy, called with
+    /// `salt ++ initCode` as raw calldata.
+    Arachnid,
+    /// `CreateX.deployCreate2(salt, initCode)`, which hashes the salt before
+    /// using it. A salt starting with the caller and a zero byte can only be
+    /// used by that caller.
+    CreateX,
+    /// The Safe singleton factory, called like Arachnid's proxy.
+    SafeSingleton,
+    /// pcaversaccio's `Create2Deployer.deploy(value, salt, code)`.
+    Create2Deployer,
+}
+
+impl Factory {
+    /// The address the factory is deployed at.
+    pub fn address(&self) -> [u8; 20] {
+        let address = match self {
+            Factory::ImmutableCreate2 => "0000000000ffe8b47b3e2130213b802212439497",
+            Factory::Arachnid => "4e59b44847b379578588920ca78fbf26c0b4956c",
+            Factory::CreateX => "ba5ed099633d3b313e4d5f7bdc1305d3c28ba5ed",
+            Factory::SafeSingleton => "914d7fec6aac8cd542e72bca78b30650d45643d7",
+            Factory::Create2Deployer => "13b0d85ccb8bf860b6b79af3029fca081ae9bef2",
+        };
+        let mut bytes = [0u8; 20];
+        bytes.copy_from_slice(&hex::decode(address).unwrap());
+        bytes
+    }
+
+    /// The salts to mine for `caller`: salts only `caller` can use where the
+    /// factory supports that, otherwise salts anyone can use.
+    pub fn salt_scheme(&self, caller: &[u8; 20]) -> SaltScheme {
+        let anyone = caller.iter().all(|&b| b == 0);
+        match self {
+            Factory::ImmutableCreate2 => SaltScheme::Caller { caller: *caller },
+            // the salt's first 20 bytes select the caller protection and
+            // byte 20 the cross-chain protection
+            Factory::CreateX if anyone => SaltScheme::Hashed { prefix: Vec::new(), head: [0u8; 24], fixed: 21 },
+            Factory::CreateX => {
+                let mut head = [0u8; 24];
+                head[..20].copy_from_slice(caller);
+                SaltScheme::Hashed { prefix: address_word(caller).to_vec(), head, fixed: 21 }
+            }
+            Factory::Arachnid | Factory::SafeSingleton | Factory::Create2Deployer => SaltScheme::Nonce,
+        }
+    }
+
+    /// The CREATE2 salt the factory uses for a salt passed to it by
+    /// `caller`, or an error if the factory would reject the salt. Without a
+    /// caller, the salt is assumed to be passed by the address in its first
+    /// 20 bytes.
+    pub fn create2_salt(&self, salt: &[u8; 32], caller: Option<&[u8; 20]>) -> Result<[u8; 32], String> {
+        let mut salt_caller = [0u8; 20];
+        salt_caller.copy_from_slice(&salt[..20]);
+        let caller = caller.unwrap_or(&salt_caller);
+        let zero = salt_caller.iter().all(|&b| b == 0);
+
+        match self {
+            Factory::ImmutableCreate2 if !zero && salt_caller != *caller => Err(format!(
+                "ImmutableCreate2Factory salts must start with the caller 0x{} or 20 zero bytes",
+                hex::encode(caller)
+            )),
+            // CreateX `_guard`: a salt starting with the caller is
+            // permissioned, byte 20 selects cross-chain redeploy protection
+            Factory::CreateX if salt[20] == 1 && (zero || salt_caller == *caller) => {
+                Err("CreateX salts with cross-chain redeploy protection depend on the chain id".to_string())
+            }
+            Factory::CreateX if salt[20] != 0 && (zero || salt_caller == *caller) => {
+                Err("CreateX rejects salts whose byte 20 is neither 0x00 nor 0x01".to_string())
+            }
+            Factory::CreateX if !zero && salt_caller == *caller => {
+                let mut preimage = address_word(caller).to_vec();
+                preimage.extend_from_slice(salt);
+                Ok(keccak256(&preimage))
+            }
+            Factory::CreateX => Ok(keccak256(salt)),
+            _ => Ok(*salt),
+        }
+    }
+
+    /// The calldata that deploys `init_code` with `salt` through the factory.
+    pub fn calldata(&self, salt: &[u8; 32], init_code: &[u8]) -> Vec<u8> {
+        let salt_and_code = [Token::FixedBytes(salt.to_vec()), Token::Bytes(init_code.to_vec())];
+        match self {
+            Factory::ImmutableCreate2 => encode_call("safeCreate2(bytes32,bytes)", &salt_and_code),
+            Factory::CreateX => encode_call("deployCreate2(bytes32,bytes)", &salt_and_code),
+            Factory::Create2Deployer => encode_call(
+                "deploy(uint256,bytes32,bytes)",
+                &[Token::Uint([0u8; 32]), Token::FixedBytes(salt.to_vec()), Token::Bytes(init_code.to_vec())],
+            ),
+            Factory::Arachnid | Factory::SafeSingleton => {
+                let mut calldata = salt.to_vec();
+                calldata.extend_from_slice(init_code);
+                calldata
+            }
+        }
+    }
+}
+
+impl FromStr for Factory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immutable-create2" => Ok(Factory::ImmutableCreate2),
+            "arachnid" => Ok(Factory::Arachnid),
+            "createx" => Ok(Factory::CreateX),
+            "safe-singleton" => Ok(Factory::SafeSingleton),
+            "create2-deployer" => Ok(Factory::Create2Deployer),
+            _ => Err(format!(
+                "unknown factory '{}' (expected immutable-create2, arachnid, createx, safe-singleton or create2-deployer)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Factory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Factory::ImmutableCreate2 => write!(f, "immutable-create2"),
+            Factory::Arachnid => write!(f, "arachnid"),
+            Factory::CreateX => write!(f, "createx"),
+            Factory::SafeSingleton => write!(f, "safe-singleton"),
+            Factory::Create2Deployer => write!(f, "create2-deployer"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create2_address;
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&hex::decode(hex).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn create2_addresses_match_known_deployments() {
+        // examples from EIP-1014
+        let zero = [0u8; 32];
+        assert_eq!(
+            create2_address(&[0u8; 20], &zero, &keccak256(&[0x00])),
+            bytes("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38")
+        );
+        assert_eq!(
+            create2_address(
+                &bytes("00000000000000000000000000000000deadbeef"),
+                &bytes("00000000000000000000000000000000000000000000000000000000cafebabe"),
+                &keccak256(&[0xde, 0xad, 0xbe, 0xef])
+            ),
+            bytes("60f3f640a8508fc6a86d45df051962668e1e8ac7")
+        );
+        assert_eq!(create2_address(&[0u8; 20], &zero, &keccak256(&[])), bytes("e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"));
+
+        // the Uniswap V2 USDC/WETH pair on mainnet
+        let mut tokens = hex::decode("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        tokens.extend_from_slice(&hex::decode("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap());
+        assert_eq!(
+            create2_address(
+                &bytes("5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f"),
+                &keccak256(&tokens),
+                &bytes("96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f")
+            ),
+            bytes("b4e16d0168e52d35cacd2c6185b44281ec28c9dc")
+        );
+    }
+
+    #[test]
+    fn createx_guards_salts_like_createx() {
+        let caller = [0x11; 20];
+        let mut salt = [0x33; 32];
+        salt[..20].copy_from_slice(&caller);
+        salt[20] = 0;
+
+        // permissioned: keccak256(abi.encode(msg.sender, salt))
+        let mut preimage = address_word(&caller).to_vec();
+        preimage.extend_from_slice(&salt);
+        assert_eq!(Factory::CreateX.create2_salt(&salt, Some(&caller)), Ok(keccak256(&preimage)));
+        assert_eq!(Factory::CreateX.create2_salt(&salt, None), Ok(keccak256(&preimage)));
+
+        // anyone else's salt, or a zero prefix: keccak256(abi.encode(salt))
+        assert_eq!(Factory::CreateX.create2_salt(&salt, Some(&[0x22; 20])), Ok(keccak256(&salt)));
+        salt[..20].copy_from_slice(&[0u8; 20]);
+        assert_eq!(Factory::CreateX.create2_salt(&salt, Some(&caller)), Ok(keccak256(&salt)));
+
+        salt[20] = 1;
+        assert!(Factory::CreateX.create2_salt(&salt, Some(&caller)).is_err());
+        salt[20] = 2;
+        assert!(Factory::CreateX.create2_salt(&salt, Some(&caller)).is_err());
+    }
+
+    #[test]
+    fn mined_salts_are_the_salts_the_factory_uses() {
+        for caller in &[[0x11; 20], [0u8; 20]] {
+            for factory in &[Factory::ImmutableCreate2, Factory::CreateX, Factory::Arachnid] {
+                let scheme = factory.salt_scheme(caller).with_high_bytes(&[0x77; 24]);
+                let passed = scheme.salt_nonce(42).unwrap_or_else(|| scheme.salt(42));
+                assert_eq!(factory.create2_salt(&passed, Some(caller)), Ok(scheme.salt(42)), "{}", factory);
+            }
+        }
+    }
+
+    #[test]
+    fn immutable_create2_salts_must_start_with_the_caller() {
+        let salt = [0x11; 32];
+        assert!(Factory::ImmutableCreate2.create2_salt(&salt, Some(&[0x11; 20])).is_ok());
+        assert!(Factory::ImmutableCreate2.create2_salt(&salt, Some(&[0x22; 20])).is_err());
+        assert_eq!(Factory::ImmutableCreate2.create2_salt(&[0u8; 32], Some(&[0x22; 20])), Ok([0u8; 32]));
+    }
+
+    #[test]
+    fn calldata() {
+        let salt = [0x33; 32];
+        let code = [0xc0, 0xde];
+        let salt_and_code = format!("{}{:064x}{:064x}{:0<64}", "33".repeat(32), 0x40, 2, "c0de");
+        assert_eq!(hex::encode(Factory::ImmutableCreate2.calldata(&salt, &code)), format!("64e03087{}", salt_and_code));
+        assert_eq!(hex::encode(Factory::CreateX.calldata(&salt, &code)), format!("26307668{}", salt_and_code));
+        assert_eq!(
+            hex::encode(Factory::Create2Deployer.calldata(&salt, &code)),
+            format!("66cfa057{:064x}{}{:064x}{:064x}{:0<64}", 0, "33".repeat(32), 0x60, 2, "c0de")
+        );
+        assert_eq!(hex::encode(Factory::Arachnid.calldata(&salt, &code)), format!("{}c0de", "33".repeat(32)));
+    }
+}
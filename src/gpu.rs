@@ -1,89 +1,135 @@
 use std::error::Error;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::Path;
 
-use console::Term;
-use ocl::{ProQue, Buffer, MemFlags, Platform, Device, Context, Queue};
-use rand::{thread_rng, Rng};
-use separator::Separatable;
-use terminal_size::{Width, Height, terminal_size};
-use tiny_keccak::Keccak;
+use ocl::{Buffer, Device, DeviceType, Kernel, MemFlags, Platform, ProQue};
 
-use crate::{Config, WORK_SIZE, u64_to_le_fixed_8};
+use crate::Config;
+use crate::identifier::{search_identifiers, IdentifierConfig, IdentifierMatch, IdentifierMiner};
+use crate::output::{info, OutputFormat, Solution};
+use crate::score::ScoreFunction;
+use crate::search::{search, search_best, tail, Hit, Miner, ScoredHit, Scorer, ShareBatch, ShareMiner};
+use crate::selector::{search_selectors, SelectorConfig, SelectorMiner, SelectorSolution};
+use crate::tuning::{Tuning, WorkSize};
 
 // Include the kernel source
-static KERNEL_SRC: &'static str = include_str!("./kernels/keccak256.cl");
+static KERNEL_SRC: &str = include_str!("./kernels/keccak256.cl");
 
-/// GPU implementation of the CREATE2 address search
-pub fn gpu(config: Config) -> Result<(), Box<dyn Error>> {
-    println!("Setting up experimental OpenCL miner using device {}...", config.gpu_device);
+/// GPU implementation of the CREATE2 address search. Runs until a salt for a
+/// matching address is found and returns it, or when scoring until
+/// `config.stop` is set.
+pub fn gpu(config: Config) -> Result<Solution, Box<dyn Error>> {
+    info(&config, &format!("Setting up experimental OpenCL miner using device {}...", config.gpu_device));
+    let (device, work_size) = open_device(config.gpu_device, config.tuning_file.as_deref(), config.format)?;
 
-    // Extract the configuration values
-    let factory = config.factory_address;
-    let _caller = config.calling_address;
-    let init_hash = config.init_code_hash;
+    if let Some(score_function) = config.score {
+        let mut scorer = GpuScorer::new(&config, device, work_size, score_function)?;
+        return search_best(&config, &mut scorer, score_function);
+    }
 
-    // Prefix unused variables with underscore
-    let _salt: [u8; 6] = [0, 0, 0, 0, 0, 0];
+    let mut miner = GpuMiner::new(&config, device, work_size)?;
+    search(&config, &mut miner)
+}
 
-    // Set up the message for the kernel (factory address + init code hash + prefix + suffix)
-    let mut message: Vec<u8> = Vec::with_capacity(53 + config.starts_with.len() + config.ends_with.len() + 1);
-    // First 20 bytes: factory address
-    message.extend_from_slice(&factory);
-    // Next 32 bytes: init code hash
-    message.extend_from_slice(&init_hash);
-    // Next byte: length of the prefix
-    message.push(config.starts_with.len() as u8);
-    // Next bytes: the prefix itself
-    message.extend_from_slice(config.starts_with.as_bytes());
-    // Next byte: length of the suffix
-    message.push(config.ends_with.len() as u8);
-    // Last bytes: the suffix itself
-    message.extend_from_slice(config.ends_with.as_bytes());
+/// GPU implementation of the function selector search. Returns once
+/// `config.count` signatures have been found.
+pub fn gpu_selectors(config: SelectorConfig) -> Result<Vec<SelectorSolution>, Box<dyn Error>> {
+    config.format.info(&format!("Setting up experimental OpenCL miner using device {}...", config.gpu_device));
+    let (device, work_size) = open_device(config.gpu_device, config.tuning_file.as_deref(), config.format)?;
 
-    // Set up the OpenCL context
-    let platform = Platform::default();
-    let device = Device::by_idx_wrap(platform, config.gpu_device as usize)?;
-    let context = Context::builder()
-        .platform(platform)
-        .devices(device.clone())
-        .build()?;
-    let _queue = Queue::new(&context, device, None)?;
+    let mut miner = GpuSelectorMiner::new(&config, device, work_size)?;
+    search_selectors(&config, &mut miner)
+}
 
-    // Create the OpenCL program queue - quit on error
-    let ocl_pq = ProQue::builder()
-        .src(KERNEL_SRC)
-        .device(device)
-        .dims(WORK_SIZE)
-        .build()?;
+/// GPU implementation of the identifier search. Returns the matches once
+/// the search space is exhausted or `config.count` have been found.
+pub fn gpu_identifiers(config: IdentifierConfig) -> Result<Vec<IdentifierMatch>, Box<dyn Error>> {
+    config.format.info(&format!("Setting up experimental OpenCL miner using device {}...", config.gpu_device));
+    let (device, work_size) = open_device(config.gpu_device, config.tuning_file.as_deref(), config.format)?;
 
-    // Prefix unused variables with underscore
-    let _term = Term::stdout();
-    let mut previous_time = 0.0;
-    let start_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
-    let mut cumulative_nonce: u64 = 0;
-    let mut rng = thread_rng();
-
-    // Main loop
-    loop {
-        // Build the kernel and define the type of each buffer
-        let kern = ocl_pq.kernel_builder("hashMessage")
-            .arg_named("message", None::<&Buffer<u8>>)
-            .arg_named("nonce", None::<&Buffer<u32>>)
-            .arg_named("solutions", None::<&Buffer<u64>>)
-            .arg_named("has_solution", None::<&Buffer<u32>>)
-            .arg_named("digest_output", None::<&Buffer<u8>>)
-            .build()?;
+    let mut miner = GpuIdentifierMiner::new(&config, device, work_size)?;
+    search_identifiers(&config, &mut miner)
+}
 
-        // Generate a random nonce
-        let nonce: [u32; 1] = [rng.gen::<u32>()];
+/// Set up the CREATE2 address miner on `config.gpu_device`, for callers
+/// that choose the nonces to test themselves, such as a pool worker.
+pub fn gpu_miner(config: &Config) -> Result<GpuMiner, Box<dyn Error>> {
+    info(config, &format!("Setting up experimental OpenCL miner using device {}...", config.gpu_device));
+    let (device, work_size) = open_device(config.gpu_device, config.tuning_file.as_deref(), config.format)?;
+    GpuMiner::new(config, device, work_size)
+}
 
-        // Create the buffers
-        let nonce_buffer = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(1)
-            .copy_host_slice(&nonce)
-            .build()?;
+/// Set up a miner on `config.gpu_device` that also collects the near-miss
+/// shares of `share_config`, for a pool worker.
+pub fn gpu_share_miner(config: &Config, share_config: &Config) -> Result<GpuShareMiner, Box<dyn Error>> {
+    info(config, &format!("Setting up experimental OpenCL miner using device {}...", config.gpu_device));
+    let (device, work_size) = open_device(config.gpu_device, config.tuning_file.as_deref(), config.format)?;
+    GpuShareMiner::new(config, share_config, device, work_size)
+}
+
+/// Open a device on the default OpenCL platform, with the benchmarked launch
+/// settings for it if there are any.
+fn open_device(index: u32, tuning_file: Option<&Path>, format: OutputFormat) -> Result<(Device, WorkSize), Box<dyn Error>> {
+    let platform = default_platform()?;
+    let device = Device::by_idx_wrap(platform, index as usize)?;
+    let device_name = device.name()?;
+
+    let tuning = match tuning_file {
+        Some(path) => Tuning::load(path)?,
+        None => Tuning::default(),
+    };
+    let work_size = match tuning.get(&device_name) {
+        Some(work_size) => {
+            format.info(&format!("Using tuned settings for {}: {:?}", device_name, work_size));
+            work_size
+        }
+        None => WorkSize::gpu_default(),
+    };
+
+    Ok((device, work_size))
+}
+
+/// The platform `Platform::default()` picks, which honours
+/// `OCL_DEFAULT_PLATFORM_IDX`, as an error rather than a panic when there is
+/// none.
+fn default_platform() -> Result<Platform, Box<dyn Error>> {
+    Ok(Platform::from(ocl::core::default_platform()?))
+}
+
+/// All GPU devices on the default OpenCL platform, with their index on that
+/// platform as used by `Config::gpu_device`.
+pub fn gpu_devices() -> Result<Vec<(u32, Device)>, Box<dyn Error>> {
+    let platform = default_platform()?;
+    let devices = Device::list_all(platform)?;
+
+    let mut gpus = Vec::new();
+    for (index, device) in devices.into_iter().enumerate() {
+        if let ocl::enums::DeviceInfoResult::Type(device_type) = device.info(ocl::enums::DeviceInfo::Type)? {
+            if device_type.contains(DeviceType::GPU) {
+                gpus.push((index as u32, device));
+            }
+        }
+    }
+
+    Ok(gpus)
+}
+
+/// Runs the `hashMessage` kernel on one OpenCL device.
+pub struct GpuMiner {
+    kernel: Kernel,
+    work_size: WorkSize,
+    nonce_buffer: Buffer<u32>,
+    solutions_buffer: Buffer<u64>,
+    has_solution_buffer: Buffer<u32>,
+    digest_output_buffer: Buffer<u8>,
+}
+
+impl GpuMiner {
+    /// Compile the kernel for `device` and allocate its buffers.
+    pub fn new(config: &Config, device: Device, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+
+        let message = message(config);
+        let ocl_pq = queue(device, work_size)?;
 
         // Create the message buffer
         let message_buffer = Buffer::builder()
@@ -93,6 +139,14 @@ pub fn gpu(config: Config) -> Result<(), Box<dyn Error>> {
             .copy_host_slice(&message)
             .build()?;
 
+        // Create the nonce buffer, holding the low and high words of the batch base
+        let nonce_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(2)
+            .fill_val(0u32)
+            .build()?;
+
         // Create the solutions buffer
         let solutions_buffer = Buffer::builder()
             .queue(ocl_pq.queue().clone())
@@ -117,193 +171,622 @@ pub fn gpu(config: Config) -> Result<(), Box<dyn Error>> {
             .fill_val(0u8)
             .build()?;
 
-        // Set the kernel arguments
-        kern.set_arg("message", &message_buffer)?;
-        kern.set_arg("nonce", &nonce_buffer)?;
-        kern.set_arg("solutions", &solutions_buffer)?;
-        kern.set_arg("has_solution", &has_solution_buffer)?;
-        kern.set_arg("digest_output", &digest_output_buffer)?;
+        // Build the kernel
+        let mut builder = ocl_pq.kernel_builder("hashMessage");
+        builder
+            .arg_named("message", &message_buffer)
+            .arg_named("nonce", &nonce_buffer)
+            .arg_named("iterations", work_size.iterations)
+            .arg_named("solutions", &solutions_buffer)
+            .arg_named("has_solution", &has_solution_buffer)
+            .arg_named("digest_output", &digest_output_buffer);
+        if let Some(local) = work_size.local {
+            builder.local_work_size(local);
+        }
+        let kernel = builder.build()?;
+
+        Ok(GpuMiner {
+            kernel,
+            work_size,
+            nonce_buffer,
+            solutions_buffer,
+            has_solution_buffer,
+            digest_output_buffer,
+        })
+    }
+}
+
+impl Miner for GpuMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine(&mut self, base: u64) -> Result<Option<Hit>, Box<dyn Error>> {
+        let nonce: [u32; 2] = [base as u32, (base >> 32) as u32];
+        self.nonce_buffer.write(&nonce[..]).enq()?;
+        self.has_solution_buffer.write(&[0u32][..]).enq()?;
 
         // Enqueue the kernel
         unsafe {
-            kern.enq()?;
+            self.kernel.enq()?;
         }
 
-        // Read the solutions buffer
-        let mut solutions = vec![0u64; 1];
-        solutions_buffer.read(&mut solutions).enq()?;
-
         // Read the has_solution buffer
         let mut has_solution = vec![0u32; 1];
-        has_solution_buffer.read(&mut has_solution).enq()?;
+        self.has_solution_buffer.read(&mut has_solution).enq()?;
+
+        if has_solution[0] == 0 {
+            return Ok(None);
+        }
+
+        // Read the solutions buffer
+        let mut solutions = vec![0u64; 1];
+        self.solutions_buffer.read(&mut solutions).enq()?;
 
         // Read the digest_output buffer
         let mut digest_output = vec![0u8; 200];
-        digest_output_buffer.read(&mut digest_output).enq()?;
-
-        // Check if a solution was found
-        if has_solution[0] != 0 {
-            // A solution was found, process it
-            let solution_bytes = u64_to_le_fixed_8(&solutions[0]);
-            
-            // Extract the address from the digest
-            let mut address_bytes: [u8; 20] = Default::default();
-            address_bytes.copy_from_slice(&digest_output[12..32]);
-            let hex_address = hex::encode(&address_bytes);
-            
-            // Print detailed information about the solution
-            println!("Found potential solution!");
-            println!("Address from kernel digest: 0x{}", hex_address);
-            
-            // Check if the address matches our criteria
-            let matches = if !config.starts_with.is_empty() && !config.ends_with.is_empty() {
-                // Check both prefix and suffix
-                hex_address.starts_with(&config.starts_with) && hex_address.ends_with(&config.ends_with)
-            } else if !config.starts_with.is_empty() {
-                // Check only prefix
-                hex_address.starts_with(&config.starts_with)
-            } else if !config.ends_with.is_empty() {
-                // Check only suffix
-                hex_address.ends_with(&config.ends_with)
-            } else {
-                // No criteria specified, always match
-                true
-            };
-            
-            if matches {
-                // Calculate the time it took to find the solution
-                let solution_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64() - start_time;
-                
-                // Found a valid solution
-                let criteria = if !config.starts_with.is_empty() && !config.ends_with.is_empty() {
-                    format!("prefix '{}' and suffix '{}'", config.starts_with, config.ends_with)
-                } else if !config.starts_with.is_empty() {
-                    format!("prefix '{}'", config.starts_with)
-                } else {
-                    format!("suffix '{}'", config.ends_with)
-                };
-                
-                println!("\nFound valid solution with {} in {:.2} seconds!", criteria, solution_time);
-                
-                // Convert address to checksummed format
-                let checksummed_address = to_checksum_address(&hex_address);
-                println!("Address: {}", checksummed_address);
-                
-                // Print the creation code hash
-                println!("Creation Code Hash: 0x{}", hex::encode(&init_hash));
-                
-                // Format the salt properly as bytes32
-                let mut full_salt = [0u8; 32]; // Initialize with all zeros
-                
-                // Copy the solution bytes (8 bytes) to the end of the salt
-                let solution_len = std::cmp::min(solution_bytes.len(), 8);
-                // Place the solution bytes at the end of the salt (after 24 zero bytes)
-                full_salt[32 - solution_len..32].copy_from_slice(&solution_bytes[0..solution_len]);
-                
-                // Format as hex
-                let salt_hex = format!("0x{}", hex::encode(&full_salt));
-                println!("Salt: {}", salt_hex);
-                
-                // Verify the address using the same method as Foundry
-                let mut hasher = Keccak::new_keccak256();
-                hasher.update(&[0xff]); // 0xff prefix
-                hasher.update(&factory); // deployer address
-                hasher.update(&full_salt); // salt
-                hasher.update(&init_hash); // init code hash
-                
-                let mut hash_result = [0u8; 32];
-                hasher.finalize(&mut hash_result);
-                
-                // Extract the address (last 20 bytes)
-                let mut computed_address = [0u8; 20];
-                computed_address.copy_from_slice(&hash_result[12..32]);
-                
-                // Convert to hex and checksum
-                let computed_hex = hex::encode(&computed_address);
-                let computed_checksummed = to_checksum_address(&computed_hex);
-                
-                println!("Verified Address: {}", computed_checksummed);
-                
-                // Exit the program with success
-                std::process::exit(0);
-            }
-        }
+        self.digest_output_buffer.read(&mut digest_output).enq()?;
+
+        // Extract the address from the digest
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&digest_output[12..32]);
+
+        Ok(Some(Hit { nonce: solutions[0], address }))
+    }
+}
+
+/// Most shares one `hashShares` launch stores.
+pub const MAX_SHARES: u32 = 4096;
+
+/// Runs the `hashShares` kernel on one OpenCL device.
+pub struct GpuShareMiner {
+    kernel: Kernel,
+    work_size: WorkSize,
+    nonce_buffer: Buffer<u32>,
+    solutions_buffer: Buffer<u64>,
+    has_solution_buffer: Buffer<u32>,
+    hit_count_buffer: Buffer<u32>,
+    hits_buffer: Buffer<u64>,
+}
+
+impl GpuShareMiner {
+    /// Compile the kernel for `device` and allocate its buffers.
+    /// `share_config` must come from `share_config(config, ..)`.
+    pub fn new(config: &Config, share_config: &Config, device: Device, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+
+        let message = message(config);
+        let ocl_pq = queue(device, work_size)?;
 
-        // Update the cumulative nonce
-        cumulative_nonce += WORK_SIZE as u64;
-
-        // Print status update
-        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
-        let elapsed = current_time - start_time;
-        if elapsed - previous_time >= 1.0 {
-            previous_time = elapsed;
-            let rate = cumulative_nonce as f64 / elapsed / 1_000_000.0;
-            
-            // Get terminal size
-            let size = terminal_size();
-            let _width = if let Some((Width(w), Height(_))) = size {
-                w as usize
-            } else {
-                80
-            };
-            
-            // Clear the terminal output
-            print!("\x1B[2J\x1B[1;1H"); // ANSI escape code to clear screen and move cursor to top-left
-            
-            // Print the status
-            println!(
-                "----- New Update -----\n\
-                 total runtime: {:.2} seconds\t\t\twork size per cycle: {}\n\
-                 rate: {:.2} million attempts per second\ttotal attempts: {}\n\
-                 current search space: {:x}xxxxxxxx\t\tsearching for prefix: {}",
-                elapsed,
-                WORK_SIZE.separated_string(),
-                rate,
-                cumulative_nonce.separated_string(),
-                nonce[0],
-                config.starts_with
-            );
-            
-            
+        let message_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(message.len())
+            .copy_host_slice(&message)
+            .build()?;
+
+        let nonce_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(2)
+            .fill_val(0u32)
+            .build()?;
+
+        let solutions_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u64)
+            .build()?;
+
+        let has_solution_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u32)
+            .build()?;
+
+        // Number of shares in the batch, including any that did not fit
+        let hit_count_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u32)
+            .build()?;
+
+        let hits_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(MAX_SHARES as usize)
+            .fill_val(0u64)
+            .build()?;
+
+        let mut builder = ocl_pq.kernel_builder("hashShares");
+        builder
+            .arg_named("message", &message_buffer)
+            .arg_named("nonce", &nonce_buffer)
+            .arg_named("iterations", work_size.iterations)
+            .arg_named("share_prefix_len", share_config.starts_with.len() as u32)
+            .arg_named("share_suffix_len", share_config.ends_with.len() as u32)
+            .arg_named("solutions", &solutions_buffer)
+            .arg_named("has_solution", &has_solution_buffer)
+            .arg_named("hit_count", &hit_count_buffer)
+            .arg_named("hits", &hits_buffer)
+            .arg_named("max_hits", MAX_SHARES);
+        if let Some(local) = work_size.local {
+            builder.local_work_size(local);
         }
+        let kernel = builder.build()?;
+
+        Ok(GpuShareMiner {
+            kernel,
+            work_size,
+            nonce_buffer,
+            solutions_buffer,
+            has_solution_buffer,
+            hit_count_buffer,
+            hits_buffer,
+        })
     }
 }
 
-// Add this function to convert an address to checksummed format
-fn to_checksum_address(address: &str) -> String {
-    // Remove '0x' prefix if present
-    let address = if address.starts_with("0x") {
-        &address[2..]
-    } else {
-        address
-    };
-    
-    // Convert address to lowercase
-    let address = address.to_lowercase();
-    
-    // Hash the address
-    let mut hasher = Keccak::new_keccak256();
-    hasher.update(address.as_bytes());
-    let mut hash = [0u8; 32];
-    hasher.finalize(&mut hash);
-    
-    // Create checksummed address
-    let mut checksummed = String::with_capacity(42);
-    checksummed.push_str("0x");
-    
-    for (i, c) in address.chars().enumerate() {
-        if c >= '0' && c <= '9' {
-            checksummed.push(c);
+impl ShareMiner for GpuShareMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine_shares(&mut self, base: u64) -> Result<ShareBatch, Box<dyn Error>> {
+        let nonce: [u32; 2] = [base as u32, (base >> 32) as u32];
+        self.nonce_buffer.write(&nonce[..]).enq()?;
+        self.has_solution_buffer.write(&[0u32][..]).enq()?;
+        self.hit_count_buffer.write(&[0u32][..]).enq()?;
+
+        unsafe {
+            self.kernel.enq()?;
+        }
+
+        let mut hit_count = vec![0u32; 1];
+        self.hit_count_buffer.read(&mut hit_count).enq()?;
+        if hit_count[0] > MAX_SHARES {
+            return Err(format!(
+                "{} shares in one batch overflowed the buffer of {}; use more share digits or a smaller work size",
+                hit_count[0], MAX_SHARES
+            ).into());
+        }
+
+        let mut shares = vec![0u64; hit_count[0] as usize];
+        if !shares.is_empty() {
+            self.hits_buffer.read(&mut shares).enq()?;
+            shares.sort_unstable();
+        }
+
+        let mut has_solution = vec![0u32; 1];
+        self.has_solution_buffer.read(&mut has_solution).enq()?;
+        let hit = if has_solution[0] == 0 {
+            None
         } else {
-            // Get the corresponding nibble from the hash
-            let nibble = hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 }) & 0xf;
-            if nibble >= 8 {
-                checksummed.push(c.to_ascii_uppercase());
-            } else {
-                checksummed.push(c);
+            let mut solutions = vec![0u64; 1];
+            self.solutions_buffer.read(&mut solutions).enq()?;
+            Some(solutions[0])
+        };
+
+        Ok(ShareBatch { hit, shares })
+    }
+}
+
+/// Runs the `scoreMessage` kernel on one OpenCL device.
+pub struct GpuScorer {
+    kernel: Kernel,
+    work_size: WorkSize,
+    nonce_buffer: Buffer<u32>,
+    best_buffer: Buffer<u64>,
+}
+
+impl GpuScorer {
+    /// Compile the kernel for `device` and allocate its buffers.
+    pub fn new(config: &Config, device: Device, work_size: WorkSize, score_function: ScoreFunction) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+
+        // scoreMessage is only compiled where its 64-bit atomic max is
+        // supported
+        let extensions = device.info(ocl::enums::DeviceInfo::Extensions)?.to_string();
+        if !extensions.split_whitespace().any(|extension| extension == "cl_khr_int64_extended_atomics") {
+            return Err(format!(
+                "device {} does not support cl_khr_int64_extended_atomics, which --score needs",
+                device.name()?
+            ).into());
+        }
+
+        let message = message(config);
+        let ocl_pq = queue(device, work_size)?;
+
+        let message_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(message.len())
+            .copy_host_slice(&message)
+            .build()?;
+
+        let nonce_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(2)
+            .fill_val(0u32)
+            .build()?;
+
+        // Best score (high word) and nonce offset (low word) of the batch
+        let best_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u64)
+            .build()?;
+
+        let mut builder = ocl_pq.kernel_builder("scoreMessage");
+        builder
+            .arg_named("message", &message_buffer)
+            .arg_named("nonce", &nonce_buffer)
+            .arg_named("iterations", work_size.iterations)
+            .arg_named("score_function", score_function.kernel_id())
+            .arg_named("threshold", 0u32)
+            .arg_named("best", &best_buffer);
+        if let Some(local) = work_size.local {
+            builder.local_work_size(local);
+        }
+        let kernel = builder.build()?;
+
+        Ok(GpuScorer { kernel, work_size, nonce_buffer, best_buffer })
+    }
+}
+
+impl Scorer for GpuScorer {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn best(&mut self, base: u64, threshold: u32) -> Result<Option<ScoredHit>, Box<dyn Error>> {
+        let nonce: [u32; 2] = [base as u32, (base >> 32) as u32];
+        self.nonce_buffer.write(&nonce[..]).enq()?;
+        self.best_buffer.write(&[0u64][..]).enq()?;
+        self.kernel.set_arg("threshold", threshold)?;
+
+        unsafe {
+            self.kernel.enq()?;
+        }
+
+        let mut best = vec![0u64; 1];
+        self.best_buffer.read(&mut best).enq()?;
+
+        let score = (best[0] >> 32) as u32;
+        if score <= threshold {
+            return Ok(None);
+        }
+
+        Ok(Some(ScoredHit { nonce: base + (best[0] & 0xffff_ffff), score }))
+    }
+}
+
+/// Runs the `hashSelector` kernel on one OpenCL device.
+pub struct GpuSelectorMiner {
+    kernel: Kernel,
+    work_size: WorkSize,
+    nonce_buffer: Buffer<u32>,
+    solutions_buffer: Buffer<u32>,
+    has_solution_buffer: Buffer<u32>,
+}
+
+impl GpuSelectorMiner {
+    /// Compile the kernel for `device` and allocate its buffers.
+    pub fn new(config: &SelectorConfig, device: Device, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+
+        let message = config.kernel_bytes();
+        let ocl_pq = queue(device, work_size)?;
+
+        let message_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(message.len())
+            .copy_host_slice(&message)
+            .build()?;
+
+        let nonce_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(2)
+            .fill_val(0u32)
+            .build()?;
+
+        // Offset of the lowest matching nonce from the batch base
+        let solutions_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(u32::MAX)
+            .build()?;
+
+        let has_solution_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u32)
+            .build()?;
+
+        let mut builder = ocl_pq.kernel_builder("hashSelector");
+        builder
+            .arg_named("message", &message_buffer)
+            .arg_named("nonce", &nonce_buffer)
+            .arg_named("iterations", work_size.iterations)
+            .arg_named("zero_bytes", config.zero_bytes as u32)
+            .arg_named("threshold", config.threshold())
+            .arg_named("solutions", &solutions_buffer)
+            .arg_named("has_solution", &has_solution_buffer);
+        if let Some(local) = work_size.local {
+            builder.local_work_size(local);
+        }
+        let kernel = builder.build()?;
+
+        Ok(GpuSelectorMiner { kernel, work_size, nonce_buffer, solutions_buffer, has_solution_buffer })
+    }
+}
+
+impl SelectorMiner for GpuSelectorMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine(&mut self, base: u64) -> Result<Option<u64>, Box<dyn Error>> {
+        let nonce: [u32; 2] = [base as u32, (base >> 32) as u32];
+        self.nonce_buffer.write(&nonce[..]).enq()?;
+        self.solutions_buffer.write(&[u32::MAX][..]).enq()?;
+        self.has_solution_buffer.write(&[0u32][..]).enq()?;
+
+        unsafe {
+            self.kernel.enq()?;
+        }
+
+        let mut has_solution = vec![0u32; 1];
+        self.has_solution_buffer.read(&mut has_solution).enq()?;
+        if has_solution[0] == 0 {
+            return Ok(None);
+        }
+
+        let mut solutions = vec![0u32; 1];
+        self.solutions_buffer.read(&mut solutions).enq()?;
+        Ok(Some(base + solutions[0] as u64))
+    }
+}
+
+/// Most hits one `hashIdentifiers` launch stores.
+pub const MAX_IDENTIFIER_HITS: u32 = 4096;
+
+/// Runs the `hashIdentifiers` kernel on one OpenCL device.
+pub struct GpuIdentifierMiner {
+    kernel: Kernel,
+    work_size: WorkSize,
+    nonce_buffer: Buffer<u32>,
+    hit_count_buffer: Buffer<u32>,
+    hits_buffer: Buffer<u64>,
+}
+
+impl GpuIdentifierMiner {
+    /// Compile the kernel for `device` and copy the dictionary and targets
+    /// to it.
+    pub fn new(config: &IdentifierConfig, device: Device, work_size: WorkSize) -> Result<Self, Box<dyn Error>> {
+        work_size.validate()?;
+
+        let message = config.kernel_bytes();
+        let (words, word_offsets) = config.kernel_words();
+        let targets = config.kernel_targets();
+        let ocl_pq = queue(device, work_size)?;
+
+        let words_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(words.len())
+            .copy_host_slice(&words)
+            .build()?;
+
+        let word_offsets_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(word_offsets.len())
+            .copy_host_slice(&word_offsets)
+            .build()?;
+
+        let message_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(message.len())
+            .copy_host_slice(&message)
+            .build()?;
+
+        let nonce_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(2)
+            .fill_val(0u32)
+            .build()?;
+
+        let targets_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(targets.len())
+            .copy_host_slice(&targets)
+            .build()?;
+
+        // Number of hits in the batch, including any that did not fit
+        let hit_count_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u32)
+            .build()?;
+
+        let hits_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(MAX_IDENTIFIER_HITS as usize)
+            .fill_val(0u64)
+            .build()?;
+
+        let mut builder = ocl_pq.kernel_builder("hashIdentifiers");
+        builder
+            .arg_named("words", &words_buffer)
+            .arg_named("word_offsets", &word_offsets_buffer)
+            .arg_named("word_count", config.words.len() as u32)
+            .arg_named("message", &message_buffer)
+            .arg_named("nonce", &nonce_buffer)
+            .arg_named("iterations", work_size.iterations)
+            .arg_named("end", config.search_space())
+            .arg_named("targets", &targets_buffer)
+            .arg_named("target_count", config.targets.len() as u32)
+            .arg_named("target_len", config.target_len() as u32)
+            .arg_named("hit_count", &hit_count_buffer)
+            .arg_named("hits", &hits_buffer)
+            .arg_named("max_hits", MAX_IDENTIFIER_HITS);
+        if let Some(local) = work_size.local {
+            builder.local_work_size(local);
+        }
+        let kernel = builder.build()?;
+
+        Ok(GpuIdentifierMiner { kernel, work_size, nonce_buffer, hit_count_buffer, hits_buffer })
+    }
+}
+
+impl IdentifierMiner for GpuIdentifierMiner {
+    fn batch_size(&self) -> u64 {
+        self.work_size.batch_size()
+    }
+
+    fn mine(&mut self, base: u64) -> Result<Vec<u64>, Box<dyn Error>> {
+        let nonce: [u32; 2] = [base as u32, (base >> 32) as u32];
+        self.nonce_buffer.write(&nonce[..]).enq()?;
+        self.hit_count_buffer.write(&[0u32][..]).enq()?;
+
+        unsafe {
+            self.kernel.enq()?;
+        }
+
+        let mut hit_count = vec![0u32; 1];
+        self.hit_count_buffer.read(&mut hit_count).enq()?;
+        if hit_count[0] > MAX_IDENTIFIER_HITS {
+            return Err(format!(
+                "{} matches in one batch overflowed the buffer of {}; use longer targets or a smaller work size",
+                hit_count[0], MAX_IDENTIFIER_HITS
+            ).into());
+        }
+        if hit_count[0] == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut hits = vec![0u64; hit_count[0] as usize];
+        self.hits_buffer.read(&mut hits).enq()?;
+        hits.sort_unstable();
+
+        Ok(hits)
+    }
+}
+
+/// Messages hashed by one `hashBatch` launch.
+pub const HASH_BATCH_SIZE: usize = 1 << 20;
+
+/// Runs the `hashBatch` kernel on one OpenCL device, for hashing many
+/// fixed-length messages.
+pub struct GpuHasher {
+    ocl_pq: ProQue,
+}
+
+impl GpuHasher {
+    /// Compile the kernel for `device`.
+    pub fn new(device: Device) -> Result<Self, Box<dyn Error>> {
+        let ocl_pq = ProQue::builder().src(KERNEL_SRC).device(device).dims(HASH_BATCH_SIZE).build()?;
+        Ok(GpuHasher { ocl_pq })
+    }
+
+    /// The Keccak-256 digest of each of `messages`, which must not be empty.
+    pub fn hash<const N: usize>(&mut self, messages: &[[u8; N]]) -> Result<Vec<[u8; 32]>, Box<dyn Error>> {
+        if N == 0 || messages.is_empty() {
+            return Err("the GPU hasher needs at least one non-empty message".into());
+        }
+
+        let mut digests = Vec::with_capacity(messages.len());
+        for chunk in messages.chunks(HASH_BATCH_SIZE) {
+            let messages_buffer = Buffer::builder()
+                .queue(self.ocl_pq.queue().clone())
+                .flags(MemFlags::new().read_only())
+                .len(chunk.len() * N)
+                .copy_host_slice(chunk.as_flattened())
+                .build()?;
+
+            let digests_buffer = Buffer::<u8>::builder()
+                .queue(self.ocl_pq.queue().clone())
+                .flags(MemFlags::new().write_only())
+                .len(chunk.len() * 32)
+                .build()?;
+
+            let kernel = self
+                .ocl_pq
+                .kernel_builder("hashBatch")
+                .arg_named("messages", &messages_buffer)
+                .arg_named("message_len", N as u32)
+                .arg_named("count", chunk.len() as u32)
+                .arg_named("digests", &digests_buffer)
+                .global_work_size(chunk.len())
+                .build()?;
+
+            unsafe {
+                kernel.enq()?;
             }
+
+            let mut bytes = vec![0u8; chunk.len() * 32];
+            digests_buffer.read(&mut bytes).enq()?;
+            digests.extend(bytes.chunks(32).map(|bytes| {
+                let mut digest = [0u8; 32];
+                digest.copy_from_slice(bytes);
+                digest
+            }));
         }
+
+        Ok(digests)
     }
-    
-    checksummed
-} 
\ No newline at end of file
+}
+
+/// The kernel's `message` argument: factory address, init code hash, prefix
+/// and suffix with their lengths, the tail mask, the salt scheme and the
+/// address scheme.
+fn message(config: &Config) -> Vec<u8> {
+    let mut message: Vec<u8> = Vec::with_capacity(58 + config.starts_with.len() + config.ends_with.len());
+    // First 20 bytes: factory address
+    message.extend_from_slice(&config.factory_address);
+    // Next 32 bytes: init code hash
+    message.extend_from_slice(&config.init_code_hash);
+    // Next byte: length of the prefix
+    message.push(config.starts_with.len() as u8);
+    // Next bytes: the prefix itself
+    message.extend_from_slice(config.starts_with.as_bytes());
+    // Next byte: length of the suffix
+    message.push(config.ends_with.len() as u8);
+    // Next bytes: the suffix itself
+    message.extend_from_slice(config.ends_with.as_bytes());
+    // Next 4 bytes: mask and required value of the last two address bytes
+    let (tail_mask, tail_value) = tail(config);
+    message.extend_from_slice(&tail_mask);
+    message.extend_from_slice(&tail_value);
+    // Next bytes: salt scheme and, for hashed salts, the absorbed prefix
+    message.extend_from_slice(&config.salt_scheme.kernel_bytes());
+    // Last bytes: address scheme and its constants
+    message.extend_from_slice(&config.address_scheme.kernel_bytes());
+
+    message
+}
+
+/// Create the OpenCL program queue for `device`.
+fn queue(device: Device, work_size: WorkSize) -> Result<ProQue, Box<dyn Error>> {
+    let ocl_pq = ProQue::builder()
+        .src(KERNEL_SRC)
+        .device(device)
+        .dims(work_size.global)
+        .build()?;
+    Ok(ocl_pq)
+}
@@ -0,0 +1,110 @@
+use crate::parse::ParseError;
+
+/// Number of low address bits from which the Uniswap v4 PoolManager reads a
+/// hook's permissions.
+pub const HOOK_FLAG_BITS: u32 = 14;
+
+/// Mask of the permission bits in the last two bytes of a hook address.
+pub const HOOK_FLAG_MASK: u16 = (1 << HOOK_FLAG_BITS) - 1;
+
+/// Permission names, as in `Hooks.Permissions`, and their address bits.
+pub const HOOK_FLAGS: [(&str, u16); 14] = [
+    ("beforeInitialize", 1 << 13),
+    ("afterInitialize", 1 << 12),
+    ("beforeAddLiquidity", 1 << 11),
+    ("afterAddLiquidity", 1 << 10),
+    ("beforeRemoveLiquidity", 1 << 9),
+    ("afterRemoveLiquidity", 1 << 8),
+    ("beforeSwap", 1 << 7),
+    ("afterSwap", 1 << 6),
+    ("beforeDonate", 1 << 5),
+    ("afterDonate", 1 << 4),
+    ("beforeSwapReturnDelta", 1 << 3),
+    ("afterSwapReturnDelta", 1 << 2),
+    ("afterAddLiquidityReturnDelta", 1 << 1),
+    ("afterRemoveLiquidityReturnDelta", 1 << 0),
+];
+
+/// Parse a comma-separated list of permission names, such as
+/// `beforeSwap,afterAddLiquidity`, into hook flag bits. Names are matched
+/// case-insensitively.
+pub fn parse_hook_flags(field: &'static str, value: &str) -> Result<u16, ParseError> {
+    if value.trim().is_empty() {
+        return Err(ParseError::Empty { field });
+    }
+
+    let mut flags = 0;
+    for name in value.split(',').map(str::trim) {
+        match HOOK_FLAGS.iter().find(|(flag, _)| flag.eq_ignore_ascii_case(name)) {
+            Some(&(_, bit)) => flags |= bit,
+            None => return Err(ParseError::UnknownHookFlag { field, name: name.to_string() }),
+        }
+    }
+
+    Ok(flags)
+}
+
+/// The permission names set in `flags`, highest bit first.
+pub fn hook_flag_names(flags: u16) -> Vec<&'static str> {
+    HOOK_FLAGS.iter().filter(|(_, bit)| flags & bit != 0).map(|&(name, _)| name).collect()
+}
+
+/// The mask and required value of the last two address bytes for a hook
+/// with exactly `flags` set: every permission bit is fixed, set or clear.
+pub fn hook_tail(flags: u16) -> ([u8; 2], [u8; 2]) {
+    (HOOK_FLAG_MASK.to_be_bytes(), (flags & HOOK_FLAG_MASK).to_be_bytes())
+}
+
+/// Check that a prefix leaves the permission bits free. The last four hex
+/// digits of an address hold the 14 flag bits.
+pub fn check_hook_prefix(prefix: &str) -> Result<(), ParseError> {
+    if prefix.len() > 36 {
+        return Err(ParseError::HookPrefixOverlap { value: prefix.to_string() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_names_round_trip() {
+        assert_eq!(parse_hook_flags("hook flags", "beforeSwap, AFTERSWAP").unwrap(), 0x00c0);
+        assert_eq!(hook_flag_names(0x00c0), vec!["beforeSwap", "afterSwap"]);
+        for flags in &[0, 1, 0x2000, 0x00c0, 0x2aaa, HOOK_FLAG_MASK] {
+            let names = hook_flag_names(*flags);
+            if !names.is_empty() {
+                assert_eq!(parse_hook_flags("hook flags", &names.join(",")).unwrap(), *flags);
+            }
+        }
+        assert_eq!(hook_flag_names(HOOK_FLAG_MASK).len(), 14);
+        // bits above the 14 permission bits have no name
+        assert!(hook_flag_names(0xc000).is_empty());
+    }
+
+    #[test]
+    fn tail_fixes_only_the_flag_bits() {
+        assert_eq!(hook_tail(0x00c0), ([0x3f, 0xff], [0x00, 0xc0]));
+        // the top two bits of the last two bytes are left free
+        assert_eq!(hook_tail(0xffff), ([0x3f, 0xff], [0x3f, 0xff]));
+    }
+
+    #[test]
+    fn hook_errors_name_the_problem() {
+        assert_eq!(parse_hook_flags("hook flags", " ").unwrap_err().to_string(), "hook flags is empty");
+        assert_eq!(
+            parse_hook_flags("hook flags", "beforeSwap,beforeNap").unwrap_err().to_string(),
+            "hook flags has unknown hook permission 'beforeNap' (expected names such as beforeSwap or afterAddLiquidity)"
+        );
+    }
+
+    #[test]
+    fn prefix_leaves_the_last_four_digits_free() {
+        check_hook_prefix(&"0".repeat(36)).unwrap();
+        assert_eq!(
+            check_hook_prefix(&"0".repeat(37)).unwrap_err().to_string(),
+            format!("prefix '{}' has 37 hex digits, but with hook flags at most 36 are free", "0".repeat(37))
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde_json::json;
+use separator::Separatable;
+use tiny_keccak::Keccak;
+
+use crate::keccak256;
+use crate::output::OutputFormat;
+use crate::selector::{write_suffix, MAX_SUFFIX_LEN};
+
+/// What the hash of an identifier is used as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentifierKind {
+    /// A function selector, the first 4 bytes of the hash.
+    Function,
+    /// A custom error selector, the first 4 bytes of the hash.
+    Error,
+    /// An event's topic0, the whole hash.
+    Event,
+}
+
+impl IdentifierKind {
+    /// Bytes of the hash that make up the selector or topic.
+    pub fn hash_len(&self) -> usize {
+        match self {
+            IdentifierKind::Function | IdentifierKind::Error => 4,
+            IdentifierKind::Event => 32,
+        }
+    }
+}
+
+impl FromStr for IdentifierKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "function" => Ok(IdentifierKind::Function),
+            "error" => Ok(IdentifierKind::Error),
+            "event" => Ok(IdentifierKind::Event),
+            _ => Err(format!("unknown identifier kind '{}' (expected function, error or event)", s)),
+        }
+    }
+}
+
+impl fmt::Display for IdentifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdentifierKind::Function => write!(f, "function"),
+            IdentifierKind::Error => write!(f, "error"),
+            IdentifierKind::Event => write!(f, "event"),
+        }
+    }
+}
+
+/// A search over identifiers `word[_suffix](params)` for words from a
+/// dictionary, whose selector or topic starts with one of a set of targets:
+/// full selectors to find collisions with, or short prefixes for vanity
+/// topics.
+#[derive(Clone, Debug)]
+pub struct IdentifierConfig {
+    pub kind: IdentifierKind,
+    pub words: Vec<String>,
+    /// The parameter list, with its parentheses.
+    pub params: String,
+    /// Longest suffix appended to each word; every word is also tried
+    /// without a suffix.
+    pub max_suffix_len: u32,
+    /// Sorted, distinct hash prefixes, all of the same length.
+    pub targets: Vec<Vec<u8>>,
+    /// Stop after this many matches, or search the whole space.
+    pub count: Option<usize>,
+    pub gpu_device: u32,
+    pub format: OutputFormat,
+    pub tuning_file: Option<PathBuf>,
+}
+
+impl IdentifierConfig {
+    /// A search of `kind` identifiers built from `words`, with no suffixes.
+    /// `targets` are sorted and deduplicated.
+    pub fn new(kind: IdentifierKind, words: Vec<String>, params: &str, mut targets: Vec<Vec<u8>>) -> Result<Self, String> {
+        if words.is_empty() {
+            return Err("the dictionary has no words".to_string());
+        }
+        if let Some(word) = words.iter().find(|word| !is_identifier(word)) {
+            return Err(format!("dictionary word '{}' is not a valid identifier", word));
+        }
+        if !params.starts_with('(') || !params.ends_with(')') || params.chars().any(char::is_whitespace) {
+            return Err(format!(
+                "parameter list '{}' must be canonical, like (address,uint256), without spaces",
+                params
+            ));
+        }
+        if params.len() > u8::MAX as usize {
+            return Err(format!("parameter list must be at most {} bytes", u8::MAX));
+        }
+
+        targets.sort();
+        targets.dedup();
+        let target_len = match targets.first() {
+            Some(target) => target.len(),
+            None => return Err("the target list is empty".to_string()),
+        };
+        if target_len == 0 || target_len > kind.hash_len() {
+            return Err(format!("{} targets must have 1 to {} bytes", kind, kind.hash_len()));
+        }
+        if targets.iter().any(|target| target.len() != target_len) {
+            return Err("all targets must have the same length".to_string());
+        }
+
+        Ok(IdentifierConfig {
+            kind,
+            words,
+            params: params.to_string(),
+            max_suffix_len: 0,
+            targets,
+            count: None,
+            gpu_device: 0,
+            format: OutputFormat::Text,
+            tuning_file: None,
+        })
+    }
+
+    /// The number of suffixes tried with each word, counting no suffix.
+    pub fn suffix_count(&self) -> u64 {
+        (0..=self.max_suffix_len).map(|len| 62u64.saturating_pow(len)).fold(0, u64::saturating_add)
+    }
+
+    /// The number of identifiers in the search, and the end of its nonces.
+    pub fn search_space(&self) -> u64 {
+        self.suffix_count().saturating_mul(self.words.len() as u64)
+    }
+
+    /// The identifier for a nonce: the word `nonce % words` with the suffix
+    /// `nonce / words`, as `hashIdentifiers` builds it.
+    pub fn identifier(&self, nonce: u64) -> String {
+        let mut buffer = [0u8; MAX_SUFFIX_LEN];
+        let (word, suffix) = self.parts(nonce, &mut buffer);
+        if suffix.is_empty() {
+            format!("{}{}", word, self.params)
+        } else {
+            format!("{}_{}{}", word, String::from_utf8_lossy(suffix), self.params)
+        }
+    }
+
+    /// The hash of the identifier for a nonce, without building the
+    /// identifier string.
+    pub fn hash(&self, nonce: u64) -> [u8; 32] {
+        let mut buffer = [0u8; MAX_SUFFIX_LEN];
+        let (word, suffix) = self.parts(nonce, &mut buffer);
+
+        let mut hasher = Keccak::new_keccak256();
+        hasher.update(word.as_bytes());
+        if !suffix.is_empty() {
+            hasher.update(b"_");
+            hasher.update(suffix);
+        }
+        hasher.update(self.params.as_bytes());
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+        hash
+    }
+
+    fn parts<'a>(&'a self, nonce: u64, buffer: &'a mut [u8; MAX_SUFFIX_LEN]) -> (&'a str, &'a [u8]) {
+        let word = &self.words[(nonce % self.words.len() as u64) as usize];
+        let suffix = match nonce / self.words.len() as u64 {
+            0 => 0,
+            index => write_suffix(index - 1, buffer),
+        };
+        (word, &buffer[..suffix])
+    }
+
+    /// The target a hash starts with, if any.
+    pub fn matched_target(&self, hash: &[u8; 32]) -> Option<&[u8]> {
+        let prefix = &hash[..self.target_len()];
+        self.targets.binary_search_by(|target| target[..].cmp(prefix)).ok().map(|i| &self.targets[i][..])
+    }
+
+    /// Length of each target in bytes.
+    pub fn target_len(&self) -> usize {
+        self.targets[0].len()
+    }
+
+    /// The kernel's `words` and `wordOffsets` arguments: the words back to
+    /// back, and where each starts, followed by the end of the last.
+    pub fn kernel_words(&self) -> (Vec<u8>, Vec<u32>) {
+        let mut words = Vec::new();
+        let mut offsets = vec![0u32];
+        for word in &self.words {
+            words.extend_from_slice(word.as_bytes());
+            offsets.push(words.len() as u32);
+        }
+        (words, offsets)
+    }
+
+    /// The kernel's `message` argument: the length and bytes of the
+    /// parameter list.
+    pub fn kernel_bytes(&self) -> Vec<u8> {
+        let mut message = vec![self.params.len() as u8];
+        message.extend_from_slice(self.params.as_bytes());
+        message
+    }
+
+    /// The kernel's `targets` argument: the sorted targets back to back.
+    pub fn kernel_targets(&self) -> Vec<u8> {
+        self.targets.concat()
+    }
+}
+
+/// Whether `word` is a valid Solidity identifier.
+pub fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Lines of a list file that carry a value: blank lines and lines starting
+/// with `#` are skipped, and only the first field of each line is kept.
+fn list_entries(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("could not read '{}': {}", path.display(), e))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Load a dictionary file: one word per line.
+pub fn load_words(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    list_entries(path)
+}
+
+/// Load a target file: one hex selector, topic or prefix per line, such as
+/// `0x82b42900 Unauthorized()`. Anything after the value is ignored.
+pub fn load_targets(path: &Path) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    list_entries(path)?
+        .iter()
+        .map(|entry| {
+            let digits = entry.strip_prefix("0x").unwrap_or(entry);
+            hex::decode(digits).map_err(|_| format!("target '{}' in '{}' is not whole bytes of hex", entry, path.display()).into())
+        })
+        .collect()
+}
+
+/// An identifier whose hash starts with a target, verified on the host.
+#[derive(Clone, Debug)]
+pub struct IdentifierMatch {
+    pub kind: IdentifierKind,
+    pub identifier: String,
+    /// The selector or topic.
+    pub hash: Vec<u8>,
+    pub target: Vec<u8>,
+    pub nonce: u64,
+    pub elapsed_seconds: f64,
+}
+
+impl IdentifierMatch {
+    /// Print the match as a `match` event.
+    pub fn report(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                json!({
+                    "event": "match",
+                    "kind": self.kind.to_string(),
+                    "identifier": self.identifier,
+                    "hash": format!("0x{}", hex::encode(&self.hash)),
+                    "target": format!("0x{}", hex::encode(&self.target)),
+                    "elapsed_seconds": self.elapsed_seconds,
+                })
+            ),
+            OutputFormat::Text => println!("0x{}  {} {}", hex::encode(&self.hash), self.kind, self.identifier),
+        }
+    }
+}
+
+/// A backend that tests a batch of consecutive identifier nonces.
+pub trait IdentifierMiner {
+    /// Number of nonces tested by one call to `mine`.
+    fn batch_size(&self) -> u64;
+
+    /// Test the nonces `base..base + batch_size()` below the end of the
+    /// search space and return, in order, those whose hash starts with a
+    /// target.
+    fn mine(&mut self, base: u64) -> Result<Vec<u64>, Box<dyn Error>>;
+}
+
+/// Run `miner` over the whole search space, or until `config.count`
+/// matches, reporting each match as it is found.
+///
+/// Each hit is recomputed on the host; a device that reports an identifier
+/// whose hash does not start with a target is treated as an error.
+pub fn search_identifiers<M: IdentifierMiner>(
+    config: &IdentifierConfig,
+    miner: &mut M,
+) -> Result<Vec<IdentifierMatch>, Box<dyn Error>> {
+    let start_time = Instant::now();
+    let mut previous_time = 0.0;
+    let end = config.search_space();
+    let mut base: u64 = 0;
+    let mut matches = Vec::new();
+
+    while base < end {
+        for nonce in miner.mine(base)? {
+            let identifier = config.identifier(nonce);
+            let hash = keccak256(identifier.as_bytes());
+            let target = match config.matched_target(&hash) {
+                Some(target) => target.to_vec(),
+                None => {
+                    return Err(format!(
+                        "device reported {} whose hash 0x{} matches no target",
+                        identifier,
+                        hex::encode(hash)
+                    ).into())
+                }
+            };
+
+            let found = IdentifierMatch {
+                kind: config.kind,
+                identifier,
+                hash: hash[..config.kind.hash_len()].to_vec(),
+                target,
+                nonce,
+                elapsed_seconds: start_time.elapsed().as_secs_f64(),
+            };
+            found.report(config.format);
+            matches.push(found);
+
+            if Some(matches.len()) == config.count {
+                return Ok(matches);
+            }
+        }
+        base = base.saturating_add(miner.batch_size());
+
+        let elapsed = start_time.elapsed().as_secs_f64();
+        if elapsed - previous_time >= 1.0 && base < end {
+            previous_time = elapsed;
+            config.format.info(&format!(
+                "searched {} of {} identifiers ({:.1}%, {:.2} million per second)",
+                base.separated_string(),
+                end.separated_string(),
+                base as f64 / end as f64 * 100.0,
+                base as f64 / elapsed / 1_000_000.0
+            ));
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_is_the_keccak_of_the_identifier() {
+        let words = vec!["owner".to_string(), "x".repeat(150)];
+        for &kind in &[IdentifierKind::Function, IdentifierKind::Error, IdentifierKind::Event] {
+            let mut config = IdentifierConfig::new(kind, words.clone(), "(address,uint256)", vec![vec![0]]).expect("valid config");
+            config.max_suffix_len = 3;
+            for nonce in (0..config.search_space()).step_by(997) {
+                assert_eq!(config.hash(nonce), keccak256(config.identifier(nonce).as_bytes()), "{}", config.identifier(nonce));
+            }
+        }
+    }
+}
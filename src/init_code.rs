@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::abi::{self, ParamType};
+
+/// Contract creation code assembled on the host, ready to be hashed into the
+/// `init_code_hash` of a `Config`.
+pub struct InitCode {
+    /// Creation bytecode with any constructor arguments appended.
+    pub code: Vec<u8>,
+    /// Where the bytecode came from, for display.
+    pub source: String,
+}
+
+impl InitCode {
+    /// Build the init code from `source` (hex bytecode, a file containing
+    /// hex bytecode, or a Foundry/Hardhat JSON artifact) followed by the
+    /// constructor arguments.
+    ///
+    /// When the source is an artifact with a constructor in its ABI, each
+    /// argument is a value to encode against the constructor's inputs.
+    /// Otherwise the arguments are taken to be already ABI-encoded hex and
+    /// are appended as-is.
+    pub fn load(source: &str, constructor_args: &[String]) -> Result<Self, Box<dyn Error>> {
+        let (bytecode, constructor, description) = if looks_like_hex(source) {
+            (decode_bytecode(source)?, None, String::from("command line"))
+        } else {
+            let path = Path::new(source);
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("could not read init code from '{}': {}", source, e))?;
+
+            match serde_json::from_str::<Value>(&contents) {
+                Ok(artifact) if artifact.is_object() => {
+                    let artifact = Artifact::parse(&artifact)
+                        .map_err(|e| format!("invalid artifact '{}': {}", source, e))?;
+                    (artifact.bytecode, artifact.constructor, format!("artifact {}", source))
+                }
+                _ => (decode_bytecode(contents.trim())?, None, format!("file {}", source)),
+            }
+        };
+
+        if bytecode.is_empty() {
+            return Err(format!("init code from {} is empty", description).into());
+        }
+
+        let encoded_args = match constructor {
+            Some(inputs) => abi::encode_args(&inputs, constructor_args)
+                .map_err(|e| format!("invalid constructor arguments: {}", e))?,
+            None => {
+                let mut encoded = Vec::new();
+                for arg in constructor_args {
+                    encoded.extend_from_slice(&decode_bytecode(arg).map_err(|e| {
+                        format!("constructor arguments must be ABI-encoded hex without an artifact ABI: {}", e)
+                    })?);
+                }
+                encoded
+            }
+        };
+
+        let mut code = bytecode;
+        code.extend_from_slice(&encoded_args);
+
+        Ok(InitCode { code, source: description })
+    }
+
+    /// The keccak-256 hash of the init code.
+    pub fn hash(&self) -> [u8; 32] {
+        crate::keccak256(&self.code)
+    }
+}
+
+/// Load raw bytes such as calldata from hex or from a file containing hex.
+pub fn load_hex(field: &str, source: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let digits = if looks_like_hex(source) {
+        source.to_string()
+    } else {
+        fs::read_to_string(source)
+            .map_err(|e| format!("could not read {} from '{}': {}", field, source, e))?
+            .trim()
+            .to_string()
+    };
+    let digits = digits.strip_prefix("0x").unwrap_or(&digits);
+    hex::decode(digits).map_err(|e| format!("{} is not valid hex: {}", field, e).into())
+}
+
+/// The parts of a compiler artifact needed to build init code.
+struct Artifact {
+    bytecode: Vec<u8>,
+    /// Constructor inputs, or `None` if the artifact carries no ABI.
+    constructor: Option<Vec<ParamType>>,
+}
+
+impl Artifact {
+    /// Extract the creation bytecode and constructor inputs from a Foundry
+    /// (`bytecode.object`) or Hardhat (`bytecode`) artifact.
+    fn parse(artifact: &Value) -> Result<Self, Box<dyn Error>> {
+        let (object, link_references) = match &artifact["bytecode"] {
+            Value::String(object) => (object.as_str(), &artifact["linkReferences"]),
+            Value::Object(bytecode) => (
+                bytecode.get("object").and_then(Value::as_str).ok_or("bytecode.object is missing")?,
+                &artifact["bytecode"]["linkReferences"],
+            ),
+            _ => return Err("no bytecode found".into()),
+        };
+
+        if link_references.as_object().is_some_and(|refs| !refs.is_empty()) {
+            return Err("bytecode has unlinked library references".into());
+        }
+
+        let bytecode = decode_bytecode(object)?;
+
+        let constructor = match artifact["abi"].as_array() {
+            Some(abi) => match abi.iter().find(|item| item["type"] == "constructor") {
+                Some(constructor) => Some(
+                    constructor["inputs"]
+                        .as_array()
+                        .map(|inputs| inputs.iter().map(ParamType::from_json).collect::<Result<Vec<_>, _>>())
+                        .unwrap_or_else(|| Ok(Vec::new()))?,
+                ),
+                // an ABI without a constructor takes no arguments
+                None => Some(Vec::new()),
+            },
+            None => None,
+        };
+
+        Ok(Artifact { bytecode, constructor })
+    }
+}
+
+fn looks_like_hex(source: &str) -> bool {
+    let digits = source.strip_prefix("0x").unwrap_or(source);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn decode_bytecode(hex_str: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let digits = hex_str.strip_prefix("0x").unwrap_or(hex_str);
+    hex::decode(digits).map_err(|e| format!("bytecode is not valid hex: {}", e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+    use std::process;
+
+    fn constructor() -> Value {
+        json!([
+            { "type": "function", "name": "owner", "inputs": [], "outputs": [{ "type": "address" }] },
+            {
+                "type": "constructor",
+                "inputs": [
+                    { "name": "owner", "type": "address" },
+                    { "name": "limits", "type": "tuple", "components": [{ "type": "uint64" }, { "type": "bool" }] }
+                ]
+            }
+        ])
+    }
+
+    // Write `artifact` to a temporary file and load it with `args`
+    fn load(name: &str, artifact: &Value, args: &[&str]) -> Result<InitCode, Box<dyn Error>> {
+        let path = env::temp_dir().join(format!("create2gpu-artifact-{}-{}.json", name, process::id()));
+        fs::write(&path, artifact.to_string()).unwrap();
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let init_code = InitCode::load(path.to_str().unwrap(), &args);
+        fs::remove_file(&path).unwrap();
+        init_code
+    }
+
+    fn expected_code() -> String {
+        format!("6080604052{:0>64}{:064x}{:064x}", "00000000000000000000000000000000000000aa", 10, 1)
+    }
+
+    #[test]
+    fn foundry_artifact_encodes_constructor_arguments() {
+        let artifact = json!({
+            "abi": constructor(),
+            "bytecode": { "object": "0x6080604052", "linkReferences": {} },
+            "deployedBytecode": { "object": "0x6080" },
+        });
+        let init_code = load("foundry", &artifact, &["0x00000000000000000000000000000000000000aa", "(10,true)"]).unwrap();
+        assert_eq!(hex::encode(&init_code.code), expected_code());
+        assert!(init_code.source.starts_with("artifact "));
+    }
+
+    #[test]
+    fn hardhat_artifact_encodes_constructor_arguments() {
+        let artifact = json!({
+            "_format": "hh-sol-artifact-1",
+            "abi": constructor(),
+            "bytecode": "0x6080604052",
+            "linkReferences": {},
+        });
+        let init_code = load("hardhat", &artifact, &["0x00000000000000000000000000000000000000aa", "(10,true)"]).unwrap();
+        assert_eq!(hex::encode(&init_code.code), expected_code());
+    }
+
+    #[test]
+    fn artifact_problems_are_reported() {
+        let unlinked = json!({ "bytecode": "0x6080", "linkReferences": { "src/Lib.sol": { "Lib": [] } } });
+        assert_eq!(Artifact::parse(&unlinked).err().unwrap().to_string(), "bytecode has unlinked library references");
+
+        let artifact = json!({ "abi": constructor(), "bytecode": "0x6080" });
+        let error = load("arity", &artifact, &["0x00000000000000000000000000000000000000aa"]).err().unwrap().to_string();
+        assert_eq!(error, "invalid constructor arguments: expected 2 arguments, got 1");
+    }
+
+    #[test]
+    fn hex_init_code_takes_encoded_arguments() {
+        let init_code = InitCode::load("0x6080", &["0x01".to_string(), "02".to_string()]).unwrap();
+        assert_eq!(init_code.code, vec![0x60, 0x80, 0x01, 0x02]);
+        assert_eq!(init_code.source, "command line");
+        assert!(InitCode::load("0x", &[]).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::dashboard::restore_terminal;
+use crate::output::info;
+use crate::Config;
+
+/// Ctrl-C presses since the handler was installed.
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+/// How often the watcher thread checks for a Ctrl-C.
+const POLL: Duration = Duration::from_millis(100);
+
+// Only async-signal-safe calls may be made here: the first press is left to
+// the watcher thread, the second exits at once
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    if INTERRUPTS.fetch_add(1, Ordering::SeqCst) > 0 {
+        restore_terminal();
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// Stop the search in `config` between batches on the first Ctrl-C, so
+/// that it can report what it has found, and exit at once on the second.
+/// Only Unix is supported; elsewhere Ctrl-C ends the search at once as
+/// before.
+pub fn stop_on_interrupt(config: &Config) -> Result<(), String> {
+    if !install_handler()? {
+        return Ok(());
+    }
+
+    let config = config.clone();
+    thread::spawn(move || {
+        while INTERRUPTS.load(Ordering::SeqCst) == 0 {
+            thread::sleep(POLL);
+        }
+        config.stop.store(true, Ordering::SeqCst);
+        config.dashboard.print_above(&config, || {
+            info(&config, "Stopping after the current batches; press Ctrl-C again to exit now")
+        });
+    });
+    Ok(())
+}
+
+// Install the SIGINT handler, or return false where there is none
+#[cfg(unix)]
+fn install_handler() -> Result<bool, String> {
+    let handler = on_interrupt as extern "C" fn(libc::c_int);
+    if unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) } == libc::SIG_ERR {
+        return Err("could not install the Ctrl-C handler".to_string());
+    }
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn install_handler() -> Result<bool, String> {
+    Ok(false)
+}
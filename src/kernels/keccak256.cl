@@ -42,6 +42,12 @@ char get_hex_char(uchar byte, bool high_nibble);
 # pragma OPENCL EXTENSION   cl_amd_media_ops : enable
 #endif
 
+// 64-bit atomic max, used by scoreMessage, which is left out on devices
+// without it so that the other kernels still build there
+#ifdef cl_khr_int64_extended_atomics
+#pragma OPENCL EXTENSION cl_khr_int64_extended_atomics : enable
+#endif
+
 typedef union _nonce_t
 {
   ulong   uint64_t;
@@ -189,6 +195,76 @@ static inline void keccakf(ulong *a)
 #undef o
 }
 
+// The full permutation, for hashes whose whole output is needed (such as
+// salts derived from the nonce)
+static inline void keccakfFull(ulong *a)
+{
+  ulong b[5];
+  ulong t;
+
+  iteration(0x0000000000000001);
+  iteration(0x0000000000008082);
+  iteration(0x800000000000808a);
+  iteration(0x8000000080008000);
+  iteration(0x000000000000808b);
+  iteration(0x0000000080000001);
+  iteration(0x8000000080008081);
+  iteration(0x8000000000008009);
+  iteration(0x000000000000008a);
+  iteration(0x0000000000000088);
+  iteration(0x0000000080008009);
+  iteration(0x000000008000000a);
+  iteration(0x000000008000808b);
+  iteration(0x800000000000008b);
+  iteration(0x8000000000008089);
+  iteration(0x8000000000008003);
+  iteration(0x8000000000008002);
+  iteration(0x8000000000000080);
+  iteration(0x000000000000800a);
+  iteration(0x800000008000000a);
+  iteration(0x8000000080008081);
+  iteration(0x8000000000008080);
+  iteration(0x0000000080000001);
+  iteration(0x8000000080008008);
+}
+
+// Absorb one byte of a message of any length into a keccak-256 sponge,
+// permuting each time a block fills. position is the byte offset in the
+// current block
+static inline void absorbByte(ulong *state, uint *position, uchar byte)
+{
+  ((uchar *) state)[*position] ^= byte;
+  *position += 1;
+  if (*position == 136) {
+    keccakfFull(state);
+    *position = 0;
+  }
+}
+
+// Pad the last block of a message absorbed with absorbByte and permute; the
+// digest is then in the first 32 bytes of the state
+static inline void finishSponge(ulong *state, uint position)
+{
+  ((uchar *) state)[position] ^= 0x01;
+  ((uchar *) state)[135] ^= 0x80;
+  keccakfFull(state);
+}
+
+// Characters of the identifier suffixes tried by hashSelector and
+// hashIdentifiers
+__constant uchar suffixAlphabet[63] = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// Absorb the suffix for a nonce: its bijective base-62 digits, least
+// significant first, so lower nonces have shorter suffixes
+static inline void absorbSuffix(ulong *state, uint *position, ulong nonce)
+{
+  ulong digits = nonce;
+  do {
+    absorbByte(state, position, suffixAlphabet[digits % 62]);
+    digits /= 62;
+  } while (digits-- > 0);
+}
+
 #define hasTotal(d, S) ( \
   ((d[0] == S)) + ((d[1] == S)) + ((d[2] == S)) + ((d[3] == S)) + \
   ((d[4] == S)) + (!(d[5] == S)) + (!(d[6] == S)) + (!(d[7] == S)) + \
@@ -236,9 +312,17 @@ static inline char nibbleToHexChar(uchar nibble) {
   return nibble < 10 ? '0' + nibble : 'a' + (nibble - 10);
 }
 
-// Function to check if the address starts with a specific prefix and/or ends with a specific suffix
+// Function to check if the address starts with a specific prefix and/or ends with a specific suffix,
+// and has the required bits in its last two bytes
 static inline bool matchesAddressCriteria(uchar const *d, __constant uchar const *prefix, int prefixLen, 
-                                         __constant uchar const *suffix, int suffixLen) {
+                                         __constant uchar const *suffix, int suffixLen,
+                                         __constant uchar const *tail) {
+  // Check the masked bits of the last two bytes (tail[0..2] is the mask,
+  // tail[2..4] the required value); used for Uniswap v4 hook flags
+  if ((d[18] & tail[0]) != tail[2] || (d[19] & tail[1]) != tail[3]) {
+    return false;
+  }
+
   // Check prefix if specified
   if (prefixLen > 0) {
     for (int i = 0; i < prefixLen; i++) {
@@ -287,88 +371,533 @@ static inline bool matchesAddressCriteria(uchar const *d, __constant uchar const
   return true;
 }
 
-__kernel void hashMessage(
-  __constant uchar const *d_message,
-  __constant uint const *d_nonce,
-  __global volatile ulong *restrict solutions,
-  __global volatile uint *restrict has_solution,
-  __global volatile uchar *restrict digest_output
-) {
-  ulong spongeBuffer[25];
-
-#define sponge ((uchar *) spongeBuffer)
-#define digest (sponge + 12)
-
+// Fill the sponge with the CREATE2 preimage for a nonce, padded for a single
+// keccak-256 block: 0xff ++ deployer ++ salt ++ init code hash. saltScheme
+// points at the scheme byte in the message: with schemes 0 and 2 the salt is
+// 24 bytes of head followed by the nonce, where the head's length and leading
+// bytes (none, the caller, or all 24 high bytes) follow the scheme byte and
+// the rest of it is zero; with scheme 1 it is
+// keccak256(prefix ++ head ++ uint64(nonce)), where the scheme byte is
+// followed by the length of the prefix's last partial block, the keccak state
+// after the prefix's full blocks, the 24-byte head and the partial block.
+// addressScheme points at the address scheme
+// byte: with scheme 1 the zkSync Era preimage is built instead, ending in a
+// second block
+static inline void buildSponge(uchar *s, __constant uchar const *d_message,
+                               __constant uchar const *saltScheme,
+                               __constant uchar const *addressScheme, ulong value) {
   nonce_t nonce;
 
   // Initialize sponge with zeros
   for (int i = 0; i < 200; i++) {
-    sponge[i] = 0;
+    s[i] = 0;
   }
 
   // Start with 0xff prefix for CREATE2
-  sponge[0] = 0xff;
+  s[0] = 0xff;
 
   // Copy deployer address (factory address)
   for (int i = 0; i < 20; i++) {
-    sponge[i + 1] = d_message[i];
+    s[i + 1] = d_message[i];
   }
 
   // populate the nonce for the salt
-  nonce.uint32_t[0] = get_global_id(0);
-  nonce.uint32_t[1] = d_nonce[0];
+  nonce.uint64_t = value;
+
+  if (saltScheme[0] == 1) {
+    // Hash the prefix and the nonce as a big-endian uint256 into the salt.
+    // The host absorbs the prefix's full blocks; the rest of the prefix and
+    // the nonce take one more block, or two if they don't fit in one
+    ulong saltBuffer[25];
+    uchar *h = (uchar *) saltBuffer;
+    uchar block[272];
+    int tailLen = saltScheme[1];
+    __constant uchar const *midstate = &saltScheme[2];
+    __constant uchar const *head = &saltScheme[202];
+    __constant uchar const *tail = &saltScheme[226];
+    int end = tailLen + 32 < 136 ? 136 : 272;
+
+    for (int i = 0; i < 200; i++) {
+      h[i] = midstate[i];
+    }
+    for (int i = 0; i < end; i++) {
+      block[i] = 0;
+    }
+    for (int i = 0; i < tailLen; i++) {
+      block[i] = tail[i];
+    }
+    for (int i = 0; i < 24; i++) {
+      block[tailLen + i] = head[i];
+    }
+    for (int i = 0; i < 8; i++) {
+      block[tailLen + 24 + i] = nonce.uint8_t[7 - i];
+    }
+
+    // padding; the two bytes coincide when the nonce ends a block exactly
+    block[tailLen + 32] ^= 0x01;
+    block[end - 1] ^= 0x80;
+
+    for (int b = 0; b < end; b += 136) {
+      for (int i = 0; i < 136; i++) {
+        h[i] ^= block[b + i];
+      }
+      keccakfFull(saltBuffer);
+    }
+
+    for (int i = 0; i < 32; i++) {
+      s[i + 21] = h[i];
+    }
+  } else {
+    // Copy the salt (32 bytes, with the nonce at the end)
+    for (int i = 0; i < 24; i++) {
+      s[i + 21] = 0; // First 24 bytes of salt are zeros
+    }
 
-  // Copy the salt (32 bytes, with the nonce at the end)
-  for (int i = 0; i < 24; i++) {
-    sponge[i + 21] = 0; // First 24 bytes of salt are zeros
+    // ...unless the scheme puts the caller or high bytes in front
+    for (int i = 0; i < saltScheme[1]; i++) {
+      s[i + 21] = saltScheme[2 + i];
+    }
+
+    // Last 8 bytes of salt are the nonce
+    s[21 + 24] = nonce.uint8_t[0];
+    s[21 + 25] = nonce.uint8_t[1];
+    s[21 + 26] = nonce.uint8_t[2];
+    s[21 + 27] = nonce.uint8_t[3];
+    s[21 + 28] = nonce.uint8_t[4];
+    s[21 + 29] = nonce.uint8_t[5];
+    s[21 + 30] = nonce.uint8_t[6];
+    s[21 + 31] = nonce.uint8_t[7];
+  }
+
+  if (addressScheme[0] == 1) {
+    // zkSync Era: keccak256("zksyncCreate2") ++ uint256(sender) ++ salt ++
+    // bytecode hash ++ keccak256(constructor input) is 160 bytes, so absorb
+    // the first block here and leave the second in the sponge
+    uchar salt[32];
+    for (int i = 0; i < 32; i++) {
+      salt[i] = s[i + 21];
+    }
+    for (int i = 0; i < 200; i++) {
+      s[i] = 0;
+    }
+    for (int i = 0; i < 32; i++) {
+      s[i] = addressScheme[1 + i];
+    }
+    for (int i = 0; i < 20; i++) {
+      s[i + 44] = d_message[i];
+    }
+    for (int i = 0; i < 32; i++) {
+      s[i + 64] = salt[i];
+      s[i + 96] = d_message[i + 20];
+    }
+    for (int i = 0; i < 8; i++) {
+      s[i + 128] = addressScheme[33 + i];
+    }
+
+    keccakfFull((ulong *) s);
+
+    for (int i = 0; i < 24; i++) {
+      s[i] ^= addressScheme[41 + i];
+    }
+    s[24] ^= 0x01;
+    s[135] ^= 0x80;
+    return;
   }
-  
-  // Last 8 bytes of salt are the nonce
-  sponge[21 + 24] = nonce.uint8_t[0];
-  sponge[21 + 25] = nonce.uint8_t[1];
-  sponge[21 + 26] = nonce.uint8_t[2];
-  sponge[21 + 27] = nonce.uint8_t[3];
-  sponge[21 + 28] = nonce.uint8_t[4];
-  sponge[21 + 29] = nonce.uint8_t[5];
-  sponge[21 + 30] = nonce.uint8_t[6];
-  sponge[21 + 31] = nonce.uint8_t[7];
 
   // Copy the init code hash (32 bytes)
   for (int i = 0; i < 32; i++) {
-    sponge[i + 53] = d_message[i + 20];
+    s[i + 53] = d_message[i + 20];
+  }
+
+  // begin padding based on message length (0xff + 20 bytes + 32 bytes + 32 bytes = 85 bytes)
+  s[85] = 0x01;
+
+  // end padding
+  s[135] = 0x80;
+}
+
+#define NIBBLE(d, i) (((i) % 2 == 0) ? (d[(i) / 2] >> 4) : (d[(i) / 2] & 0xf))
+
+// Score from the Uniswap v4 address challenge (VanityAddressLib.score):
+// 10 points per leading zero nibble, nothing unless the first non-zero
+// nibble is a 4, 60 points for exactly four leading 4s or 40 for more, 1
+// point per 4 anywhere, and 20 points if the address ends in 4444
+static inline uint uniswapV4Score(uchar const *d) {
+  uint i = 0;
+  while (i < 40 && NIBBLE(d, i) == 0) {
+    i++;
+  }
+  uint leadingZeroes = i;
+  while (i < 40 && NIBBLE(d, i) == 4) {
+    i++;
+  }
+  uint leadingFours = i - leadingZeroes;
+
+  if (leadingFours == 0) {
+    return 0;
+  }
+
+  uint score = leadingZeroes * 10;
+  if (leadingFours == 4) {
+    score += 60;
+  } else if (leadingFours > 4) {
+    score += 40;
+  }
+
+  for (uint j = 0; j < 40; j++) {
+    if (NIBBLE(d, j) == 4) {
+      score += 1;
+    }
+  }
+
+  if (d[18] == 0x44 && d[19] == 0x44) {
+    score += 20;
+  }
+
+  return score;
+}
+
+// Score an address with the scoring function selected by the host
+static inline uint scoreAddress(uchar const *d, uint scoreFunction) {
+  switch (scoreFunction) {
+    case 0: return uniswapV4Score(d);
+    default: return 0;
   }
+}
+
+__kernel void hashMessage(
+  __constant uchar const *d_message,
+  __constant uint const *d_nonce,
+  uint iterations,
+  __global volatile ulong *restrict solutions,
+  __global volatile uint *restrict has_solution,
+  __global volatile uchar *restrict digest_output
+) {
+  ulong spongeBuffer[25];
+
+#define sponge ((uchar *) spongeBuffer)
+#define digest (sponge + 12)
 
   // Get the prefix and suffix to check for and their lengths
   int prefixLen = d_message[52];
   __constant uchar const *prefix = &d_message[53];
   int suffixLen = d_message[53 + prefixLen];
   __constant uchar const *suffix = &d_message[54 + prefixLen];
+  __constant uchar const *tail = &d_message[54 + prefixLen + suffixLen];
+  __constant uchar const *saltScheme = &d_message[58 + prefixLen + suffixLen];
+  __constant uchar const *addressScheme = saltScheme + (saltScheme[0] == 1 ? 226 : 2) + saltScheme[1];
+
+  // the batch starts at this nonce; each work item covers `iterations`
+  // consecutive nonces after it
+  ulong base = ((ulong) d_nonce[1] << 32) | d_nonce[0];
+  ulong first = base + (ulong) get_global_id(0) * iterations;
+
+  for (uint iteration = 0; iteration < iterations; iteration++) {
+    ulong nonce = first + iteration;
+    buildSponge(sponge, d_message, saltScheme, addressScheme, nonce);
+
+    // Apply keccakf
+    keccakf(spongeBuffer);
+
+    // Check if the address matches the criteria. Several work items can
+    // match in one batch, so the first to claim the slot writes the nonce
+    // and digest and the others leave them alone
+    if (matchesAddressCriteria(digest, prefix, prefixLen, suffix, suffixLen, tail)
+        && atomic_cmpxchg(has_solution, 0, 1) == 0) {
+      solutions[0] = nonce;
+
+      // Copy the digest to the output buffer
+      for (int i = 0; i < 200; i++) {
+        digest_output[i] = sponge[i];
+      }
+    }
+  }
 
-  // begin padding based on message length (0xff + 20 bytes + 32 bytes + 32 bytes = 85 bytes)
-  sponge[85] = 0x01;
+#undef digest
+#undef sponge
+}
 
-  // fill padding
-  for (int i = 86; i < 135; i++) {
-    sponge[i] = 0;
+#ifdef cl_khr_int64_extended_atomics
+// Score every nonce in the batch and keep the best one scoring above the
+// threshold (the best score seen so far by any device). best[0] packs the
+// score in its high 32 bits and the nonce's offset from the batch base in
+// its low 32 bits, so a single atomic max keeps both consistent
+__kernel void scoreMessage(
+  __constant uchar const *d_message,
+  __constant uint const *d_nonce,
+  uint iterations,
+  uint scoreFunction,
+  uint threshold,
+  __global volatile ulong *restrict best
+) {
+  ulong spongeBuffer[25];
+
+#define sponge ((uchar *) spongeBuffer)
+#define digest (sponge + 12)
+
+  // the patterns are unused, but the salt and address schemes follow them
+  int prefixLen = d_message[52];
+  int suffixLen = d_message[53 + prefixLen];
+  __constant uchar const *saltScheme = &d_message[58 + prefixLen + suffixLen];
+  __constant uchar const *addressScheme = saltScheme + (saltScheme[0] == 1 ? 226 : 2) + saltScheme[1];
+
+  ulong base = ((ulong) d_nonce[1] << 32) | d_nonce[0];
+  uint first = (uint) get_global_id(0) * iterations;
+
+  for (uint iteration = 0; iteration < iterations; iteration++) {
+    uint offset = first + iteration;
+    buildSponge(sponge, d_message, saltScheme, addressScheme, base + offset);
+
+    keccakf(spongeBuffer);
+
+    uint score = scoreAddress(digest, scoreFunction);
+    if (score > threshold) {
+      atom_max(best, ((ulong) score << 32) | offset);
+    }
   }
 
-  // end padding
-  sponge[135] = 0x80;
+#undef digest
+#undef sponge
+}
+#endif
 
-  // Apply keccakf
-  keccakf(spongeBuffer);
+// No constraint on the last two address bytes, for criteria without hook
+// flags
+__constant uchar noTail[4] = { 0, 0, 0, 0 };
 
-  // Check if the address matches the criteria
-  if (matchesAddressCriteria(digest, prefix, prefixLen, suffix, suffixLen)) {
-    // Found a solution
-    solutions[0] = nonce.uint64_t;
-    has_solution[0] = 1;
-    
-    // Copy the digest to the output buffer
-    for (int i = 0; i < 200; i++) {
-      digest_output[i] = sponge[i];
+// Test every nonce in the batch like hashMessage, and also record near-miss
+// shares: nonces whose address has the first sharePrefixLen digits of the
+// prefix and the last shareSuffixLen digits of the suffix, ignoring the
+// tail. A match is also a share. Up to maxHits shares are stored in hits;
+// hitCount counts them all, so a count above maxHits means some were dropped
+__kernel void hashShares(
+  __constant uchar const *d_message,
+  __constant uint const *d_nonce,
+  uint iterations,
+  uint sharePrefixLen,
+  uint shareSuffixLen,
+  __global volatile ulong *restrict solutions,
+  __global volatile uint *restrict has_solution,
+  __global volatile uint *restrict hitCount,
+  __global volatile ulong *restrict hits,
+  uint maxHits
+) {
+  ulong spongeBuffer[25];
+
+#define sponge ((uchar *) spongeBuffer)
+#define digest (sponge + 12)
+
+  int prefixLen = d_message[52];
+  __constant uchar const *prefix = &d_message[53];
+  int suffixLen = d_message[53 + prefixLen];
+  __constant uchar const *suffix = &d_message[54 + prefixLen];
+  __constant uchar const *tail = &d_message[54 + prefixLen + suffixLen];
+  __constant uchar const *saltScheme = &d_message[58 + prefixLen + suffixLen];
+  __constant uchar const *addressScheme = saltScheme + (saltScheme[0] == 1 ? 226 : 2) + saltScheme[1];
+
+  ulong base = ((ulong) d_nonce[1] << 32) | d_nonce[0];
+  ulong first = base + (ulong) get_global_id(0) * iterations;
+
+  for (uint iteration = 0; iteration < iterations; iteration++) {
+    ulong nonce = first + iteration;
+    buildSponge(sponge, d_message, saltScheme, addressScheme, nonce);
+    keccakf(spongeBuffer);
+
+    if (matchesAddressCriteria(digest, prefix, sharePrefixLen, suffix + suffixLen - shareSuffixLen, shareSuffixLen, noTail)) {
+      uint slot = atomic_inc(hitCount);
+      if (slot < maxHits) {
+        hits[slot] = nonce;
+      }
+
+      if (matchesAddressCriteria(digest, prefix, prefixLen, suffix, suffixLen, tail)
+          && atomic_cmpxchg(has_solution, 0, 1) == 0) {
+        solutions[0] = nonce;
+      }
     }
   }
+
+#undef digest
+#undef sponge
+}
+
+// Hash function signatures name_<suffix>(params) and keep the lowest nonce
+// whose selector has at least zeroBytes zero bytes and is below threshold.
+// The message holds the length and bytes of the name, then the length and
+// bytes of the parameter list. solutions[0] keeps the nonce's offset from the
+// batch base, so that a 32-bit atomic min works on every device, and must
+// start out all ones; has_solution[0] is set once any nonce matches, since
+// the last offset of a 2^32 batch is all ones too
+__kernel void hashSelector(
+  __constant uchar const *d_message,
+  __constant uint const *d_nonce,
+  uint iterations,
+  uint zeroBytes,
+  ulong threshold,
+  __global volatile uint *restrict solutions,
+  __global volatile uint *restrict has_solution
+) {
+  ulong state[25];
+  uchar *d = (uchar *) state;
+
+  int nameLen = d_message[0];
+  __constant uchar const *name = &d_message[1];
+  int paramsLen = d_message[1 + nameLen];
+  __constant uchar const *params = &d_message[2 + nameLen];
+
+  ulong base = ((ulong) d_nonce[1] << 32) | d_nonce[0];
+  uint first = (uint) get_global_id(0) * iterations;
+
+  for (uint iteration = 0; iteration < iterations; iteration++) {
+    uint offset = first + iteration;
+    ulong nonce = base + offset;
+    uint position = 0;
+
+    for (int i = 0; i < 25; i++) {
+      state[i] = 0;
+    }
+    for (int i = 0; i < nameLen; i++) {
+      absorbByte(state, &position, name[i]);
+    }
+    absorbByte(state, &position, '_');
+
+    absorbSuffix(state, &position, nonce);
+
+    for (int i = 0; i < paramsLen; i++) {
+      absorbByte(state, &position, params[i]);
+    }
+    finishSponge(state, position);
+
+    uint zeros = (d[0] == 0) + (d[1] == 0) + (d[2] == 0) + (d[3] == 0);
+    ulong selector = ((ulong) d[0] << 24) | ((ulong) d[1] << 16) | ((ulong) d[2] << 8) | d[3];
+    if (zeros >= zeroBytes && selector < threshold) {
+      atomic_min(solutions, offset);
+      atomic_xchg(has_solution, 1);
+    }
+  }
+}
+
+// Compare a digest with a target of len bytes, like memcmp
+static inline int compareTarget(uchar const *d, __global uchar const *target, uint len)
+{
+  for (uint i = 0; i < len; i++) {
+    if (d[i] != target[i]) {
+      return d[i] < target[i] ? -1 : 1;
+    }
+  }
+  return 0;
+}
+
+// Hash identifiers word[_suffix](params) and record the nonces whose hash
+// starts with one of the targets. A nonce picks the word nonce % wordCount
+// and the suffix index nonce / wordCount, where index 0 is no suffix and
+// index i the suffix of i - 1, so every word is tried with each suffix
+// before the next. The words are stored back to back, word i spanning
+// wordOffsets[i]..wordOffsets[i + 1]; the message holds the length and bytes
+// of the parameter list. The targets are targetLen bytes each and sorted.
+// Nonces from end on are outside the search space and skipped. Up to
+// maxHits nonces are stored in hits; hitCount counts them all, so a count
+// above maxHits means some were dropped
+__kernel void hashIdentifiers(
+  __global uchar const *words,
+  __global uint const *wordOffsets,
+  uint wordCount,
+  __constant uchar const *d_message,
+  __constant uint const *d_nonce,
+  uint iterations,
+  ulong end,
+  __global uchar const *targets,
+  uint targetCount,
+  uint targetLen,
+  __global volatile uint *restrict hitCount,
+  __global volatile ulong *restrict hits,
+  uint maxHits
+) {
+  ulong state[25];
+  uchar *d = (uchar *) state;
+
+  int paramsLen = d_message[0];
+  __constant uchar const *params = &d_message[1];
+
+  ulong base = ((ulong) d_nonce[1] << 32) | d_nonce[0];
+  ulong first = base + (ulong) get_global_id(0) * iterations;
+
+  for (uint iteration = 0; iteration < iterations; iteration++) {
+    ulong nonce = first + iteration;
+    if (nonce >= end) {
+      return;
+    }
+
+    uint word = nonce % wordCount;
+    ulong suffix = nonce / wordCount;
+    uint position = 0;
+
+    for (int i = 0; i < 25; i++) {
+      state[i] = 0;
+    }
+    for (uint i = wordOffsets[word]; i < wordOffsets[word + 1]; i++) {
+      absorbByte(state, &position, words[i]);
+    }
+    if (suffix > 0) {
+      absorbByte(state, &position, '_');
+      absorbSuffix(state, &position, suffix - 1);
+    }
+    for (int i = 0; i < paramsLen; i++) {
+      absorbByte(state, &position, params[i]);
+    }
+    finishSponge(state, position);
+
+    // binary search the sorted targets
+    uint low = 0;
+    uint high = targetCount;
+    while (low < high) {
+      uint middle = low + (high - low) / 2;
+      int order = compareTarget(d, &targets[middle * targetLen], targetLen);
+      if (order == 0) {
+        uint slot = atomic_inc(hitCount);
+        if (slot < maxHits) {
+          hits[slot] = nonce;
+        }
+        break;
+      }
+      if (order < 0) {
+        high = middle;
+      } else {
+        low = middle + 1;
+      }
+    }
+  }
+}
+
+// Hash count messages of messageLen bytes each, stored back to back, and
+// write each 32-byte digest to digests at the same index
+__kernel void hashBatch(
+  __global uchar const *messages,
+  uint messageLen,
+  uint count,
+  __global uchar *restrict digests
+) {
+  ulong state[25];
+  uchar *d = (uchar *) state;
+
+  uint index = get_global_id(0);
+  if (index >= count) {
+    return;
+  }
+
+  __global uchar const *message = &messages[(ulong) index * messageLen];
+  uint position = 0;
+
+  for (int i = 0; i < 25; i++) {
+    state[i] = 0;
+  }
+  for (uint i = 0; i < messageLen; i++) {
+    absorbByte(state, &position, message[i]);
+  }
+  finishSponge(state, position);
+
+  for (int i = 0; i < 32; i++) {
+    digests[(ulong) index * 32 + i] = d[i];
+  }
 }
 
 // Helper function implementation at the end of the file
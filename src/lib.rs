@@ -1,25 +1,71 @@
-mod gpu;
+pub mod abi;
+pub mod account;
+pub mod bench;
+pub mod checkpoint;
+pub mod clone;
+pub mod deploy;
+pub mod cpu;
+pub mod dashboard;
+pub mod difficulty;
+pub mod factory;
+pub mod gpu;
+pub mod hooks;
+pub mod identifier;
+pub mod init_code;
+pub mod interrupt;
+pub mod metrics;
+pub mod output;
+pub mod parse;
+pub mod pool;
+#[cfg(test)]
+mod port;
+pub mod safe;
+pub mod score;
+pub mod search;
+pub mod selector;
+pub mod serve;
+#[cfg(any(test, feature = "testing"))]
+#[doc(hidden)]
+pub mod testing;
+pub mod tuning;
+pub mod zksync;
 
 extern crate byteorder;
 extern crate console;
 extern crate fs2;
 extern crate hex;
 extern crate itertools;
+#[cfg(unix)]
+extern crate libc;
 extern crate ocl;
 extern crate ocl_extras;
 extern crate rand;
 extern crate rayon;
 extern crate separator;
+extern crate serde;
+extern crate serde_json;
 extern crate terminal_size;
 extern crate tiny_keccak;
 
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64};
+use std::sync::{Arc, Mutex, OnceLock};
+
 use hex::FromHex;
 
-// Export only the gpu function
+pub use cpu::cpu;
+pub use dashboard::Dashboard;
 pub use gpu::gpu;
+pub use init_code::InitCode;
+pub use metrics::Metrics;
+pub use output::{OutputFormat, Solution};
+pub use score::ScoreFunction;
+pub use search::{AddressScheme, HighBytes, SaltScheme};
 
-// workset size (tweak this!)
-const WORK_SIZE: u32 = 0x4000000; // max. 0x15400000 to abs. max 0xffffffff
+/// Default global work size for devices without a tuning entry. Run
+/// `create2gpu bench` to find a better one per device.
+pub const WORK_SIZE: u32 = 0x4000000; // max. 0x15400000 to abs. max 0xffffffff
 
 /// Requires three hex-encoded arguments: the address of the contract that will
 /// be calling CREATE2, the address of the caller of said contract *(assuming
@@ -42,6 +88,18 @@ pub struct Config {
     pub starts_with: String,  // Field for the prefix to search for
     pub ends_with: String,    // New field for the suffix to search for
     pub case_sensitive: bool, // Field for case-sensitive matching
+    pub format: OutputFormat, // How progress and solutions are reported
+    pub tuning_file: Option<PathBuf>, // Benchmarked launch settings per device
+    pub hook_flags: Option<u16>, // Exact Uniswap v4 hook permission bits for the address
+    pub score: Option<ScoreFunction>, // Keep the best-scoring address instead of matching a pattern
+    pub best_score: Arc<AtomicU32>, // Best score found so far, shared by all devices
+    pub stop: Arc<AtomicBool>, // Set to stop the search between batches, shared by all devices
+    pub paused: Arc<AtomicBool>, // While set, devices wait between batches
+    pub attempts: Arc<AtomicU64>, // Nonces tested so far by all devices
+    pub metrics: Arc<Metrics>, // Counters served by --metrics-addr, shared by all devices
+    pub dashboard: Arc<Dashboard>, // Text-mode status screen, shared by all devices
+    pub salt_scheme: SaltScheme, // How the CREATE2 salt is derived from each nonce
+    pub address_scheme: AddressScheme, // How the chain derives the address from the salt
 }
 
 /// Validate the provided arguments and construct the Config struct.
@@ -185,7 +243,7 @@ impl Config {
         // Validate prefix if provided
         let prefix = if let Some(prefix_str) = prefix_string {
             // Validate that the prefix contains only valid hex characters
-            if !prefix_str.chars().all(|c| c.is_digit(16)) {
+            if !prefix_str.chars().all(|c| c.is_ascii_hexdigit()) {
                 return Err("prefix must contain only valid hexadecimal characters");
             }
             
@@ -207,6 +265,18 @@ impl Config {
             starts_with: String::new(),
             ends_with: String::new(),
             case_sensitive: false,
+            format: OutputFormat::Text,
+            tuning_file: None,
+            hook_flags: None,
+            score: None,
+            best_score: Arc::default(),
+            stop: Arc::default(),
+            paused: Arc::default(),
+            attempts: Arc::default(),
+            metrics: Arc::default(),
+            dashboard: Arc::default(),
+            salt_scheme: SaltScheme::Nonce,
+            address_scheme: AddressScheme::Create2,
           }
         )
     }
@@ -251,3 +321,94 @@ pub fn u64_to_le_fixed_8(x: &u64) -> [u8; 8] {
     let b8: u8 = (x & mask) as u8;
     [b8, b7, b6, b5, b4, b3, b2, b1]
 }
+
+/// Compute the CREATE2 address `keccak256(0xff ++ deployer ++ salt ++
+/// init_code_hash)[12..]`.
+pub fn create2_address(deployer: &[u8; 20], salt: &[u8; 32], init_code_hash: &[u8; 32]) -> [u8; 20] {
+    let mut preimage = [0u8; 85];
+    preimage[0] = 0xff;
+    preimage[1..21].copy_from_slice(deployer);
+    preimage[21..53].copy_from_slice(salt);
+    preimage[53..85].copy_from_slice(init_code_hash);
+
+    let hash = keccak256(&preimage);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..32]);
+    address
+}
+
+/// The hasher for the first GPU, built on the first call to
+/// `keccak256_batch_gpu`, or why it could not be built.
+static BATCH_HASHER: OnceLock<Result<Mutex<gpu::GpuHasher>, String>> = OnceLock::new();
+
+/// Compute the keccak-256 hash of each of many fixed-length messages, such
+/// as Merkle leaves or storage slot preimages, on the first GPU. Falls back
+/// to the CPU if there is no GPU or the GPU fails; `keccak256_batch_gpu`
+/// tells why.
+pub fn keccak256_batch<const N: usize>(messages: &[[u8; N]]) -> Vec<[u8; 32]> {
+    if N == 0 || messages.is_empty() {
+        return cpu::keccak256_batch_cpu(messages);
+    }
+    keccak256_batch_gpu(messages).unwrap_or_else(|_| cpu::keccak256_batch_cpu(messages))
+}
+
+/// Compute the keccak-256 hash of each of many fixed-length messages on the
+/// first GPU, or return why that failed.
+///
+/// The first call compiles the whole search program, which can take a few
+/// seconds; later calls reuse it. If there is no GPU or the program fails to
+/// build, every call returns that error without trying again.
+pub fn keccak256_batch_gpu<const N: usize>(messages: &[[u8; N]]) -> Result<Vec<[u8; 32]>, Box<dyn Error>> {
+    let hasher = BATCH_HASHER.get_or_init(|| {
+        let (_, device) = gpu::gpu_devices()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .ok_or("no GPU found")?;
+        gpu::GpuHasher::new(device).map(Mutex::new).map_err(|e| e.to_string())
+    });
+    match hasher {
+        Ok(hasher) => hasher.lock().unwrap_or_else(|e| e.into_inner()).hash(messages),
+        Err(e) => Err(e.clone().into()),
+    }
+}
+
+/// Compute the keccak-256 hash of a byte string.
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = tiny_keccak::Keccak::new_keccak256();
+    hasher.update(bytes);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing::{random_messages, rng};
+
+    #[test]
+    fn keccak256_batch_matches_keccak256() {
+        // on the GPU if there is one, otherwise on the CPU; the lengths
+        // straddle the 136-byte block boundaries
+        let mut rng = rng();
+        assert!(keccak256_batch::<32>(&[]).is_empty());
+        assert_eq!(keccak256_batch(&[[0u8; 0]; 3]), vec![keccak256(&[]); 3]);
+
+        let (messages, digests) = random_messages::<_, 64>(&mut rng, 256);
+        assert_eq!(keccak256_batch(&messages), digests);
+        let (messages, digests) = random_messages::<_, 135>(&mut rng, 256);
+        assert_eq!(keccak256_batch(&messages), digests);
+        let (messages, digests) = random_messages::<_, 136>(&mut rng, 256);
+        assert_eq!(keccak256_batch(&messages), digests);
+        let (messages, digests) = random_messages::<_, 300>(&mut rng, 256);
+        assert_eq!(keccak256_batch(&messages), digests);
+
+        // without a GPU this says why, here and on every later call
+        match keccak256_batch_gpu(&messages) {
+            Ok(gpu_digests) => assert_eq!(gpu_digests, digests),
+            Err(e) => assert_eq!(keccak256_batch_gpu(&messages).unwrap_err().to_string(), e.to_string()),
+        }
+    }
+}
@@ -1,35 +1,71 @@
 extern crate create2gpu;
 extern crate clap;
 
+use std::fs;
 use std::process;
 use std::error::Error;
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use clap::{Parser, Subcommand};
 
-use create2gpu::{Config, gpu};
+use create2gpu::{AddressScheme, Config, HighBytes, InitCode, OutputFormat, SaltScheme, ScoreFunction, Solution, cpu, gpu, keccak256};
+use create2gpu::bench::bench;
+use create2gpu::difficulty::{format_duration, Difficulty};
+use create2gpu::abi::{ParamType, Token};
+use create2gpu::account::{kernel_initializer, AccountFactory, SmartAccount};
+use create2gpu::checkpoint::Checkpoint;
+use create2gpu::clone::CloneVariant;
+use create2gpu::dashboard::watch_keys;
+use create2gpu::deploy::Deployment;
+use create2gpu::factory::Factory;
+use create2gpu::hooks::{check_hook_prefix, parse_hook_flags};
+use create2gpu::init_code::load_hex;
+use create2gpu::interrupt::stop_on_interrupt;
+use create2gpu::metrics::serve_metrics;
+use create2gpu::safe::SafeProxy;
+use create2gpu::selector::SelectorConfig;
+use create2gpu::pool::{coordinate, work};
+use create2gpu::serve::{serve, Backend, JobServer, JobSpec};
+use create2gpu::cpu::{cpu_identifiers, cpu_selectors};
+use create2gpu::gpu::{gpu_identifiers, gpu_selectors};
+use create2gpu::identifier::{load_targets, load_words, IdentifierConfig, IdentifierKind};
+use create2gpu::tuning::DEFAULT_TUNING_FILE;
+use create2gpu::output::{emit, info, to_checksum_address, Event, Prediction, Summary};
+use create2gpu::parse::{check_patterns, parse_address, parse_hash, parse_pattern, parse_selector};
 
 /// A tool for finding CREATE2 salts that generate addresses with specific prefixes using GPU acceleration
 #[derive(Parser, Debug)]
-#[command(name = "create2gpu", author, version, about, long_about = None)]
+#[command(name = "create2gpu", author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Prefix for the contract address (e.g., "dead", "cafe", etc.)
-    #[arg(long, short, value_name = "HEX", required_unless_present = "ends_with")]
+    #[arg(long, short, value_name = "HEX", required_unless_present_any = ["ends_with", "hook_flags", "score"])]
     starts_with: Option<String>,
 
     /// Suffix for the contract address (e.g., "dead", "cafe", etc.)
-    #[arg(long, short, value_name = "HEX", required_unless_present = "starts_with")]
+    #[arg(long, short, value_name = "HEX", required_unless_present_any = ["starts_with", "hook_flags", "score"])]
     ends_with: Option<String>,
 
-    /// Address of the contract deployer that will call CREATE2
-    #[arg(long, value_name = "ADDRESS")]
-    deployer: String,
+    /// Uniswap v4 hook permissions the address must encode, e.g. "beforeSwap,afterAddLiquidity".
+    /// The low 14 bits of the address must have exactly these flags set.
+    #[arg(long, value_name = "NAMES", conflicts_with = "ends_with")]
+    hook_flags: Option<String>,
 
-    /// Address of the caller (for factory addresses with frontrunning protection)
-    #[arg(long, short, value_name = "ADDRESS")]
-    caller: String,
+    /// Instead of matching a pattern, search indefinitely for the best-scoring address and
+    /// report each improvement. "uniswap-v4" uses the Uniswap v4 address challenge scoring.
+    #[arg(long, value_name = "FUNCTION", conflicts_with_all = ["starts_with", "ends_with", "hook_flags"])]
+    score: Option<ScoreFunction>,
 
-    /// Keccak-256 hash of the initialization code
-    #[arg(long, value_name = "HASH")]
-    init_code_hash: String,
+    #[command(flatten)]
+    derivation: DerivationArgs,
+
+    /// Address of the caller (for factory addresses with frontrunning protection)
+    #[arg(long, short, value_name = "ADDRESS", required = true)]
+    caller: Option<String>,
 
     /// GPU device to use (0 for default GPU)
     #[arg(long, short, value_name = "DEVICE", default_value = "0")]
@@ -38,48 +74,915 @@ struct Args {
     /// Use all available GPUs
     #[arg(long, short = 'a')]
     all_gpus: bool,
+
+    /// Search on the CPU instead of a GPU
+    #[arg(long, conflicts_with = "all_gpus")]
+    cpu: bool,
+
+    /// Output format: "text" for the status screen, "json" for one JSON object per event
+    #[arg(long, value_name = "FORMAT", default_value = "text", global = true)]
+    format: OutputFormat,
+
+    /// Salt bytes ahead of the nonce that the factory leaves free: "zero", "random" (drawn for
+    /// each device) or a hex counter, used as is by device 0 and counted up for each further
+    /// device. Give each run a new counter value to search disjoint salts across machines.
+    #[arg(long, value_name = "MODE", default_value = "zero")]
+    high_bytes: HighBytes,
+
+    /// Write a Foundry script that deploys the solution to this file. Needs a deployment
+    /// transaction: --factory with the init code, --safe-singleton or --account-factory.
+    #[arg(long, value_name = "FILE")]
+    forge_script: Option<PathBuf>,
+
+    /// Serve Prometheus metrics of the search at http://ADDR/metrics, e.g. "0.0.0.0:9720". Not
+    /// available with subcommands.
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<String>,
+
+    /// Save the totals and best candidate to this file when the search ends, and resume from
+    /// it: a scoring search only reports addresses that beat the saved best score
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// File holding the benchmarked launch settings for each device
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_TUNING_FILE, global = true)]
+    tuning_file: PathBuf,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+/// How the contract address is derived: the CREATE2 deployer and the init code it deploys
+#[derive(clap::Args, Debug)]
+struct DerivationArgs {
+    /// Address of the contract deployer that will call CREATE2
+    #[arg(long, value_name = "ADDRESS", required_unless_present = "factory")]
+    deployer: Option<String>,
+
+    /// Well-known CREATE2 factory to deploy through, in place of --deployer: "immutable-create2",
+    /// "arachnid", "createx", "safe-singleton" or "create2-deployer". Mines salts that follow
+    /// the factory's rules for --caller.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["deployer", "safe_singleton", "account_factory", "zksync"]
+    )]
+    factory: Option<Factory>,
+
+    /// Keccak-256 hash of the initialization code
+    #[arg(long, value_name = "HASH", required_unless_present_any = ["init_code", "safe_singleton", "clone_of", "account_factory"], conflicts_with = "init_code")]
+    init_code_hash: Option<String>,
+
+    /// Initialization code as hex, a file containing hex, or a Foundry/Hardhat artifact JSON
+    #[arg(long, value_name = "HEX|FILE")]
+    init_code: Option<String>,
+
+    /// Constructor arguments appended to the init code. With an artifact these are values
+    /// encoded against the constructor ABI, otherwise ABI-encoded hex.
+    #[arg(long, value_name = "ARG", num_args = 1.., allow_hyphen_values = true, requires = "init_code")]
+    constructor_args: Vec<String>,
+
+    /// Safe singleton the proxy delegates to. Mines the saltNonce of
+    /// SafeProxyFactory.createProxyWithNonce, with --deployer as the factory.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        requires_all = ["safe_initializer", "safe_proxy_code"],
+        conflicts_with_all = ["init_code_hash", "init_code"]
+    )]
+    safe_singleton: Option<String>,
+
+    /// Initializer calldata passed to createProxyWithNonce (usually Safe.setup), as hex or a file containing hex
+    #[arg(long, value_name = "HEX|FILE", requires = "safe_singleton")]
+    safe_initializer: Option<String>,
+
+    /// The factory's proxy creation code (SafeProxyFactory.proxyCreationCode()), as hex or a file containing hex
+    #[arg(long, value_name = "HEX|FILE", requires = "safe_singleton")]
+    safe_proxy_code: Option<String>,
+
+    /// Implementation of a minimal proxy clone deployed by --deployer; derives the init code
+    /// hash of the clone
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["init_code_hash", "init_code", "safe_singleton"])]
+    clone_of: Option<String>,
+
+    /// Clone layout: "erc1167" (OpenZeppelin Clones), "solady" (LibClone), "push0"
+    /// (LibClone PUSH0 variant) or "erc1967" (LibClone ERC-1967 proxy)
+    #[arg(long, value_name = "VARIANT", default_value = "erc1167", requires = "clone_of")]
+    clone_variant: CloneVariant,
+
+    /// ERC-4337 account factory at --deployer: "simple-account", "light-account",
+    /// "light-account-v2" or "kernel". Mines the salt argument of createAccount.
+    #[arg(
+        long,
+        value_name = "FACTORY",
+        requires = "account_implementation",
+        conflicts_with_all = ["init_code_hash", "init_code", "safe_singleton", "clone_of"]
+    )]
+    account_factory: Option<AccountFactory>,
+
+    /// Owner of the smart account
+    #[arg(long, value_name = "ADDRESS", requires = "account_factory")]
+    account_owner: Option<String>,
+
+    /// Account implementation the factory deploys proxies of (its accountImplementation())
+    #[arg(long, value_name = "ADDRESS", requires = "account_factory")]
+    account_implementation: Option<String>,
+
+    /// The factory's ERC1967Proxy creation code, as hex or a file containing hex
+    /// (simple-account and light-account)
+    #[arg(long, value_name = "HEX|FILE", requires = "account_factory")]
+    account_proxy_code: Option<String>,
+
+    /// Kernel initialize calldata passed to createAccount, as hex or a file containing hex;
+    /// built from --account-owner and --kernel-validator if omitted
+    #[arg(long, value_name = "HEX|FILE", requires = "account_factory")]
+    account_initializer: Option<String>,
+
+    /// ECDSA validator module that validates the Kernel account's owner
+    #[arg(long, value_name = "ADDRESS", requires = "account_factory")]
+    kernel_validator: Option<String>,
+
+    /// Derive zkSync Era CREATE2 addresses, with --init-code-hash as the versioned bytecode hash
+    /// and --deployer as the sender that calls the ContractDeployer
+    #[arg(long, conflicts_with_all = ["init_code", "safe_singleton", "clone_of", "account_factory"])]
+    zksync: bool,
+
+    /// ABI-encoded constructor arguments of the zkSync deployment, as hex or a file containing
+    /// hex (empty by default)
+    #[arg(long, value_name = "HEX|FILE", requires = "zksync")]
+    zksync_constructor_input: Option<String>,
+}
+
+/// The search a coordinator hands out: the fields of a `serve` job
+#[derive(clap::Args, Debug)]
+struct JobArgs {
+    /// Address of the contract deployer that will call CREATE2
+    #[arg(long, value_name = "ADDRESS", required_unless_present = "factory", conflicts_with = "factory")]
+    deployer: Option<String>,
+
+    /// Well-known CREATE2 factory to deploy through, in place of --deployer
+    #[arg(long, value_name = "NAME")]
+    factory: Option<String>,
+
+    /// Address of the caller (for factories with frontrunning protection); zero by default
+    #[arg(long, short, value_name = "ADDRESS")]
+    caller: Option<String>,
+
+    /// Keccak-256 hash of the initialization code
+    #[arg(long, value_name = "HASH")]
+    init_code_hash: String,
+
+    /// Prefix for the contract address
+    #[arg(long, short, value_name = "HEX")]
+    starts_with: Option<String>,
+
+    /// Suffix for the contract address
+    #[arg(long, short, value_name = "HEX")]
+    ends_with: Option<String>,
+
+    /// Uniswap v4 hook permissions the address must encode
+    #[arg(long, value_name = "NAMES")]
+    hook_flags: Option<String>,
+
+    /// Salt bytes ahead of the nonce: "zero", "random" or a hex counter, shared by all workers
+    #[arg(long, value_name = "MODE")]
+    high_bytes: Option<String>,
+}
+
+impl JobArgs {
+    fn spec(&self) -> JobSpec {
+        JobSpec {
+            deployer: self.deployer.clone(),
+            factory: self.factory.clone(),
+            caller: self.caller.clone(),
+            init_code_hash: self.init_code_hash.clone(),
+            starts_with: self.starts_with.clone(),
+            ends_with: self.ends_with.clone(),
+            hook_flags: self.hook_flags.clone(),
+            score: None,
+            high_bytes: self.high_bytes.clone(),
+        }
+    }
+}
+
+/// A derivation resolved from `DerivationArgs`
+struct Derivation {
+    deployer: [u8; 20],
+    init_code_hash: [u8; 32],
+    salt_scheme: SaltScheme,
+    address_scheme: AddressScheme,
+    /// The init code, when it is known rather than only its hash
+    init_code: Option<Vec<u8>>,
+    factory: Option<Factory>,
+    safe: Option<SafeProxy>,
+    account: Option<SmartAccount>,
+}
+
+impl Derivation {
+    // The transaction that deploys `address`, given the salt or salt nonce passed to the factory
+    fn deployment(&self, salt: &[u8; 32], address: [u8; 20]) -> Option<Deployment> {
+        let (via, calldata) = if let Some(ref safe) = self.safe {
+            ("SafeProxyFactory".to_string(), safe.create_proxy_calldata(salt))
+        } else if let Some(ref account) = self.account {
+            (format!("{} factory", account.factory), account.create_account_calldata(salt))
+        } else {
+            match (self.factory, &self.init_code) {
+                (Some(factory), Some(init_code)) => (factory.to_string(), factory.calldata(salt, init_code)),
+                _ => return None,
+            }
+        };
+        Some(Deployment { via, to: self.deployer, calldata, address })
+    }
+
+    fn new(deployer: [u8; 20], init_code_hash: [u8; 32]) -> Self {
+        Derivation {
+            deployer,
+            init_code_hash,
+            salt_scheme: SaltScheme::Nonce,
+            address_scheme: AddressScheme::Create2,
+            init_code: None,
+            factory: None,
+            safe: None,
+            account: None,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Benchmark the kernel on each device over a grid of launch settings and save the fastest
+    /// settings to the tuning file. Benchmarks the CPU backend if there is no GPU.
+    Bench {
+        /// Seconds to run each combination of settings
+        #[arg(long, value_name = "SECONDS", default_value = "2", value_parser = parse_seconds)]
+        duration: Duration,
+    },
+
+    /// Compute the address a salt deploys to, without searching
+    Predict {
+        #[command(flatten)]
+        derivation: DerivationArgs,
+
+        /// CREATE2 salt (32 bytes of hex)
+        #[arg(long, value_name = "SALT", required_unless_present = "salt_nonce", conflicts_with = "salt_nonce")]
+        salt: Option<String>,
+
+        /// Nonce the factory hashes into the salt, such as the Safe saltNonce (decimal or 0x hex)
+        #[arg(long, value_name = "UINT256")]
+        salt_nonce: Option<String>,
+    },
+
+    /// Check that a salt deploys to the claimed address. Exits non-zero on mismatch.
+    Verify {
+        #[command(flatten)]
+        derivation: DerivationArgs,
+
+        /// CREATE2 salt (32 bytes of hex)
+        #[arg(long, value_name = "SALT", required_unless_present = "salt_nonce", conflicts_with = "salt_nonce")]
+        salt: Option<String>,
+
+        /// Nonce the factory hashes into the salt, such as the Safe saltNonce (decimal or 0x hex)
+        #[arg(long, value_name = "UINT256")]
+        salt_nonce: Option<String>,
+
+        /// Address the salt is claimed to deploy to
+        #[arg(long, value_name = "ADDRESS")]
+        address: String,
+    },
+
+    /// Find suffixes for a function name that give its selector zero bytes or a low value,
+    /// such as transfer_gSq(address,uint256)
+    Selector {
+        /// Canonical function signature, e.g. "transfer(address,uint256)"
+        signature: String,
+
+        /// Zero bytes the selector must have, anywhere in it (each saves 12 gas of calldata)
+        #[arg(
+            long,
+            value_name = "COUNT",
+            default_value = "0",
+            value_parser = clap::value_parser!(u8).range(0..=4),
+            required_unless_present = "below"
+        )]
+        zero_bytes: u8,
+
+        /// The selector, read as a number, must be below this value, e.g. 0x00010000
+        #[arg(long, value_name = "HEX")]
+        below: Option<String>,
+
+        /// Number of signatures to find, shortest suffixes first
+        #[arg(long, value_name = "COUNT", default_value = "1")]
+        count: usize,
+
+        /// GPU device to use (0 for default GPU)
+        #[arg(long, short, value_name = "DEVICE", default_value = "0")]
+        gpu: u32,
+
+        /// Search on the CPU instead of a GPU
+        #[arg(long)]
+        cpu: bool,
+    },
+
+    /// Hash identifiers built from dictionary words and suffixes, such as Unauthorized_x1(), and
+    /// report those whose selector or topic0 starts with a target from a file
+    Identifier {
+        /// What the identifiers are: "function", "error" or "event"
+        #[arg(long, value_name = "KIND", default_value = "error")]
+        kind: IdentifierKind,
+
+        /// File with one candidate name per line
+        #[arg(long, value_name = "FILE")]
+        words: PathBuf,
+
+        /// Canonical parameter list of every candidate, e.g. "(address,uint256)"
+        #[arg(long, value_name = "PARAMS", default_value = "()")]
+        params: String,
+
+        /// File with one hex target per line: selectors to collide with, or topic0 prefixes.
+        /// Anything after the value on a line is ignored.
+        #[arg(long, value_name = "FILE")]
+        targets: PathBuf,
+
+        /// Longest alphanumeric suffix appended to each word after an underscore
+        #[arg(long, value_name = "LEN", default_value = "2", value_parser = clap::value_parser!(u32).range(0..=8))]
+        max_suffix_len: u32,
+
+        /// Stop after this many matches instead of searching every candidate
+        #[arg(long, value_name = "COUNT")]
+        count: Option<usize>,
+
+        /// GPU device to use (0 for default GPU)
+        #[arg(long, short, value_name = "DEVICE", default_value = "0")]
+        gpu: u32,
+
+        /// Search on the CPU instead of a GPU
+        #[arg(long)]
+        cpu: bool,
+    },
+
+    /// Own a pattern search and hand out disjoint nonce ranges to workers on other machines
+    /// that connect over TCP, until one of them finds a salt
+    Coordinator {
+        /// Address to listen on for workers
+        #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:8721")]
+        listen: String,
+
+        /// Have workers report near-miss shares that match this many hex
+        /// digits of the pattern, to check their hash rate; 0 turns shares
+        /// off
+        #[arg(long, value_name = "DIGITS", default_value_t = 0)]
+        share_digits: usize,
+
+        #[command(flatten)]
+        job: JobArgs,
+    },
+
+    /// Connect to a coordinator and search the nonce ranges it hands out
+    Worker {
+        /// Address of the coordinator, e.g. "10.0.0.2:8721"
+        #[arg(long, value_name = "ADDR")]
+        connect: String,
+
+        /// Name the coordinator shows for this worker
+        #[arg(long, value_name = "NAME", default_value = "worker")]
+        name: String,
+
+        /// GPU device to use (0 for default GPU)
+        #[arg(long, short, value_name = "DEVICE", default_value = "0")]
+        gpu: u32,
+
+        /// Search on the CPU instead of a GPU
+        #[arg(long)]
+        cpu: bool,
+    },
+
+    /// Run as a service: accept search jobs over HTTP/JSON, run them one after another and
+    /// keep their status and results for polling
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8720")]
+        listen: String,
+
+        /// GPU device to run the jobs on (0 for default GPU)
+        #[arg(long, short, value_name = "DEVICE", default_value = "0")]
+        gpu: u32,
+
+        /// Run the jobs on the CPU instead of a GPU
+        #[arg(long)]
+        cpu: bool,
+    },
+}
+
+fn main() {
     // Parse command line arguments
     let args = Args::parse();
+    let format = args.format;
+
+    if let Err(e) = run(args) {
+        format.error(&e.to_string());
+        process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    if args.command.is_some() && args.metrics_addr.is_some() {
+        return Err("--metrics-addr serves the metrics of an address search and cannot be used with a subcommand".into());
+    }
+
+    match args.command {
+        Some(Command::Bench { duration }) => {
+            bench(&args.tuning_file, duration, args.format)?;
+            Ok(())
+        }
+        Some(Command::Predict { ref derivation, ref salt, ref salt_nonce }) => {
+            let prediction = predict(derivation, salt.as_deref(), salt_nonce.as_deref(), args.format)?;
+            prediction.report(args.format, false);
+            Ok(())
+        }
+        Some(Command::Verify { ref derivation, ref salt, ref salt_nonce, ref address }) => {
+            let claimed = parse_address("address", address)?;
+            let prediction = predict(derivation, salt.as_deref(), salt_nonce.as_deref(), args.format)?;
+            if prediction.address != claimed {
+                return Err(format!(
+                    "salt 0x{} deploys to {}, not the claimed {}",
+                    hex::encode(prediction.salt),
+                    to_checksum_address(&hex::encode(prediction.address)),
+                    to_checksum_address(&hex::encode(claimed))
+                ).into());
+            }
+            prediction.report(args.format, true);
+            Ok(())
+        }
+        Some(Command::Selector { ref signature, zero_bytes, ref below, count, gpu, cpu }) => {
+            let mut config = SelectorConfig::new(signature)?;
+            config.zero_bytes = zero_bytes;
+            config.below = match below {
+                Some(below) => Some(parse_selector("below", below)?),
+                None => None,
+            };
+            config.count = count;
+            config.gpu_device = gpu;
+            config.format = args.format;
+            config.tuning_file = Some(args.tuning_file.clone());
+            if config.zero_bytes == 0 && config.below.is_none() {
+                return Err("--zero-bytes or --below must be set".into());
+            }
+
+            args.format.info(&config.describe());
+            if cpu {
+                cpu_selectors(config).map_err(|e| format!("CPU search failed: {}", e))?;
+            } else {
+                gpu_selectors(config).map_err(|e| format!("GPU search failed: {}", e))?;
+            }
+            Ok(())
+        }
+        Some(Command::Identifier { kind, ref words, ref params, ref targets, max_suffix_len, count, gpu, cpu }) => {
+            let mut config = IdentifierConfig::new(kind, load_words(words)?, params, load_targets(targets)?)?;
+            config.max_suffix_len = max_suffix_len;
+            config.count = count;
+            config.gpu_device = gpu;
+            config.format = args.format;
+            config.tuning_file = Some(args.tuning_file.clone());
+
+            args.format.info(&format!(
+                "Hashing {} {} identifiers from {} words against {} targets",
+                config.search_space(),
+                kind,
+                config.words.len(),
+                config.targets.len()
+            ));
+            let matches = if cpu {
+                cpu_identifiers(config).map_err(|e| format!("CPU search failed: {}", e))?
+            } else {
+                gpu_identifiers(config).map_err(|e| format!("GPU search failed: {}", e))?
+            };
+            args.format.info(&format!("Found {} matches", matches.len()));
+            Ok(())
+        }
+        Some(Command::Coordinator { ref listen, share_digits, ref job }) => {
+            coordinate(listen, job.spec(), share_digits, args.format)?;
+            Ok(())
+        }
+        Some(Command::Worker { ref connect, ref name, gpu, cpu }) => {
+            work(connect, name, cpu, gpu, Some(args.tuning_file.clone()), args.format)
+        }
+        Some(Command::Serve { ref listen, gpu, cpu }) => {
+            let backend: Backend = if cpu { create2gpu::cpu } else { create2gpu::gpu };
+            let server = JobServer::new(backend, gpu, Some(args.tuning_file.clone()));
+            serve(listen, Arc::new(server), args.format)
+        }
+        None => run_search(args),
+    }
+}
+
+// Compute the address a salt, or a salt nonce for factories that derive the salt, deploys to
+fn predict(
+    args: &DerivationArgs,
+    salt: Option<&str>,
+    salt_nonce: Option<&str>,
+    format: OutputFormat,
+) -> Result<Prediction, Box<dyn Error>> {
+    let derivation = derive(args, &[0u8; 20], format)?;
+
+    match (&derivation.salt_scheme, salt, salt_nonce) {
+        (SaltScheme::Hashed { .. }, _, Some(salt_nonce)) => {
+            let salt_nonce = parse_uint256("salt nonce", salt_nonce)?;
+            let salt = match derivation.factory {
+                Some(factory) => factory.create2_salt(&salt_nonce, None)?,
+                None => derivation.salt_scheme.hash_salt_nonce(&salt_nonce).unwrap(),
+            };
+            let mut prediction = Prediction::new(derivation.address_scheme, derivation.deployer, salt, derivation.init_code_hash);
+            prediction.salt_nonce = Some(salt_nonce);
+            Ok(prediction)
+        }
+        (SaltScheme::Hashed { .. }, Some(_), None) => Err("this factory derives the salt from --salt-nonce, not --salt".into()),
+        (_, Some(salt), None) => {
+            let mut salt = parse_hash("salt", salt)?;
+            if let Some(factory) = derivation.factory {
+                salt = factory.create2_salt(&salt, None)?;
+            }
+            Ok(Prediction::new(derivation.address_scheme, derivation.deployer, salt, derivation.init_code_hash))
+        }
+        (_, _, Some(_)) => Err("--salt-nonce needs a factory that derives the salt, such as --safe-singleton".into()),
+        (_, None, None) => unreachable!("clap requires --salt or --salt-nonce"),
+    }
+}
+
+// Resolve the deployer, init code hash and salt scheme for salts used by `caller`
+fn derive(args: &DerivationArgs, caller: &[u8; 20], format: OutputFormat) -> Result<Derivation, Box<dyn Error>> {
+    let deployer = match args.factory {
+        Some(factory) => factory.address(),
+        None => parse_address("deployer", args.deployer.as_deref().unwrap_or_default())?,
+    };
+
+    if let Some(ref singleton) = args.safe_singleton {
+        let safe = SafeProxy {
+            singleton: parse_address("Safe singleton", singleton)?,
+            initializer: load_hex("Safe initializer", args.safe_initializer.as_deref().unwrap_or_default())?,
+            proxy_creation_code: load_hex("Safe proxy creation code", args.safe_proxy_code.as_deref().unwrap_or_default())?,
+        };
+        if safe.proxy_creation_code.is_empty() {
+            return Err("Safe proxy creation code is empty".into());
+        }
+        let init_code_hash = safe.init_code_hash();
+        format.info(&format!(
+            "Derived Safe proxy init code hash ({} bytes): 0x{}",
+            safe.init_code().len(),
+            hex::encode(init_code_hash)
+        ));
+        return Ok(Derivation { salt_scheme: safe.salt_scheme(), safe: Some(safe), ..Derivation::new(deployer, init_code_hash) });
+    }
 
+    if let Some(factory) = args.account_factory {
+        let account = smart_account(args, factory)?;
+        let init_code_hash = account.init_code_hash();
+        format.info(&format!(
+            "Derived {} account init code hash ({} bytes): 0x{}",
+            factory,
+            account.init_code().len(),
+            hex::encode(init_code_hash)
+        ));
+        return Ok(Derivation {
+            salt_scheme: account.salt_scheme(),
+            account: Some(account),
+            ..Derivation::new(deployer, init_code_hash)
+        });
+    }
+
+    let mut derivation = match args.clone_of {
+        Some(ref implementation) => {
+            let implementation = parse_address("clone implementation", implementation)?;
+            let init_code = args.clone_variant.init_code(&implementation);
+            let init_code_hash = keccak256(&init_code);
+            format.info(&format!(
+                "Derived {} clone init code hash ({} bytes): 0x{}",
+                args.clone_variant,
+                init_code.len(),
+                hex::encode(init_code_hash)
+            ));
+            Derivation { init_code: Some(init_code), ..Derivation::new(deployer, init_code_hash) }
+        }
+        None => from_init_code(args, deployer, format)?,
+    };
+
+    if let Some(factory) = args.factory {
+        derivation.salt_scheme = factory.salt_scheme(caller);
+        derivation.factory = Some(factory);
+    }
+
+    if args.zksync {
+        let input = match args.zksync_constructor_input {
+            Some(ref source) => load_hex("zkSync constructor input", source)?,
+            None => Vec::new(),
+        };
+        format.info(&format!("Deriving zkSync Era addresses with {} bytes of constructor input", input.len()));
+        derivation.address_scheme = AddressScheme::ZkSync { constructor_input_hash: keccak256(&input) };
+    }
+
+    Ok(derivation)
+}
+
+// Build the smart account from the options its factory needs
+fn smart_account(args: &DerivationArgs, factory: AccountFactory) -> Result<SmartAccount, Box<dyn Error>> {
+    let owner = match args.account_owner {
+        Some(ref owner) => Some(parse_address("account owner", owner)?),
+        None => None,
+    };
+
+    let proxy_creation_code = match args.account_proxy_code {
+        Some(ref code) if factory.needs_proxy_code() => load_hex("account proxy creation code", code)?,
+        Some(_) => return Err(format!("--account-proxy-code does not apply to {} accounts", factory).into()),
+        None if factory.needs_proxy_code() => {
+            return Err(format!("{} accounts need the factory's ERC1967Proxy creation code (--account-proxy-code)", factory).into())
+        }
+        None => Vec::new(),
+    };
+
+    let initializer = match (factory, &args.account_initializer, &args.kernel_validator, owner) {
+        (AccountFactory::Kernel, Some(initializer), None, _) => load_hex("Kernel initializer", initializer)?,
+        (AccountFactory::Kernel, None, Some(validator), Some(owner)) => {
+            kernel_initializer(&parse_address("Kernel validator", validator)?, &owner)
+        }
+        (AccountFactory::Kernel, Some(_), Some(_), _) => {
+            return Err("pass either --account-initializer or --kernel-validator, not both".into())
+        }
+        (AccountFactory::Kernel, None, _, _) => {
+            return Err("kernel accounts need --account-initializer, or --account-owner and --kernel-validator".into())
+        }
+        (_, None, None, _) => Vec::new(),
+        (_, _, _, _) => {
+            return Err(format!("--account-initializer and --kernel-validator only apply to kernel accounts, not {}", factory).into())
+        }
+    };
+
+    // every factory but Kernel takes the owner directly; Kernel's is in the initializer
+    let owner = match (owner, factory) {
+        (Some(owner), _) => owner,
+        (None, AccountFactory::Kernel) => [0u8; 20],
+        (None, _) => return Err(format!("{} accounts need --account-owner", factory).into()),
+    };
+
+    Ok(SmartAccount {
+        factory,
+        owner,
+        implementation: parse_address("account implementation", args.account_implementation.as_deref().unwrap_or_default())?,
+        proxy_creation_code,
+        initializer,
+    })
+}
+
+// Parse a positive, finite number of seconds
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) if !duration.is_zero() => Ok(duration),
+        _ => Err("must be a positive number of seconds".to_string()),
+    }
+}
+
+// Parse an unsigned 256-bit integer in decimal or 0x hex into a big-endian word
+fn parse_uint256(field: &str, value: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    match Token::parse(&ParamType::Uint(256), value) {
+        Ok(Token::Uint(word)) => Ok(word),
+        Ok(_) => unreachable!("a uint256 parses to Token::Uint"),
+        Err(e) => Err(format!("invalid {}: {}", field, e).into()),
+    }
+}
+
+// Take the init code hash as given, or derive it from the init code and constructor arguments
+fn from_init_code(derivation: &DerivationArgs, deployer: [u8; 20], format: OutputFormat) -> Result<Derivation, Box<dyn Error>> {
+    match (&derivation.init_code_hash, &derivation.init_code) {
+        (Some(hash), _) => Ok(Derivation::new(deployer, parse_hash("init code hash", hash)?)),
+        (None, Some(source)) => {
+            let init_code = InitCode::load(source, &derivation.constructor_args)?;
+            let hash = init_code.hash();
+            let message = format!(
+                "Derived init code hash from {} ({} bytes): 0x{}",
+                init_code.source,
+                init_code.code.len(),
+                hex::encode(hash)
+            );
+            format.info(&message);
+            Ok(Derivation { init_code: Some(init_code.code), ..Derivation::new(deployer, hash) })
+        }
+        (None, None) => unreachable!("clap requires --init-code-hash, --init-code, --safe-singleton, --clone-of or --account-factory"),
+    }
+}
+
+fn run_search(args: Args) -> Result<(), Box<dyn Error>> {
     // Parse the addresses and hash
-    let factory_address = parse_address(&args.deployer)?;
-    let calling_address = parse_address(&args.caller)?;
-    let init_code_hash = parse_hash(&args.init_code_hash)?;
+    let calling_address = parse_address("caller", args.caller.as_deref().unwrap_or_default())?;
+    let derivation = derive(&args.derivation, &calling_address, args.format)?;
+
+    // Validate the patterns to search for
+    let starts_with = parse_pattern("prefix", args.starts_with.as_deref().unwrap_or_default())?;
+    let ends_with = parse_pattern("suffix", args.ends_with.as_deref().unwrap_or_default())?;
+    let hook_flags = match args.hook_flags {
+        Some(ref names) => {
+            let flags = parse_hook_flags("hook flags", names)?;
+            check_hook_prefix(&starts_with)?;
+            Some(flags)
+        }
+        None => None,
+    };
+    if starts_with.is_empty() && ends_with.is_empty() && hook_flags.is_none() && args.score.is_none() {
+        return Err("at least one of --starts-with, --ends-with or --hook-flags must be non-empty".into());
+    }
+    check_patterns(&starts_with, &ends_with)?;
+    if args.forge_script.is_some() && derivation.deployment(&[0u8; 32], [0u8; 20]).is_none() {
+        return Err("--forge-script needs a deployment transaction: use --factory with --init-code or --clone-of, \
+                    --safe-singleton or --account-factory".into());
+    }
 
     // Create the base configuration
     let base_config = Config {
-        factory_address,
+        factory_address: derivation.deployer,
         calling_address,
-        init_code_hash,
+        init_code_hash: derivation.init_code_hash,
         gpu_device: args.gpu,
         leading_zeroes_threshold: 0,
         total_zeroes_threshold: 0,
         prefix: None,
-        starts_with: args.starts_with.unwrap_or_default().to_lowercase(),
-        ends_with: args.ends_with.unwrap_or_default().to_lowercase(),
+        starts_with,
+        ends_with,
         case_sensitive: false,
+        format: args.format,
+        tuning_file: Some(args.tuning_file.clone()),
+        hook_flags,
+        score: args.score,
+        best_score: Default::default(),
+        stop: Default::default(),
+        paused: Default::default(),
+        attempts: Default::default(),
+        metrics: Default::default(),
+        dashboard: Default::default(),
+        salt_scheme: derivation.salt_scheme.clone(),
+        address_scheme: derivation.address_scheme,
     };
 
-    if args.all_gpus {
+    if let Some(ref addr) = args.metrics_addr {
+        serve_metrics(addr, base_config.metrics.clone(), base_config.attempts.clone(), args.format)?;
+    }
+
+    let checkpoint = match args.checkpoint {
+        Some(ref path) => Checkpoint::load(path, &base_config)?,
+        None => Checkpoint::new(&base_config),
+    };
+    if checkpoint.attempts > 0 {
+        info(&base_config, &format!(
+            "Resuming after {} attempts in {}{}",
+            checkpoint.attempts,
+            format_duration(checkpoint.elapsed_seconds),
+            checkpoint.best_score.map(|score| format!(", best score {}", score)).unwrap_or_default()
+        ));
+    }
+    if let (Some(_), Some(score)) = (base_config.score, checkpoint.best_score) {
+        base_config.best_score.store(score, Ordering::SeqCst);
+    }
+
+    // Show how long the search should take before starting it
+    match base_config.score {
+        Some(score_function) => info(
+            &base_config,
+            &format!("Scoring addresses with {}; every new best score is reported until the search is stopped", score_function),
+        ),
+        None => {
+            let difficulty = Difficulty::of(&base_config);
+            info(&base_config, &difficulty.describe());
+            if let Some(warning) = difficulty.warning() {
+                info(&base_config, &warning);
+            }
+        }
+    }
+
+    // Pause, resume and quit from the keyboard while the devices search
+    let keys = match args.format {
+        OutputFormat::Text => watch_keys(&base_config),
+        OutputFormat::Json => None,
+    };
+    stop_on_interrupt(&base_config)?;
+    let started = Instant::now();
+
+    let result = if args.cpu {
+        cpu(device_config(&base_config, args.high_bytes, 0)).map_err(|e| format!("CPU search failed: {}", e).into())
+    } else if args.all_gpus {
         // Run on all available GPUs
-        run_on_all_gpus(base_config)?;
+        run_on_all_gpus(base_config.clone(), args.high_bytes)
     } else {
         // Original single-GPU code
-        println!("Using GPU device {}...", base_config.gpu_device);
-        if let Err(e) = gpu(base_config) {
-            eprintln!("GPU search failed: {}", e);
-            process::exit(1);
+        info(&base_config, &format!("Using GPU device {}...", base_config.gpu_device));
+        gpu(device_config(&base_config, args.high_bytes, base_config.gpu_device))
+            .map_err(|e| format!("GPU search failed: {}", e).into())
+    };
+    drop(keys);
+    let stopped = base_config.stop.load(Ordering::SeqCst);
+    let attempts = base_config.attempts.load(Ordering::SeqCst);
+    let elapsed_seconds = started.elapsed().as_secs_f64();
+
+    let solution = match result {
+        Ok(solution) => solution,
+        Err(_) if stopped => {
+            return finish_search(&base_config, args.checkpoint.as_deref(), checkpoint, attempts, elapsed_seconds, None, true)
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Check a Safe solution once more the way SafeProxyFactory computes the address
+    if let (Some(safe), Some(salt_nonce)) = (&derivation.safe, &solution.salt_nonce) {
+        let address = safe.address(&derivation.deployer, salt_nonce);
+        if address != solution.address {
+            return Err(format!(
+                "Safe proxy address {} for the salt nonce does not match the mined address {}",
+                to_checksum_address(&hex::encode(address)),
+                to_checksum_address(&hex::encode(solution.address))
+            ).into());
+        }
+    }
+
+    // Likewise for a smart account, with the salt argument passed to createAccount
+    if let Some(ref account) = derivation.account {
+        let salt = solution.salt_nonce.unwrap_or(solution.salt);
+        let address = account.address(&derivation.deployer, &salt);
+        if address != solution.address {
+            return Err(format!(
+                "{} account address {} for the salt does not match the mined address {}",
+                account.factory,
+                to_checksum_address(&hex::encode(address)),
+                to_checksum_address(&hex::encode(solution.address))
+            ).into());
+        }
+    }
+
+    // And for a factory preset, with the salt passed to the factory
+    if let Some(factory) = derivation.factory {
+        let salt = solution.salt_nonce.unwrap_or(solution.salt);
+        if factory.create2_salt(&salt, Some(&calling_address))? != solution.salt {
+            return Err(format!("{} would not deploy with the mined salt 0x{}", factory, hex::encode(solution.salt)).into());
         }
     }
 
+    emit(&base_config, Event::Solution(&solution));
+
+    let salt = solution.salt_nonce.unwrap_or(solution.salt);
+    match derivation.deployment(&salt, solution.address) {
+        Some(deployment) => {
+            emit(&base_config, Event::Deployment(&deployment));
+            if let Some(ref path) = args.forge_script {
+                fs::write(path, deployment.forge_script())
+                    .map_err(|e| format!("could not write forge script to '{}': {}", path.display(), e))?;
+                info(&base_config, &format!(
+                    "Wrote {}; deploy with: forge script {} --broadcast",
+                    path.display(),
+                    path.display()
+                ));
+            }
+        }
+        None if derivation.factory.is_some() => {
+            info(&base_config, "Pass --init-code instead of --init-code-hash to print the factory calldata")
+        }
+        None => {}
+    }
+
+    finish_search(&base_config, args.checkpoint.as_deref(), checkpoint, attempts, elapsed_seconds, Some(&solution), stopped)
+}
+
+// Add this run to the checkpoint and save it, then sum up the search if it
+// was stopped before it found a solution
+fn finish_search(
+    config: &Config,
+    path: Option<&Path>,
+    mut checkpoint: Checkpoint,
+    attempts: u64,
+    elapsed_seconds: f64,
+    best: Option<&Solution>,
+    stopped: bool,
+) -> Result<(), Box<dyn Error>> {
+    checkpoint.record(attempts, elapsed_seconds, best);
+    if let Some(path) = path {
+        checkpoint.save(path)?;
+        info(config, &format!("Saved checkpoint {}", path.display()));
+    }
+    if stopped {
+        emit(config, Event::Stopped(&Summary {
+            attempts: checkpoint.attempts,
+            elapsed_seconds: checkpoint.elapsed_seconds,
+            best: checkpoint.best,
+        }));
+    }
     Ok(())
 }
 
+// The configuration for one device, with the free salt bytes ahead of the nonce filled in
+fn device_config(base_config: &Config, high_bytes: HighBytes, device: u32) -> Config {
+    let mut config = base_config.clone();
+    config.gpu_device = device;
+    if high_bytes != HighBytes::Zero {
+        let high = high_bytes.for_device(device);
+        config.salt_scheme = base_config.salt_scheme.with_high_bytes(&high);
+        info(&config, &format!("Device {} searches salts with high bytes 0x{}", device, hex::encode(high)));
+    }
+    config
+}
+
 // Helper function to run the search on all available GPUs
-fn run_on_all_gpus(base_config: Config) -> Result<(), Box<dyn Error>> {
+fn run_on_all_gpus(base_config: Config, high_bytes: HighBytes) -> Result<Solution, Box<dyn Error>> {
     // Get all available platforms and devices
     let platforms = ocl::Platform::list();
     
@@ -91,15 +994,12 @@ fn run_on_all_gpus(base_config: Config) -> Result<(), Box<dyn Error>> {
     let mut total_gpus = 0;
     
     // Collect all available GPUs across all platforms
-    for platform_id in 0..platforms.len() {
-        // Get the platform
-        let platform_id = platforms[platform_id];
-        
+    for &platform_id in &platforms {
         // Get devices for this platform
         let devices = match ocl::Device::list(platform_id, None) {
             Ok(devices) => devices,
             Err(e) => {
-                println!("Warning: Failed to get devices for platform {}: {}", platform_id, e);
+                info(&base_config, &format!("Warning: Failed to get devices for platform {}: {}", platform_id, e));
                 continue;
             }
         };
@@ -109,7 +1009,7 @@ fn run_on_all_gpus(base_config: Config) -> Result<(), Box<dyn Error>> {
             let device_type = match device.info(ocl::enums::DeviceInfo::Type) {
                 Ok(t) => t,
                 Err(e) => {
-                    println!("Warning: Failed to get device type for device {}: {}", device_id, e);
+                    info(&base_config, &format!("Warning: Failed to get device type for device {}: {}", device_id, e));
                     continue;
                 }
             };
@@ -119,8 +1019,7 @@ fn run_on_all_gpus(base_config: Config) -> Result<(), Box<dyn Error>> {
                 // Convert to string and check if it contains "GPU"
                 let type_str = format!("{:?}", device_type);
                 if type_str.contains("GPU") {
-                    let mut config = base_config.clone();
-                    config.gpu_device = device_id as u32;
+                    let config = device_config(&base_config, high_bytes, device_id as u32);
                     gpu_configs.push((platform_id, device_id as u32, config));
                     total_gpus += 1;
                 }
@@ -132,88 +1031,45 @@ fn run_on_all_gpus(base_config: Config) -> Result<(), Box<dyn Error>> {
         return Err("No GPU devices found".into());
     }
     
-    println!("Found {} GPU devices across {} platforms", total_gpus, platforms.len());
+    info(&base_config, &format!("Found {} GPU devices across {} platforms", total_gpus, platforms.len()));
     
-    // Create a channel for the first GPU to signal when a solution is found
+    // Create a channel for the GPUs to report their result
     let (tx, rx) = std::sync::mpsc::channel();
     
     // Spawn threads for each GPU
     let _handles: Vec<_> = gpu_configs.into_iter().map(|(platform_id, device_id, cfg)| {
         let tx = tx.clone();
-        let gpu_device = cfg.gpu_device; // Clone the GPU device ID before moving cfg
         std::thread::spawn(move || {
-            println!("Starting search on platform {:?} GPU {}", platform_id, device_id);
-            if let Err(e) = gpu(cfg) {
-                eprintln!("GPU {} search failed: {}", gpu_device, e);
-            }
+            info(&cfg, &format!("Starting search on platform {:?} GPU {}", platform_id, device_id));
+            let gpu_device = cfg.gpu_device;
+            let result = gpu(cfg).map_err(|e| format!("GPU {} search failed: {}", gpu_device, e));
             // Signal that we're done (either success or failure)
-            let _ = tx.send(());
+            let _ = tx.send(result);
         })
     }).collect();
+    drop(tx);
     
-    // Wait for the first GPU to find a solution
-    let _ = rx.recv();
-    
-    // All threads will exit when the main thread exits
-    println!("Solution found! Exiting...");
-    
-    Ok(())
-}
-
-// Helper function to parse an address from a hex string
-fn parse_address(address_str: &str) -> Result<[u8; 20], Box<dyn Error>> {
-    let address_str = if address_str.starts_with("0x") {
-        &address_str[2..]
-    } else {
-        address_str
-    };
-    
-    // Ensure the string has an even length
-    let address_str = if address_str.len() % 2 != 0 {
-        format!("0{}", address_str) // Pad with a leading zero if needed
-    } else {
-        address_str.to_string()
-    };
-    
-    let bytes = hex::decode(&address_str)?;
-    if bytes.len() != 20 {
-        // If we don't have exactly 20 bytes, pad or truncate
-        let mut result = [0u8; 20];
-        let copy_len = std::cmp::min(bytes.len(), 20);
-        result[20 - copy_len..].copy_from_slice(&bytes[..copy_len]);
-        return Ok(result);
-    }
-    
-    let mut result = [0u8; 20];
-    result.copy_from_slice(&bytes);
-    Ok(result)
-}
-
-// Helper function to parse a hash from a hex string
-fn parse_hash(hash_str: &str) -> Result<[u8; 32], Box<dyn Error>> {
-    let hash_str = if hash_str.starts_with("0x") {
-        &hash_str[2..]
-    } else {
-        hash_str
-    };
-    
-    // Ensure the string has an even length
-    let hash_str = if hash_str.len() % 2 != 0 {
-        format!("0{}", hash_str) // Pad with a leading zero if needed
-    } else {
-        hash_str.to_string()
-    };
-    
-    let bytes = hex::decode(&hash_str)?;
-    if bytes.len() != 32 {
-        // If we don't have exactly 32 bytes, pad or truncate
-        let mut result = [0u8; 32];
-        let copy_len = std::cmp::min(bytes.len(), 32);
-        result[32 - copy_len..].copy_from_slice(&bytes[..copy_len]);
-        return Ok(result);
+    // Wait for the first GPU to find a solution, reporting failures as they come in
+    let mut best: Option<Solution> = None;
+    for result in rx {
+        match result {
+            // a stopped scoring search hears back from every device; keep
+            // the best of their results
+            Ok(solution) if base_config.stop.load(Ordering::SeqCst) => {
+                if best.as_ref().is_none_or(|best| best.score < solution.score) {
+                    best = Some(solution);
+                }
+            }
+            Ok(solution) => {
+                // All threads will exit when the main thread exits
+                info(&base_config, "Solution found! Exiting...");
+                return Ok(solution);
+            }
+            // a stopped search ends with every device stopping
+            Err(_) if base_config.stop.load(Ordering::SeqCst) => {}
+            Err(e) => emit(&base_config, Event::Error(&e)),
+        }
     }
     
-    let mut result = [0u8; 32];
-    result.copy_from_slice(&bytes);
-    Ok(result)
+    best.ok_or_else(|| "all GPU searches failed".into())
 }
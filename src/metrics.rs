@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::output::OutputFormat;
+use crate::serve::{read_line, MAX_LINE_LEN, READ_TIMEOUT};
+
+/// Upper bounds of the batch latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 12] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Counters of one device.
+#[derive(Clone, Debug, Default)]
+struct DeviceMetrics {
+    hashes: u64,
+    /// Hashes per second over the last batch.
+    rate: f64,
+    /// Batches per latency bucket; the last counts batches over every bound.
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    batch_seconds: f64,
+    errors: u64,
+}
+
+/// Counters of a running search, shared by all devices and served in the
+/// Prometheus text format by `serve_metrics`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    devices: Mutex<BTreeMap<u32, DeviceMetrics>>,
+    solutions: AtomicU64,
+    improvements: AtomicU64,
+}
+
+impl Metrics {
+    /// Record a batch of `hashes` nonces that took `seconds` on `device`.
+    pub fn record_batch(&self, device: u32, hashes: u64, seconds: f64) {
+        let mut devices = self.devices.lock().unwrap();
+        let metrics = devices.entry(device).or_default();
+        metrics.hashes += hashes;
+        metrics.rate = if seconds > 0.0 { hashes as f64 / seconds } else { 0.0 };
+        let bucket = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound).unwrap_or(LATENCY_BUCKETS.len());
+        metrics.buckets[bucket] += 1;
+        metrics.batch_seconds += seconds;
+    }
+
+    /// Record a batch that failed or reported a wrong result on `device`.
+    pub fn record_error(&self, device: u32) {
+        self.devices.lock().unwrap().entry(device).or_default().errors += 1;
+    }
+
+    /// Record a matching address.
+    pub fn record_solution(&self) {
+        self.solutions.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Record a new best score.
+    pub fn record_improvement(&self) {
+        self.improvements.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The metrics in the Prometheus text exposition format, with `attempts`
+    /// as the total over all devices.
+    pub fn render(&self, attempts: u64) -> String {
+        let devices = self.devices.lock().unwrap().clone();
+        let mut text = String::new();
+
+        // each family is written in full before the next, as the format requires
+        header(&mut text, "attempts_total", "counter", "Nonces tested by all devices.");
+        let _ = writeln!(text, "create2gpu_attempts_total {}", attempts);
+
+        header(&mut text, "solutions_total", "counter", "Addresses found that match the pattern.");
+        let _ = writeln!(text, "create2gpu_solutions_total {}", self.solutions.load(Ordering::SeqCst));
+
+        header(&mut text, "improvements_total", "counter", "New best scores found while scoring.");
+        let _ = writeln!(text, "create2gpu_improvements_total {}", self.improvements.load(Ordering::SeqCst));
+
+        header(&mut text, "device_hashes_total", "counter", "Nonces hashed by each device.");
+        for (device, metrics) in &devices {
+            let _ = writeln!(text, "create2gpu_device_hashes_total{{device=\"{}\"}} {}", device, metrics.hashes);
+        }
+
+        header(&mut text, "device_hash_rate", "gauge", "Hashes per second of each device over its last batch.");
+        for (device, metrics) in &devices {
+            let _ = writeln!(text, "create2gpu_device_hash_rate{{device=\"{}\"}} {}", device, metrics.rate);
+        }
+
+        header(&mut text, "batch_duration_seconds", "histogram", "Time each device took per batch.");
+        for (device, metrics) in &devices {
+            let mut count = 0;
+            for (bound, batches) in LATENCY_BUCKETS.iter().zip(&metrics.buckets) {
+                count += batches;
+                let _ = writeln!(text, "create2gpu_batch_duration_seconds_bucket{{device=\"{}\",le=\"{}\"}} {}", device, bound, count);
+            }
+            count += metrics.buckets[LATENCY_BUCKETS.len()];
+            let _ = writeln!(text, "create2gpu_batch_duration_seconds_bucket{{device=\"{}\",le=\"+Inf\"}} {}", device, count);
+            let _ = writeln!(text, "create2gpu_batch_duration_seconds_sum{{device=\"{}\"}} {}", device, metrics.batch_seconds);
+            let _ = writeln!(text, "create2gpu_batch_duration_seconds_count{{device=\"{}\"}} {}", device, count);
+        }
+
+        header(&mut text, "kernel_errors_total", "counter", "Batches that failed or reported a wrong result on each device.");
+        for (device, metrics) in &devices {
+            let _ = writeln!(text, "create2gpu_kernel_errors_total{{device=\"{}\"}} {}", device, metrics.errors);
+        }
+
+        text
+    }
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP create2gpu_{} {}\n# TYPE create2gpu_{} {}", name, help, name, kind);
+}
+
+/// Serve `metrics` on `addr` at `GET /metrics` from a background thread,
+/// with `attempts` as the total over all devices. Returns once the address
+/// is bound.
+pub fn serve_metrics(addr: &str, metrics: Arc<Metrics>, attempts: Arc<AtomicU64>, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("could not listen on {}: {}", addr, e))?;
+    format.info(&format!("Serving metrics on http://{}/metrics", listener.local_addr()?));
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream
+                .map_err(|e| e.into())
+                .and_then(|stream| answer(stream, &metrics, attempts.load(Ordering::SeqCst)));
+            if let Err(e) = result {
+                format.error(&format!("could not answer a metrics request: {}", e));
+            }
+        }
+    });
+    Ok(())
+}
+
+// Answer one scrape and close the connection. Scrapes are quick, so they are
+// answered one at a time on the listening thread; a client that stops
+// sending holds it for at most READ_TIMEOUT
+fn answer(mut stream: TcpStream, metrics: &Metrics, attempts: u64) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request_line = read_line(&mut reader)?;
+    let mut too_long = request_line.is_none();
+    while !too_long {
+        match read_line(&mut reader)? {
+            Some(header) if header.trim().is_empty() => break,
+            Some(_) => {}
+            None => too_long = true,
+        }
+    }
+
+    let mut parts = request_line.as_deref().unwrap_or_default().split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        _ if too_long => (
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            format!("request line or header is over {} bytes\n", MAX_LINE_LEN),
+        ),
+        (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4", metrics.render(attempts)),
+        _ => ("404 Not Found", "text/plain", "metrics are served at GET /metrics\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    // The value of the sample line that starts with `series`
+    fn sample(text: &str, series: &str) -> u64 {
+        let line = text.lines().find(|line| line.starts_with(series)).unwrap();
+        line[series.len()..].trim().parse().unwrap()
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        for seconds in &[0.0005, 0.02, 0.02, 0.3, 3.0, 60.0] {
+            metrics.record_batch(0, 1000, *seconds);
+        }
+        metrics.record_batch(1, 1000, 0.002);
+        let text = metrics.render(7000);
+
+        let counts: Vec<u64> = text
+            .lines()
+            .filter(|line| line.starts_with("create2gpu_batch_duration_seconds_bucket{device=\"0\""))
+            .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(counts.len(), LATENCY_BUCKETS.len() + 1);
+        assert!(counts.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", counts);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_bucket{device=\"0\",le=\"0.001\"}"), 1);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_bucket{device=\"0\",le=\"0.025\"}"), 3);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_bucket{device=\"0\",le=\"10\"}"), 5);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_bucket{device=\"0\",le=\"+Inf\"}"), 6);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_count{device=\"0\"}"), 6);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_bucket{device=\"1\",le=\"+Inf\"}"), 1);
+        assert_eq!(sample(&text, "create2gpu_batch_duration_seconds_count{device=\"1\"}"), 1);
+        assert_eq!(sample(&text, "create2gpu_device_hashes_total{device=\"0\"}"), 6000);
+        assert_eq!(sample(&text, "create2gpu_attempts_total"), 7000);
+    }
+
+    #[test]
+    fn every_family_is_declared_before_its_samples() {
+        let metrics = Metrics::default();
+        metrics.record_batch(0, 1000, 0.1);
+        metrics.record_batch(1, 1000, 0.1);
+        metrics.record_error(1);
+        metrics.record_solution();
+
+        let mut help = None;
+        let mut family: Option<String> = None;
+        for line in metrics.render(2000).lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                help = rest.split(' ').next();
+                family = None;
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let name = rest.split(' ').next().unwrap();
+                assert_eq!(help, Some(name), "TYPE without HELP: {}", line);
+                family = Some(name.to_string());
+            } else {
+                let name = line.split(['{', ' ']).next().unwrap();
+                let family = family.as_deref().unwrap_or_else(|| panic!("sample before its TYPE: {}", line));
+                let suffix = name.strip_prefix(family).unwrap_or_else(|| panic!("{} is not in {}", name, family));
+                assert!(["", "_bucket", "_sum", "_count"].contains(&suffix), "{} is not in {}", name, family);
+            }
+        }
+    }
+
+    #[test]
+    fn overlong_requests_are_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        // one byte over the limit and nothing after it, so that the server
+        // reads all of it and closes without a reset
+        write!(client, "GET /{}", "a".repeat(MAX_LINE_LEN - 4)).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        answer(stream, &Metrics::default(), 0).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+    }
+}
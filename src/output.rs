@@ -0,0 +1,371 @@
+use std::fmt;
+use std::str::FromStr;
+
+use separator::Separatable;
+use serde_json::{json, Value};
+use tiny_keccak::Keccak;
+
+use crate::abi::format_uint;
+use crate::deploy::Deployment;
+use crate::{AddressScheme, Config};
+use crate::difficulty::{format_duration, Difficulty};
+use crate::hooks::hook_flag_names;
+
+/// How search events are written to stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Human-readable status dashboard and solution printout.
+    #[default]
+    Text,
+    /// One JSON object per line for each progress, solution or error event.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format '{}' (expected text or json)", s)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Print an informational message. In JSON mode these go to stderr so
+    /// that stdout only carries events.
+    pub fn info(&self, message: &str) {
+        match self {
+            OutputFormat::Json => eprintln!("{}", message),
+            OutputFormat::Text => println!("{}", message),
+        }
+    }
+
+    /// Report an error as an `error` event.
+    pub fn error(&self, message: &str) {
+        match self {
+            OutputFormat::Json => println!("{}", Event::Error(message).to_json()),
+            OutputFormat::Text => eprintln!("Error: {}", message),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Periodic status of a running search on one device.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub device: u32,
+    pub elapsed_seconds: f64,
+    pub attempts: u64,
+    pub work_size: u64,
+    /// High 32 bits of the nonce for the batch that was just searched.
+    pub search_space: u32,
+    pub difficulty: Difficulty,
+    /// Best address this device has found, when scoring.
+    pub best: Option<Solution>,
+}
+
+impl Progress {
+    /// Attempts per second since the search started.
+    pub fn rate(&self) -> f64 {
+        if self.elapsed_seconds > 0.0 {
+            self.attempts as f64 / self.elapsed_seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Expected time to the first solution at the current rate.
+    pub fn expected_seconds(&self) -> f64 {
+        self.difficulty.expected_seconds(self.rate())
+    }
+
+    /// Probability of having found a solution by now.
+    pub fn probability(&self) -> f64 {
+        self.difficulty.probability_found(self.attempts)
+    }
+}
+
+/// A salt that yields an address matching the search criteria, or the best
+/// score so far when scoring, verified on the host.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub device: u32,
+    pub salt: [u8; 32],
+    pub address: [u8; 20],
+    pub init_code_hash: [u8; 32],
+    pub deployer: [u8; 20],
+    pub caller: [u8; 20],
+    pub attempts: u64,
+    pub elapsed_seconds: f64,
+    /// Score of the address, when scoring.
+    pub score: Option<u32>,
+    /// The nonce to pass to the factory, when the factory derives the salt
+    /// from it.
+    pub salt_nonce: Option<[u8; 32]>,
+}
+
+/// The totals of a search that was stopped, including any earlier runs
+/// resumed from a checkpoint.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub attempts: u64,
+    pub elapsed_seconds: f64,
+    /// The best candidate so far, as a `solution` event.
+    pub best: Option<Value>,
+}
+
+impl Summary {
+    /// Attempts per second over the whole search.
+    pub fn rate(&self) -> f64 {
+        if self.elapsed_seconds > 0.0 {
+            self.attempts as f64 / self.elapsed_seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The address a salt deploys to, computed on the host by `predict` and
+/// `verify`.
+#[derive(Clone, Debug)]
+pub struct Prediction {
+    pub deployer: [u8; 20],
+    pub salt: [u8; 32],
+    pub init_code_hash: [u8; 32],
+    pub address: [u8; 20],
+    /// The nonce the salt was derived from, for factories that hash it.
+    pub salt_nonce: Option<[u8; 32]>,
+}
+
+impl Prediction {
+    pub fn new(address_scheme: AddressScheme, deployer: [u8; 20], salt: [u8; 32], init_code_hash: [u8; 32]) -> Self {
+        let address = address_scheme.address(&deployer, &salt, &init_code_hash);
+        Prediction { deployer, salt, init_code_hash, address, salt_nonce: None }
+    }
+
+    /// Print the prediction as a `prediction` event, or as a `verified`
+    /// event once it has been checked against a claimed address.
+    pub fn report(&self, format: OutputFormat, verified: bool) {
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                json!({
+                    "event": if verified { "verified" } else { "prediction" },
+                    "address": format!("0x{}", hex::encode(self.address)),
+                    "checksum_address": to_checksum_address(&hex::encode(self.address)),
+                    "deployer": to_checksum_address(&hex::encode(self.deployer)),
+                    "salt": format!("0x{}", hex::encode(self.salt)),
+                    "init_code_hash": format!("0x{}", hex::encode(self.init_code_hash)),
+                    "salt_nonce": self.salt_nonce.as_ref().map(format_uint),
+                })
+            ),
+            OutputFormat::Text => {
+                println!("Deployer: {}", to_checksum_address(&hex::encode(self.deployer)));
+                println!("Creation Code Hash: 0x{}", hex::encode(self.init_code_hash));
+                println!("Salt: 0x{}", hex::encode(self.salt));
+                if let Some(ref salt_nonce) = self.salt_nonce {
+                    println!("Salt Nonce: {}", format_uint(salt_nonce));
+                }
+                if verified {
+                    println!("Verified Address: {}", to_checksum_address(&hex::encode(self.address)));
+                } else {
+                    println!("Address: {}", to_checksum_address(&hex::encode(self.address)));
+                }
+            }
+        }
+    }
+}
+
+/// Everything the miner reports while running.
+pub enum Event<'a> {
+    Progress(&'a Progress),
+    Solution(&'a Solution),
+    /// A new best score while scoring.
+    Improvement(&'a Solution),
+    /// The transaction that deploys the solution.
+    Deployment(&'a Deployment),
+    /// The search was stopped.
+    Stopped(&'a Summary),
+    Error(&'a str),
+}
+
+impl<'a> Event<'a> {
+    /// The JSON representation of the event. Field names are part of the
+    /// public interface; add new fields rather than renaming existing ones.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Event::Progress(p) => {
+                let mut event = json!({
+                    "event": "progress",
+                    "device": p.device,
+                    "elapsed_seconds": p.elapsed_seconds,
+                    "attempts": p.attempts,
+                    "rate": p.rate(),
+                    "work_size": p.work_size,
+                    "expected_attempts": p.difficulty.expected_attempts(),
+                    "expected_seconds": p.expected_seconds(),
+                    "probability": p.probability(),
+                });
+                if let Some(ref best) = p.best {
+                    event["best_score"] = json!(best.score);
+                }
+                event
+            }
+            Event::Solution(s) | Event::Improvement(s) => json!({
+                "event": if let Event::Solution(_) = self { "solution" } else { "improvement" },
+                "device": s.device,
+                "salt": format!("0x{}", hex::encode(s.salt)),
+                "address": format!("0x{}", hex::encode(s.address)),
+                "checksum_address": to_checksum_address(&hex::encode(s.address)),
+                "init_code_hash": format!("0x{}", hex::encode(s.init_code_hash)),
+                "deployer": to_checksum_address(&hex::encode(s.deployer)),
+                "caller": to_checksum_address(&hex::encode(s.caller)),
+                "attempts": s.attempts,
+                "elapsed_seconds": s.elapsed_seconds,
+                "score": s.score,
+                "salt_nonce": s.salt_nonce.as_ref().map(format_uint),
+            }),
+            Event::Deployment(d) => json!({
+                "event": "deployment",
+                "via": d.via,
+                "to": to_checksum_address(&hex::encode(d.to)),
+                "address": to_checksum_address(&hex::encode(d.address)),
+                "calldata": format!("0x{}", hex::encode(&d.calldata)),
+                "cast_command": d.cast_command(),
+            }),
+            Event::Stopped(s) => json!({
+                "event": "stopped",
+                "attempts": s.attempts,
+                "elapsed_seconds": s.elapsed_seconds,
+                "rate": s.rate(),
+                "best": s.best,
+            }),
+            Event::Error(message) => json!({
+                "event": "error",
+                "message": message,
+            }),
+        }
+    }
+}
+
+/// Write an event to stdout in the configured format.
+pub fn emit(config: &Config, event: Event) {
+    match config.format {
+        OutputFormat::Json => println!("{}", event.to_json()),
+        OutputFormat::Text => print_text(config, &event),
+    }
+}
+
+/// Print an informational message in the configured format.
+pub fn info(config: &Config, message: &str) {
+    config.format.info(message);
+}
+
+fn print_text(config: &Config, event: &Event) {
+    match event {
+        Event::Progress(p) => config.dashboard.progress(config, p),
+        Event::Improvement(s) => config.dashboard.found(config, s, || {
+            println!("\nNew best score {} after {:.2} seconds!", s.score.unwrap_or_default(), s.elapsed_seconds);
+            println!("Address: {}", to_checksum_address(&hex::encode(s.address)));
+            println!("Creation Code Hash: 0x{}", hex::encode(s.init_code_hash));
+            println!("Salt: 0x{}", hex::encode(s.salt));
+            if let Some(ref salt_nonce) = s.salt_nonce {
+                println!("Salt Nonce: {}", format_uint(salt_nonce));
+            }
+        }),
+        Event::Solution(s) => config.dashboard.found(config, s, || {
+            match s.score {
+                Some(score) => println!("\nBest score {} found in {:.2} seconds", score, s.elapsed_seconds),
+                None => println!("\nFound valid solution with {} in {:.2} seconds!", describe_criteria(config), s.elapsed_seconds),
+            }
+            println!("Address: {}", to_checksum_address(&hex::encode(s.address)));
+            println!("Creation Code Hash: 0x{}", hex::encode(s.init_code_hash));
+            println!("Salt: 0x{}", hex::encode(s.salt));
+            if let Some(ref salt_nonce) = s.salt_nonce {
+                println!("Salt Nonce: {}", format_uint(salt_nonce));
+            }
+            println!("Verified Address: {}", to_checksum_address(&hex::encode(s.address)));
+        }),
+        Event::Deployment(d) => {
+            println!("\nDeploy with {}:", d.via);
+            println!("To: {}", to_checksum_address(&hex::encode(d.to)));
+            println!("Calldata: 0x{}", hex::encode(&d.calldata));
+            println!("{}", d.cast_command());
+        }
+        Event::Stopped(s) => {
+            println!("\nSearch stopped after {}", format_duration(s.elapsed_seconds));
+            println!("Total attempts: {} ({:.2} million per second on average)", s.attempts.separated_string(), s.rate() / 1_000_000.0);
+            match s.best {
+                Some(ref best) => println!(
+                    "Best candidate: {}{}  salt {}",
+                    best["score"].as_u64().map(|score| format!("score {}  ", score)).unwrap_or_default(),
+                    best["checksum_address"].as_str().unwrap_or_default(),
+                    best["salt"].as_str().unwrap_or_default()
+                ),
+                None => println!("Best candidate: none"),
+            }
+        }
+        Event::Error(message) => config.dashboard.print_above(config, || config.format.error(message)),
+    }
+}
+
+/// The pattern of a search, such as `prefix 'dead' and suffix 'beef'`.
+pub fn describe_criteria(config: &Config) -> String {
+    let mut criteria = Vec::new();
+    if !config.starts_with.is_empty() {
+        criteria.push(format!("prefix '{}'", config.starts_with));
+    }
+    if !config.ends_with.is_empty() {
+        criteria.push(format!("suffix '{}'", config.ends_with));
+    }
+    if let Some(flags) = config.hook_flags {
+        criteria.push(format!("hook flags 0x{:04x} ({})", flags, hook_flag_names(flags).join(", ")));
+    }
+    criteria.join(" and ")
+}
+
+/// Convert an address to its EIP-55 checksummed form.
+pub fn to_checksum_address(address: &str) -> String {
+    // Remove '0x' prefix if present
+    let address = address.strip_prefix("0x").unwrap_or(address);
+
+    // Convert address to lowercase
+    let address = address.to_lowercase();
+
+    // Hash the address
+    let mut hasher = Keccak::new_keccak256();
+    hasher.update(address.as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+
+    // Create checksummed address
+    let mut checksummed = String::with_capacity(42);
+    checksummed.push_str("0x");
+
+    for (i, c) in address.chars().enumerate() {
+        if c.is_ascii_digit() {
+            checksummed.push(c);
+        } else {
+            // Get the corresponding nibble from the hash
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0xf;
+            if nibble >= 8 {
+                checksummed.push(c.to_ascii_uppercase());
+            } else {
+                checksummed.push(c);
+            }
+        }
+    }
+
+    checksummed
+}
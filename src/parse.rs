@@ -0,0 +1,261 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::output::to_checksum_address;
+
+/// Why a command-line address, hash or pattern was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The value was empty (after removing any `0x` prefix).
+    Empty { field: &'static str },
+    /// The value contains a character that is not a hex digit.
+    InvalidHex { field: &'static str, value: String, character: char },
+    /// The value has the wrong number of hex digits.
+    WrongLength { field: &'static str, value: String, expected: usize, actual: usize },
+    /// A mixed-case address does not match its EIP-55 checksum.
+    BadChecksum { field: &'static str, value: String, expected: String },
+    /// A pattern is longer than the 40 hex digits of an address.
+    PatternTooLong { field: &'static str, value: String, length: usize },
+    /// The prefix and suffix overlap and require different digits at the
+    /// same position of the address.
+    PatternConflict { position: usize, prefix_digit: char, suffix_digit: char },
+    /// A name in `--hook-flags` is not a Uniswap v4 hook permission.
+    UnknownHookFlag { field: &'static str, name: String },
+    /// The prefix reaches into the last four digits, which hold the hook
+    /// permission bits.
+    HookPrefixOverlap { value: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty { field } => write!(f, "{} is empty", field),
+            ParseError::InvalidHex { field, value, character } => {
+                write!(f, "{} '{}' contains non-hex character '{}'", field, value, character)
+            }
+            ParseError::WrongLength { field, value, expected, actual } => write!(
+                f,
+                "{} '{}' has {} hex digits but must have exactly {}",
+                field, value, actual, expected
+            ),
+            ParseError::BadChecksum { field, value, expected } => write!(
+                f,
+                "{} '{}' has an invalid EIP-55 checksum (expected {}); use all lowercase to skip the check",
+                field, value, expected
+            ),
+            ParseError::PatternTooLong { field, value, length } => write!(
+                f,
+                "{} '{}' has {} hex digits but an address only has 40",
+                field, value, length
+            ),
+            ParseError::PatternConflict { position, prefix_digit, suffix_digit } => write!(
+                f,
+                "prefix and suffix overlap at address digit {} and disagree ('{}' vs '{}')",
+                position, prefix_digit, suffix_digit
+            ),
+            ParseError::UnknownHookFlag { field, name } => write!(
+                f,
+                "{} has unknown hook permission '{}' (expected names such as beforeSwap or afterAddLiquidity)",
+                field, name
+            ),
+            ParseError::HookPrefixOverlap { value } => write!(
+                f,
+                "prefix '{}' has {} hex digits, but with hook flags at most 36 are free",
+                value,
+                value.len()
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a 20-byte address. The value must have exactly 40 hex digits, with
+/// an optional `0x` prefix. Mixed-case values are checked against their
+/// EIP-55 checksum; all-lowercase or all-uppercase values are accepted as-is.
+pub fn parse_address(field: &'static str, value: &str) -> Result<[u8; 20], ParseError> {
+    let digits = parse_hex_digits(field, value, 40)?;
+
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        let expected = to_checksum_address(&digits);
+        if expected[2..] != digits {
+            return Err(ParseError::BadChecksum { field, value: value.to_string(), expected });
+        }
+    }
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&decode(&digits));
+    Ok(address)
+}
+
+/// Parse a 32-byte hash. The value must have exactly 64 hex digits, with an
+/// optional `0x` prefix.
+pub fn parse_hash(field: &'static str, value: &str) -> Result<[u8; 32], ParseError> {
+    let digits = parse_hex_digits(field, value, 64)?;
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&decode(&digits));
+    Ok(hash)
+}
+
+/// Parse a 4-byte selector read as a big-endian number: exactly 8 hex
+/// digits, with an optional `0x` prefix.
+pub fn parse_selector(field: &'static str, value: &str) -> Result<u32, ParseError> {
+    let digits = parse_hex_digits(field, value, 8)?;
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&decode(&digits));
+    Ok(u32::from_be_bytes(selector))
+}
+
+/// Parse an address pattern such as a prefix or suffix: up to 40 hex digits
+/// with an optional `0x` prefix and surrounding whitespace. The result is
+/// lowercased.
+pub fn parse_pattern(field: &'static str, value: &str) -> Result<String, ParseError> {
+    let digits = strip_0x(value.trim());
+
+    if let Some(character) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ParseError::InvalidHex { field, value: value.to_string(), character });
+    }
+
+    if digits.len() > 40 {
+        return Err(ParseError::PatternTooLong { field, value: value.to_string(), length: digits.len() });
+    }
+
+    Ok(digits.to_lowercase())
+}
+
+/// Check that a prefix and suffix can both hold for the same address. They
+/// may only overlap if they agree on every shared digit.
+pub fn check_patterns(prefix: &str, suffix: &str) -> Result<(), ParseError> {
+    let suffix_start = 40usize.saturating_sub(suffix.len());
+
+    for (position, prefix_digit) in prefix.chars().enumerate().skip(suffix_start) {
+        let suffix_digit = suffix[position - suffix_start..].chars().next().unwrap();
+        if prefix_digit != suffix_digit {
+            return Err(ParseError::PatternConflict { position, prefix_digit, suffix_digit });
+        }
+    }
+
+    Ok(())
+}
+
+fn strip_0x(value: &str) -> &str {
+    value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value)
+}
+
+fn parse_hex_digits(field: &'static str, value: &str, expected: usize) -> Result<String, ParseError> {
+    let digits = strip_0x(value.trim());
+
+    if digits.is_empty() {
+        return Err(ParseError::Empty { field });
+    }
+
+    if let Some(character) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ParseError::InvalidHex { field, value: value.to_string(), character });
+    }
+
+    if digits.len() != expected {
+        return Err(ParseError::WrongLength {
+            field,
+            value: value.to_string(),
+            expected,
+            actual: digits.len(),
+        });
+    }
+
+    Ok(digits.to_string())
+}
+
+/// Decode a string already known to hold an even number of hex digits.
+fn decode(digits: &str) -> Vec<u8> {
+    hex::decode(digits).expect("digits were validated as hex")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checksummed addresses from EIP-55
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn addresses_with_a_valid_checksum_or_one_case_are_accepted() {
+        for address in CHECKSUMMED.iter() {
+            let expected = hex::decode(&address[2..]).unwrap();
+            assert_eq!(parse_address("deployer", address).unwrap().to_vec(), expected);
+            assert_eq!(parse_address("deployer", &address.to_lowercase()).unwrap().to_vec(), expected);
+            assert_eq!(parse_address("deployer", &address[2..].to_uppercase()).unwrap().to_vec(), expected);
+        }
+        assert_eq!(parse_address("deployer", " 0X00000000000000000000000000000000000000ff ").unwrap()[19], 0xff);
+    }
+
+    #[test]
+    fn addresses_with_a_bad_checksum_are_rejected() {
+        let value = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        let error = parse_address("caller", value).unwrap_err();
+        assert_eq!(error, ParseError::BadChecksum { field: "caller", value: value.to_string(), expected: CHECKSUMMED[0].to_string() });
+        assert_eq!(
+            error.to_string(),
+            "caller '0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD' has an invalid EIP-55 checksum \
+             (expected 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed); use all lowercase to skip the check"
+        );
+    }
+
+    #[test]
+    fn wrong_lengths_are_rejected() {
+        assert_eq!(parse_address("deployer", "0x").unwrap_err().to_string(), "deployer is empty");
+        assert_eq!(
+            parse_address("deployer", "0x1234").unwrap_err().to_string(),
+            "deployer '0x1234' has 4 hex digits but must have exactly 40"
+        );
+        // an odd number of digits is a length error, not a hex error
+        assert_eq!(
+            parse_hash("init code hash", &format!("0x{}", "1".repeat(63))).unwrap_err(),
+            ParseError::WrongLength { field: "init code hash", value: format!("0x{}", "1".repeat(63)), expected: 64, actual: 63 }
+        );
+        assert!(parse_hash("init code hash", &"11".repeat(33)).is_err());
+        assert_eq!(parse_hash("init code hash", &"11".repeat(32)).unwrap(), [0x11; 32]);
+        assert_eq!(parse_selector("target", "0xa9059cbb").unwrap(), 0xa9059cbb);
+        assert!(parse_selector("target", "0xa9059cb").is_err());
+    }
+
+    #[test]
+    fn non_hex_characters_are_rejected() {
+        assert_eq!(
+            parse_hash("init code hash", &format!("0x{}g", "1".repeat(63))).unwrap_err().to_string(),
+            format!("init code hash '0x{}g' contains non-hex character 'g'", "1".repeat(63))
+        );
+        assert_eq!(parse_pattern("prefix", "0xdeadbeez").unwrap_err().to_string(), "prefix '0xdeadbeez' contains non-hex character 'z'");
+    }
+
+    #[test]
+    fn patterns_are_lowercased_and_at_most_40_digits() {
+        assert_eq!(parse_pattern("prefix", "0xDEAD").unwrap(), "dead");
+        assert_eq!(parse_pattern("prefix", " dead\n").unwrap(), "dead");
+        assert_eq!(parse_pattern("prefix", " 0xDEAD ").unwrap(), "dead");
+        assert_eq!(parse_pattern("suffix", "").unwrap(), "");
+        assert_eq!(parse_pattern("suffix", &"a".repeat(40)).unwrap(), "a".repeat(40));
+        assert_eq!(
+            parse_pattern("suffix", &"a".repeat(41)).unwrap_err().to_string(),
+            format!("suffix '{}' has 41 hex digits but an address only has 40", "a".repeat(41))
+        );
+    }
+
+    #[test]
+    fn overlapping_patterns_must_agree() {
+        check_patterns("dead", "beef").unwrap();
+        check_patterns(&"ab".repeat(20), &"ab".repeat(20)).unwrap();
+        check_patterns(&format!("{}ab", "0".repeat(36)), "abcd").unwrap();
+        assert_eq!(
+            check_patterns(&format!("{}ab", "0".repeat(36)), "acbd").unwrap_err().to_string(),
+            "prefix and suffix overlap at address digit 37 and disagree ('b' vs 'c')"
+        );
+    }
+}
@@ -0,0 +1,674 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::{cpu_miner, cpu_share_miner};
+use crate::difficulty::{format_duration, Difficulty};
+use crate::factory::Factory;
+use crate::gpu::{gpu_miner, gpu_share_miner};
+use crate::output::{emit, info, Event, OutputFormat, Solution};
+use crate::search::{matches_criteria, share_config, Miner, ShareBatch, ShareMiner};
+use crate::serve::JobSpec;
+use crate::{Config, HighBytes};
+
+/// Version of the coordinator/worker protocol. Both ends must speak the
+/// same one.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Seconds of work the coordinator aims to hand out in each range.
+const RANGE_SECONDS: f64 = 10.0;
+
+/// Seconds between the coordinator's status lines.
+const STATUS_INTERVAL: f64 = 5.0;
+
+/// Shares a worker must be expected to have found before its count is
+/// checked.
+const MIN_EXPECTED_SHARES: f64 = 20.0;
+
+/// Standard deviations below the expected share count at which a worker is
+/// flagged.
+const SHARE_DEVIATIONS: f64 = 4.0;
+
+/// How long the coordinator waits for a worker's next message before it
+/// drops the worker and hands its range out again. Ranges are sized to take
+/// RANGE_SECONDS, so a worker this slow has stalled.
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A message between the coordinator and a worker, sent as one JSON object
+/// per line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// From a worker when it connects.
+    Hello { version: u32, name: String },
+    /// The search to run. Its high bytes are resolved to a counter, so that
+    /// every worker derives the same salts from the same nonces. With
+    /// `share_digits` above zero, workers also report near-miss shares that
+    /// match that many digits of the pattern.
+    Job { job: JobSpec, share_digits: usize },
+    /// From a worker once its miner is set up.
+    Ready { batch_size: u64 },
+    /// Test the nonces `start..start + count`.
+    Range { start: u64, count: u64 },
+    /// From a worker when it has tested a range, or stopped early at a
+    /// matching nonce `hit`, at `rate` nonces per second, with the shares it
+    /// found in order.
+    Done { start: u64, count: u64, rate: f64, hit: Option<u64>, shares: Vec<u64> },
+    /// The job is over, or the worker was turned away.
+    Stop { reason: String },
+}
+
+fn send(stream: &mut TcpStream, message: &Message) -> Result<(), Box<dyn Error>> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+// The next message, or None once the other end has closed the connection
+fn receive(reader: &mut BufReader<TcpStream>) -> Result<Option<Message>, Box<dyn Error>> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => return Ok(None),
+        Ok(_) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            return Err("timed out waiting for the next message".into())
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let message = serde_json::from_str(&line).map_err(|e| format!("invalid message: {}", e))?;
+    Ok(Some(message))
+}
+
+/// A connected worker, as the coordinator sees it.
+struct WorkerState {
+    name: String,
+    /// Nonces per second over its last range.
+    rate: f64,
+    /// The range it is working on.
+    range: Option<(u64, u64)>,
+    /// Nonces it has tested, and the seconds it took.
+    searched: u64,
+    busy_seconds: f64,
+    /// Shares it has reported.
+    shares: u64,
+    /// Whether it has already been flagged for too few shares.
+    flagged: bool,
+}
+
+impl WorkerState {
+    fn new(name: String) -> Self {
+        WorkerState { name, rate: 0.0, range: None, searched: 0, busy_seconds: 0.0, shares: 0, flagged: false }
+    }
+}
+
+/// The coordinator's record of a job: which nonces have been handed out,
+/// who is working on them and what came back.
+struct Pool {
+    config: Config,
+    factory: Option<Factory>,
+    /// The criteria of near-miss shares, and the digits they match.
+    share_config: Option<Config>,
+    share_digits: usize,
+    /// The first nonce no worker has been given yet.
+    next: u64,
+    /// Ranges of workers that left before finishing them, to hand out again.
+    returned: Vec<(u64, u64)>,
+    /// Nonces tested so far by all workers.
+    searched: u64,
+    workers: BTreeMap<u32, WorkerState>,
+    next_worker: u32,
+    solution: Option<Solution>,
+    start_time: Instant,
+    previous_status: f64,
+}
+
+impl Pool {
+    fn new(config: Config, factory: Option<Factory>, share_config: Option<Config>, share_digits: usize) -> Self {
+        Pool {
+            config,
+            factory,
+            share_config,
+            share_digits,
+            next: 0,
+            returned: Vec::new(),
+            searched: 0,
+            workers: BTreeMap::new(),
+            next_worker: 0,
+            solution: None,
+            start_time: Instant::now(),
+            previous_status: 0.0,
+        }
+    }
+
+    // A range for a worker: a returned one if there is any, otherwise about
+    // RANGE_SECONDS of fresh nonces at its rate, in whole batches
+    fn take_range(&mut self, batch_size: u64, rate: f64) -> (u64, u64) {
+        if let Some(range) = self.returned.pop() {
+            return range;
+        }
+        let batches = (rate * RANGE_SECONDS / batch_size as f64).max(1.0) as u64;
+        let start = self.next;
+        let count = batches.saturating_mul(batch_size);
+        self.next = start.saturating_add(count);
+        (start, count)
+    }
+
+    // Check a nonce reported by a worker on the host: its salt must deploy to
+    // an address that matches the job, and the factory must accept the salt
+    fn verify_hit(&self, worker: u32, nonce: u64) -> Result<Solution, Box<dyn Error>> {
+        let config = &self.config;
+        let salt = config.salt_scheme.salt(nonce);
+        let address = config.address_scheme.address(&config.factory_address, &salt, &config.init_code_hash);
+        if !matches_criteria(config, &address) {
+            return Err(format!("nonce {} deploys to 0x{}, which does not match", nonce, hex::encode(address)).into());
+        }
+
+        let salt_nonce = config.salt_scheme.salt_nonce(nonce);
+        if let Some(factory) = self.factory {
+            if factory.create2_salt(&salt_nonce.unwrap_or(salt), Some(&config.calling_address))? != salt {
+                return Err(format!("{} would not deploy with the salt 0x{} of nonce {}", factory, hex::encode(salt), nonce).into());
+            }
+        }
+
+        Ok(Solution {
+            device: worker,
+            salt,
+            address,
+            init_code_hash: config.init_code_hash,
+            deployer: config.factory_address,
+            caller: config.calling_address,
+            attempts: self.searched,
+            elapsed_seconds: self.start_time.elapsed().as_secs_f64(),
+            score: None,
+            salt_nonce,
+        })
+    }
+
+    // Check that the shares of a range are distinct nonces of the range, in
+    // order, that match the share criteria
+    fn verify_shares(&self, start: u64, count: u64, shares: &[u64]) -> Result<(), Box<dyn Error>> {
+        let share_config = match self.share_config {
+            Some(ref share_config) => share_config,
+            None if shares.is_empty() => return Ok(()),
+            None => return Err("shares were reported although this job does not ask for them".into()),
+        };
+
+        let end = start.saturating_add(count);
+        let mut previous = None;
+        for &nonce in shares {
+            if nonce < start || nonce >= end || previous.is_some_and(|previous| nonce <= previous) {
+                return Err(format!("share {} is out of order or outside the range {}..{}", nonce, start, end).into());
+            }
+            previous = Some(nonce);
+
+            let salt = share_config.salt_scheme.salt(nonce);
+            let address = share_config.address_scheme.address(&share_config.factory_address, &salt, &share_config.init_code_hash);
+            if !matches_criteria(share_config, &address) {
+                return Err(format!("share {} deploys to 0x{}, which does not match", nonce, hex::encode(address)).into());
+            }
+        }
+        Ok(())
+    }
+
+    // Warn once about a worker that has found far fewer shares than the
+    // nonces it claims to have tested should have given
+    fn check_shares(&mut self, id: u32) {
+        if self.share_config.is_none() {
+            return;
+        }
+        let odds = 16f64.powi(self.share_digits as i32);
+        let format = self.config.format;
+        let worker = self.workers.get_mut(&id).unwrap();
+        let expected = worker.searched as f64 / odds;
+        if worker.flagged || expected < MIN_EXPECTED_SHARES {
+            return;
+        }
+        if (worker.shares as f64) < expected - SHARE_DEVIATIONS * expected.sqrt() {
+            worker.flagged = true;
+            format.info(&format!(
+                "Warning: worker {} ({}) found {} shares where {:.0} were expected; it may be testing fewer nonces than it reports",
+                id, worker.name, worker.shares, expected
+            ));
+        }
+    }
+
+    // Print a status line if the last one is old enough
+    fn report_status(&mut self) {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        if elapsed - self.previous_status < STATUS_INTERVAL {
+            return;
+        }
+        self.previous_status = elapsed;
+
+        let rate: f64 = self.workers.values().map(|worker| worker.rate).sum();
+        let difficulty = Difficulty::of(&self.config);
+        self.config.format.info(&format!(
+            "{} workers, {} nonces searched, {:.2} million per second combined, expected time to solution: {}",
+            self.workers.len(),
+            self.searched,
+            rate / 1_000_000.0,
+            format_duration(difficulty.expected_seconds(rate))
+        ));
+
+        if self.share_config.is_some() {
+            let odds = 16f64.powi(self.share_digits as i32);
+            for (id, worker) in &self.workers {
+                let estimated = if worker.busy_seconds > 0.0 { worker.shares as f64 * odds / worker.busy_seconds } else { 0.0 };
+                self.config.format.info(&format!(
+                    "  worker {} ({}): {:.2} million per second reported, {:.2} million estimated from {} shares{}",
+                    id,
+                    worker.name,
+                    worker.rate / 1_000_000.0,
+                    estimated / 1_000_000.0,
+                    worker.shares,
+                    if worker.flagged { " (flagged)" } else { "" }
+                ));
+            }
+        }
+    }
+}
+
+type SharedPool = Arc<(Mutex<Pool>, Condvar)>;
+
+/// Own a pattern search and hand out disjoint nonce ranges to the workers
+/// that connect to `addr`, until one of them finds a matching salt.
+///
+/// Workers may join and leave at any time; the range of a worker that
+/// leaves is handed out again. Every reported hit is checked on the host
+/// before it is accepted, and a worker that reports a bad one is
+/// disconnected. The solution is reported before it is returned.
+///
+/// With `share_digits` above zero, workers also report the nonces whose
+/// address matches the first that many digits of the pattern. The
+/// coordinator checks each one, estimates every worker's real rate from
+/// them and warns about workers that find far fewer than expected.
+pub fn coordinate(addr: &str, mut job: JobSpec, share_digits: usize, format: OutputFormat) -> Result<Solution, Box<dyn Error>> {
+    // draw random high bytes once, here, so that every worker uses them
+    if let Some(ref high_bytes) = job.high_bytes {
+        job.high_bytes = Some(hex::encode(high_bytes.parse::<HighBytes>()?.for_device(0)));
+    }
+    let mut config = job.config(0, None)?;
+    config.format = format;
+    if config.score.is_some() {
+        return Err("the coordinator only runs pattern searches, not scoring".into());
+    }
+    let factory = match job.factory {
+        Some(ref factory) => Some(factory.parse::<Factory>()?),
+        None => None,
+    };
+    let share_config = match share_digits {
+        0 => None,
+        digits => Some(share_config(&config, digits)?),
+    };
+    format.info(&Difficulty::of(&config).describe());
+
+    let listener = TcpListener::bind(addr).map_err(|e| format!("could not listen on {}: {}", addr, e))?;
+    format.info(&format!(
+        "Coordinating on {}; start workers with: create2gpu worker --connect <host>:{}",
+        listener.local_addr()?,
+        listener.local_addr()?.port()
+    ));
+
+    let pool: SharedPool = Arc::new((Mutex::new(Pool::new(config, factory, share_config, share_digits)), Condvar::new()));
+
+    let accepting = pool.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let pool = accepting.clone();
+                    let job = job.clone();
+                    thread::spawn(move || serve_worker(&pool, &job, share_digits, stream));
+                }
+                Err(e) => format.error(&format!("could not accept a worker: {}", e)),
+            }
+        }
+    });
+
+    let (ref lock, ref found) = *pool;
+    let mut state = lock.lock().unwrap();
+    loop {
+        if let Some(ref solution) = state.solution {
+            info(&state.config, &format!("Worker {} found a solution", solution.device));
+            emit(&state.config, Event::Solution(solution));
+            return Ok(solution.clone());
+        }
+        state = found.wait(state).unwrap();
+    }
+}
+
+// Run one worker's connection, and hand its unfinished range back when it ends
+fn serve_worker(pool: &SharedPool, job: &JobSpec, share_digits: usize, stream: TcpStream) {
+    let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let (ref lock, _) = **pool;
+    let id = {
+        let mut state = lock.lock().unwrap();
+        state.next_worker += 1;
+        state.next_worker
+    };
+
+    let result = run_worker_connection(pool, job, share_digits, id, &peer, stream);
+
+    let mut state = lock.lock().unwrap();
+    let format = state.config.format;
+    if let Some(worker) = state.workers.remove(&id) {
+        if let Some(range) = worker.range {
+            state.returned.push(range);
+        }
+        format.info(&format!("Worker {} ({}) left", id, worker.name));
+    }
+    if let Err(e) = result {
+        format.error(&format!("worker {} at {}: {}", id, peer, e));
+    }
+}
+
+fn run_worker_connection(
+    pool: &SharedPool,
+    job: &JobSpec,
+    share_digits: usize,
+    id: u32,
+    peer: &str,
+    mut stream: TcpStream,
+) -> Result<(), Box<dyn Error>> {
+    let (ref lock, ref found) = **pool;
+    stream.set_read_timeout(Some(STALL_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let name = match receive(&mut reader)? {
+        Some(Message::Hello { version, name }) if version == PROTOCOL_VERSION => name,
+        Some(Message::Hello { version, .. }) => {
+            let reason = format!("protocol version {} is not supported, expected {}", version, PROTOCOL_VERSION);
+            send(&mut stream, &Message::Stop { reason: reason.clone() })?;
+            return Err(reason.into());
+        }
+        Some(_) => return Err("expected a hello message".into()),
+        None => return Ok(()),
+    };
+
+    send(&mut stream, &Message::Job { job: job.clone(), share_digits })?;
+    let batch_size = match receive(&mut reader)? {
+        Some(Message::Ready { batch_size }) if batch_size > 0 => batch_size,
+        Some(_) => return Err("expected a ready message".into()),
+        None => return Ok(()),
+    };
+
+    {
+        let mut state = lock.lock().unwrap();
+        state.workers.insert(id, WorkerState::new(name.clone()));
+        state.config.format.info(&format!("Worker {} ({}) joined from {} with batches of {}", id, name, peer, batch_size));
+    }
+
+    loop {
+        let (start, count) = {
+            let mut state = lock.lock().unwrap();
+            if state.solution.is_some() {
+                drop(state);
+                send(&mut stream, &Message::Stop { reason: "a solution was found".to_string() })?;
+                return Ok(());
+            }
+            let rate = state.workers[&id].rate;
+            let range = state.take_range(batch_size, rate);
+            state.workers.get_mut(&id).unwrap().range = Some(range);
+            range
+        };
+        send(&mut stream, &Message::Range { start, count })?;
+        let sent = Instant::now();
+
+        let (rate, hit, shares) = match receive(&mut reader)? {
+            Some(Message::Done { start: done_start, count: done_count, rate, hit, shares }) if (done_start, done_count) == (start, count) => {
+                (rate, hit, shares)
+            }
+            Some(_) => return Err(format!("expected the result of range {}..{}", start, start.saturating_add(count)).into()),
+            None => return Ok(()),
+        };
+
+        let mut state = lock.lock().unwrap();
+        state.verify_shares(start, count, &shares).map_err(|e| format!("rejected shares: {}", e))?;
+        if let Some(nonce) = hit {
+            // a bad hit is turned away with the range, which goes back to
+            // the pool when the connection ends
+            let solution = state.verify_hit(id, nonce).map_err(|e| format!("rejected hit: {}", e))?;
+            state.solution.get_or_insert(solution);
+            found.notify_all();
+        }
+        let worker = state.workers.get_mut(&id).unwrap();
+        worker.rate = rate;
+        worker.range = None;
+        // a range cut short by a hit only counts up to the hit
+        let searched = hit.map_or(count, |nonce| nonce - start + 1);
+        worker.searched = worker.searched.saturating_add(searched);
+        worker.busy_seconds += sent.elapsed().as_secs_f64() * searched as f64 / count as f64;
+        worker.shares += shares.len() as u64;
+        state.searched = state.searched.saturating_add(count);
+        state.check_shares(id);
+        state.report_status();
+    }
+}
+
+/// Connect to the coordinator at `addr` and test the ranges it hands out on
+/// the CPU or GPU `device`, until it stops the job or closes the
+/// connection.
+pub fn work(addr: &str, name: &str, cpu: bool, device: u32, tuning_file: Option<PathBuf>, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let mut stream = TcpStream::connect(addr).map_err(|e| format!("could not connect to {}: {}", addr, e))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    send(&mut stream, &Message::Hello { version: PROTOCOL_VERSION, name: name.to_string() })?;
+
+    let (job, share_digits) = match receive(&mut reader)? {
+        Some(Message::Job { job, share_digits }) => (job, share_digits),
+        Some(Message::Stop { reason }) => return Err(format!("the coordinator turned this worker away: {}", reason).into()),
+        Some(_) => return Err("expected a job message".into()),
+        None => return Err("the coordinator closed the connection".into()),
+    };
+
+    // the coordinator resolved the high bytes for device 0; every device
+    // must use them as they are
+    let mut config = job.config(0, tuning_file)?;
+    config.gpu_device = device;
+    config.format = format;
+    format.info(&format!("Joined the search of coordinator {}", addr));
+
+    let shares = match share_digits {
+        0 => None,
+        digits => Some(share_config(&config, digits)?),
+    };
+    match (cpu, shares) {
+        (true, Some(shares)) => run_ranges(&mut cpu_share_miner(&config, &shares)?, &mut stream, &mut reader, format),
+        (false, Some(shares)) => run_ranges(&mut gpu_share_miner(&config, &shares)?, &mut stream, &mut reader, format),
+        (true, None) => run_ranges(&mut NoShares(cpu_miner(&config)?), &mut stream, &mut reader, format),
+        (false, None) => run_ranges(&mut NoShares(gpu_miner(&config)?), &mut stream, &mut reader, format),
+    }
+}
+
+/// A miner that reports no shares, for jobs that do not ask for them.
+struct NoShares<M: Miner>(M);
+
+impl<M: Miner> ShareMiner for NoShares<M> {
+    fn batch_size(&self) -> u64 {
+        self.0.batch_size()
+    }
+
+    fn mine_shares(&mut self, base: u64) -> Result<ShareBatch, Box<dyn Error>> {
+        Ok(ShareBatch { hit: self.0.mine(base)?.map(|hit| hit.nonce), shares: Vec::new() })
+    }
+}
+
+fn run_ranges<M: ShareMiner>(
+    miner: &mut M,
+    stream: &mut TcpStream,
+    reader: &mut BufReader<TcpStream>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    send(stream, &Message::Ready { batch_size: miner.batch_size() })?;
+
+    loop {
+        match receive(reader)? {
+            Some(Message::Range { start, count }) => {
+                let started = Instant::now();
+                let end = start.saturating_add(count);
+                let mut base = start;
+                let mut hit = None;
+                let mut shares = Vec::new();
+                while base < end && hit.is_none() {
+                    let batch = miner.mine_shares(base)?;
+                    hit = batch.hit;
+                    // shares past the hit are not searched as far as the
+                    // coordinator is concerned
+                    shares.extend(batch.shares.into_iter().filter(|&nonce| nonce < end && hit.is_none_or(|hit| nonce <= hit)));
+                    base = base.saturating_add(miner.batch_size());
+                }
+
+                let rate = (base - start) as f64 / started.elapsed().as_secs_f64();
+                format.info(&format!("Searched nonces {}..{} at {:.2} million per second", start, base, rate / 1_000_000.0));
+                send(stream, &Message::Done { start, count, rate, hit, shares })?;
+            }
+            Some(Message::Stop { reason }) => {
+                format.info(&format!("The coordinator stopped the job: {}", reason));
+                return Ok(());
+            }
+            Some(_) => return Err("expected a range or stop message".into()),
+            None => {
+                format.info("The coordinator closed the connection");
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // A pool for a search of addresses starting with `starts_with`, with
+    // shares of `share_digits` digits
+    fn pool(starts_with: &str, share_digits: usize) -> Pool {
+        let job = job(starts_with);
+        let config = job.config(0, None).unwrap();
+        let share_config = match share_digits {
+            0 => None,
+            digits => Some(share_config(&config, digits).unwrap()),
+        };
+        Pool::new(config, None, share_config, share_digits)
+    }
+
+    fn job(starts_with: &str) -> JobSpec {
+        serde_json::from_value(json!({
+            "deployer": "0x0000000000000000000000000000000000000002",
+            "init_code_hash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+            "starts_with": starts_with,
+        }))
+        .unwrap()
+    }
+
+    // The nonces below `end` whose address matches `config`
+    fn matching(config: &Config, end: u64) -> Vec<u64> {
+        (0..end)
+            .filter(|&nonce| {
+                let address = config.address_scheme.address(&config.factory_address, &config.salt_scheme.salt(nonce), &config.init_code_hash);
+                matches_criteria(config, &address)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fresh_ranges_are_disjoint_whole_batches() {
+        let mut pool = pool("a", 0);
+        assert_eq!(pool.take_range(100, 0.0), (0, 100));
+        // ten seconds at 105 per second rounds down to ten whole batches
+        assert_eq!(pool.take_range(100, 105.0), (100, 1_000));
+        assert_eq!(pool.take_range(100, 0.0), (1_100, 100));
+    }
+
+    #[test]
+    fn returned_ranges_are_handed_out_again() {
+        let mut pool = pool("a", 0);
+        let first = pool.take_range(100, 0.0);
+        pool.returned.push(first);
+        assert_eq!(pool.take_range(100, 1_000_000.0), first);
+        assert_eq!(pool.take_range(100, 0.0), (100, 100));
+    }
+
+    #[test]
+    fn hits_are_checked_on_the_host() {
+        let pool = pool("a", 0);
+        let hits = matching(&pool.config, 64);
+        let hit = hits[0];
+        let miss = (0..64).find(|nonce| !hits.contains(nonce)).unwrap();
+
+        let solution = pool.verify_hit(3, hit).unwrap();
+        assert_eq!(solution.device, 3);
+        assert!(hex::encode(solution.address).starts_with('a'));
+        let error = pool.verify_hit(3, miss).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("nonce {} deploys to", miss)), "{}", error);
+    }
+
+    #[test]
+    fn shares_must_match_in_order_within_the_range() {
+        let pool = pool("abcd", 1);
+        let shares = matching(pool.share_config.as_ref().unwrap(), 200);
+        assert!(shares.len() >= 3);
+        let miss = (0..200).find(|nonce| !shares.contains(nonce)).unwrap();
+
+        pool.verify_shares(0, 200, &shares).unwrap();
+        pool.verify_shares(0, 200, &[]).unwrap();
+
+        let out_of_order = [shares[1], shares[0]];
+        let duplicate = [shares[0], shares[0]];
+        for bad in [&out_of_order[..], &duplicate[..], &shares[1..2]] {
+            let start = if bad.len() == 1 { shares[1] + 1 } else { 0 };
+            let error = pool.verify_shares(start, 200, bad).unwrap_err().to_string();
+            assert!(error.contains("out of order or outside the range"), "{}", error);
+        }
+        let error = pool.verify_shares(0, 200, &[miss]).unwrap_err().to_string();
+        assert!(error.contains("which does not match"), "{}", error);
+
+        let error = self::pool("abcd", 0).verify_shares(0, 200, &shares).unwrap_err().to_string();
+        assert_eq!(error, "shares were reported although this job does not ask for them");
+    }
+
+    #[test]
+    fn a_bad_hit_drops_the_worker_and_returns_its_range() {
+        let job = job("a");
+        let pool: SharedPool = Arc::new((Mutex::new(pool("a", 0)), Condvar::new()));
+        let miss = {
+            let config = &pool.0.lock().unwrap().config;
+            let hits = matching(config, 64);
+            (0..64).find(|nonce| !hits.contains(nonce)).unwrap()
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let coordinator = {
+            let (accepted, _) = listener.accept().unwrap();
+            let pool = pool.clone();
+            thread::spawn(move || serve_worker(&pool, &job, 0, accepted))
+        };
+
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        send(&mut stream, &Message::Hello { version: PROTOCOL_VERSION, name: "test".to_string() }).unwrap();
+        assert!(matches!(receive(&mut reader).unwrap(), Some(Message::Job { .. })));
+        send(&mut stream, &Message::Ready { batch_size: 64 }).unwrap();
+        let (start, count) = match receive(&mut reader).unwrap() {
+            Some(Message::Range { start, count }) => (start, count),
+            message => panic!("expected a range, got {:?}", message),
+        };
+        send(&mut stream, &Message::Done { start, count, rate: 1.0, hit: Some(miss), shares: Vec::new() }).unwrap();
+
+        // the coordinator closes the connection on the bad hit
+        assert!(receive(&mut reader).unwrap().is_none());
+        coordinator.join().unwrap();
+
+        let state = pool.0.lock().unwrap();
+        assert!(state.solution.is_none());
+        assert!(state.workers.is_empty());
+        assert_eq!(state.returned, vec![(start, count)]);
+    }
+}
@@ -0,0 +1,468 @@
+//! A line-by-line port of `kernels/keccak256.cl` to Rust, checked against
+//! the host-side hashing, matching and scoring over random inputs.
+//!
+//! The kernel stops `keccakf` part-way through the 24th round, so only the
+//! address bytes 12..32 of the digest are valid. The port needs no OpenCL
+//! device, so a broken edit to the hand-unrolled rounds is caught on any
+//! machine as long as the port is kept in step with the kernel;
+//! `tests/kernel.rs` compares the kernel itself with the host on the first
+//! OpenCL device.
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// `rhoPi_(m, n)` steps after the first, in kernel order.
+const RHO_PI: [(usize, u32); 23] = [
+    (7, 3), (11, 6), (17, 10), (18, 15), (3, 21), (5, 28), (16, 36), (8, 45),
+    (21, 55), (24, 2), (4, 14), (15, 27), (23, 41), (19, 56), (13, 8), (12, 25),
+    (2, 43), (20, 62), (14, 18), (22, 39), (9, 61), (6, 20), (1, 44),
+];
+
+fn theta(a: &mut [u64; 25], b: &mut [u64; 5]) {
+    for i in 0..5 {
+        b[i] = a[i] ^ a[i + 5] ^ a[i + 10] ^ a[i + 15] ^ a[i + 20];
+    }
+    for &(m, n, o) in [(4, 1, 0), (0, 2, 1), (1, 3, 2), (2, 4, 3), (3, 0, 4)].iter() {
+        let t = b[m] ^ b[n].rotate_left(1);
+        for row in 0..5 {
+            a[o + row * 5] ^= t;
+        }
+    }
+}
+
+fn rho_pi(a: &mut [u64; 25], b: &mut [u64; 5]) {
+    let t = a[1];
+    b[0] = a[10];
+    a[10] = t.rotate_left(1);
+    for &(m, n) in RHO_PI.iter() {
+        let t = b[0];
+        b[0] = a[m];
+        a[m] = t.rotate_left(n);
+    }
+}
+
+fn chi(a: &mut [u64; 25], b: &mut [u64; 5]) {
+    for n in (0..25).step_by(5) {
+        b.copy_from_slice(&a[n..n + 5]);
+        for i in 0..5 {
+            a[n + i] = b[i] ^ (!b[(i + 1) % 5] & b[(i + 2) % 5]);
+        }
+    }
+}
+
+fn keccakf_full(a: &mut [u64; 25]) {
+    let mut b = [0u64; 5];
+
+    for &rc in ROUND_CONSTANTS.iter() {
+        theta(a, &mut b);
+        rho_pi(a, &mut b);
+        chi(a, &mut b);
+        a[0] ^= rc;
+    }
+}
+
+fn keccakf(a: &mut [u64; 25]) {
+    let mut b = [0u64; 5];
+
+    for &rc in ROUND_CONSTANTS[..23].iter() {
+        theta(a, &mut b);
+        rho_pi(a, &mut b);
+        chi(a, &mut b);
+        a[0] ^= rc;
+    }
+
+    // iteration 24 (partial), where `o` views the state as 32-bit words
+    for i in 0..5 {
+        b[i] = a[i] ^ a[i + 5] ^ a[i + 10] ^ a[i + 15] ^ a[i + 20];
+    }
+    a[0] ^= b[4] ^ b[1].rotate_left(1);
+    a[6] ^= b[0] ^ b[2].rotate_left(1);
+    a[12] ^= b[1] ^ b[3].rotate_left(1);
+    a[18] ^= b[2] ^ b[4].rotate_left(1);
+    a[24] ^= b[3] ^ b[0].rotate_left(1);
+
+    let mut o = [0u32; 50];
+    for (i, lane) in a.iter().enumerate() {
+        o[2 * i] = *lane as u32;
+        o[2 * i + 1] = (*lane >> 32) as u32;
+    }
+
+    o[3] = (o[13] >> 20) | (o[12] << 12);
+    let rotated = [a[12].rotate_left(43), a[18].rotate_left(21), a[24].rotate_left(14)];
+    for (i, lane) in rotated.iter().enumerate() {
+        o[4 + 2 * i] = *lane as u32;
+        o[5 + 2 * i] = (*lane >> 32) as u32;
+    }
+
+    o[3] ^= !o[5] & o[7];
+    o[4] ^= !o[6] & o[8];
+    o[5] ^= !o[7] & o[9];
+    o[6] ^= !o[8] & o[0];
+    o[7] ^= !o[9] & o[1];
+
+    for (i, lane) in a.iter_mut().enumerate() {
+        *lane = o[2 * i] as u64 | ((o[2 * i + 1] as u64) << 32);
+    }
+}
+
+fn to_state(sponge: &[u8]) -> [u64; 25] {
+    let mut state = [0u64; 25];
+    for (lane, bytes) in state.iter_mut().zip(sponge.chunks(8)) {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(bytes);
+        *lane = u64::from_le_bytes(word);
+    }
+    state
+}
+
+fn to_bytes(state: &[u64; 25]) -> [u8; 200] {
+    let mut bytes = [0u8; 200];
+    for (chunk, lane) in bytes.chunks_mut(8).zip(state.iter()) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    bytes
+}
+
+/// The 20 address bytes `hashMessage` produces for a nonce, where
+/// `salt_scheme` and `address_scheme` are the scheme parts of the kernel
+/// message.
+fn hash_message(
+    deployer: &[u8; 20],
+    init_code_hash: &[u8; 32],
+    salt_scheme: &[u8],
+    address_scheme: &[u8],
+    nonce: u64,
+) -> [u8; 20] {
+    let mut sponge = [0u8; 200];
+    sponge[0] = 0xff;
+    sponge[1..21].copy_from_slice(deployer);
+
+    if salt_scheme[0] == 1 {
+        let tail_len = salt_scheme[1] as usize;
+        let mut state = to_state(&salt_scheme[2..202]);
+        let end = if tail_len + 32 < 136 { 136 } else { 272 };
+        let mut block = [0u8; 272];
+        block[..tail_len].copy_from_slice(&salt_scheme[226..226 + tail_len]);
+        block[tail_len..tail_len + 24].copy_from_slice(&salt_scheme[202..226]);
+        block[tail_len + 24..tail_len + 32].copy_from_slice(&nonce.to_be_bytes());
+        block[tail_len + 32] ^= 0x01;
+        block[end - 1] ^= 0x80;
+
+        for chunk in block[..end].chunks(136) {
+            let mut bytes = to_bytes(&state);
+            for (b, &x) in bytes.iter_mut().zip(chunk) {
+                *b ^= x;
+            }
+            state = to_state(&bytes);
+            keccakf_full(&mut state);
+        }
+        sponge[21..53].copy_from_slice(&to_bytes(&state)[..32]);
+    } else {
+        let head_len = salt_scheme[1] as usize;
+        sponge[21..21 + head_len].copy_from_slice(&salt_scheme[2..2 + head_len]);
+        sponge[45..53].copy_from_slice(&nonce.to_le_bytes());
+    }
+
+    if address_scheme[0] == 1 {
+        let mut block = [0u8; 200];
+        block[..32].copy_from_slice(&address_scheme[1..33]);
+        block[44..64].copy_from_slice(deployer);
+        block[64..96].copy_from_slice(&sponge[21..53]);
+        block[96..128].copy_from_slice(init_code_hash);
+        block[128..136].copy_from_slice(&address_scheme[33..41]);
+
+        let mut state = to_state(&block);
+        keccakf_full(&mut state);
+        sponge = to_bytes(&state);
+        for (b, &x) in sponge.iter_mut().zip(&address_scheme[41..65]) {
+            *b ^= x;
+        }
+        sponge[24] ^= 0x01;
+        sponge[135] ^= 0x80;
+    } else {
+        sponge[53..85].copy_from_slice(init_code_hash);
+        sponge[85] = 0x01;
+        sponge[135] = 0x80;
+    }
+
+    let mut state = to_state(&sponge);
+    keccakf(&mut state);
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&to_bytes(&state)[12..32]);
+    address
+}
+
+/// `absorbByte`: xor a byte into the sponge, permuting when a block
+/// fills.
+fn absorb_byte(state: &mut [u64; 25], position: &mut usize, byte: u8) {
+    let mut bytes = to_bytes(state);
+    bytes[*position] ^= byte;
+    *state = to_state(&bytes);
+    *position += 1;
+    if *position == 136 {
+        keccakf_full(state);
+        *position = 0;
+    }
+}
+
+/// The selector `hashSelector` computes for a nonce, where `message` is
+/// the kernel message.
+fn hash_selector(message: &[u8], nonce: u64) -> [u8; 4] {
+    let name_len = message[0] as usize;
+    let name = &message[1..1 + name_len];
+    let params_len = message[1 + name_len] as usize;
+    let params = &message[2 + name_len..2 + name_len + params_len];
+
+    let mut state = [0u64; 25];
+    let mut position = 0;
+    for &byte in name.iter().chain(b"_") {
+        absorb_byte(&mut state, &mut position, byte);
+    }
+
+    absorb_suffix(&mut state, &mut position, nonce);
+    for &byte in params {
+        absorb_byte(&mut state, &mut position, byte);
+    }
+
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&finish_sponge(state, position)[..4]);
+    selector
+}
+
+/// The hash `hashIdentifiers` computes for a nonce.
+fn hash_identifier(words: &[u8], word_offsets: &[u32], message: &[u8], nonce: u64) -> [u8; 32] {
+    let word_count = (word_offsets.len() - 1) as u64;
+    let word = (nonce % word_count) as usize;
+    let suffix = nonce / word_count;
+    let params = &message[1..1 + message[0] as usize];
+
+    let mut state = [0u64; 25];
+    let mut position = 0;
+    for &byte in &words[word_offsets[word] as usize..word_offsets[word + 1] as usize] {
+        absorb_byte(&mut state, &mut position, byte);
+    }
+    if suffix > 0 {
+        absorb_byte(&mut state, &mut position, b'_');
+        absorb_suffix(&mut state, &mut position, suffix - 1);
+    }
+    for &byte in params {
+        absorb_byte(&mut state, &mut position, byte);
+    }
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&finish_sponge(state, position)[..32]);
+    hash
+}
+
+/// `absorbSuffix`: the nonce's bijective base-62 digits.
+fn absorb_suffix(state: &mut [u64; 25], position: &mut usize, nonce: u64) {
+    const ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut digits = nonce;
+    loop {
+        absorb_byte(state, position, ALPHABET[(digits % 62) as usize]);
+        digits /= 62;
+        if digits == 0 {
+            break;
+        }
+        digits -= 1;
+    }
+}
+
+/// `finishSponge`: pad, permute and return the state.
+fn finish_sponge(mut state: [u64; 25], position: usize) -> [u8; 200] {
+    let mut bytes = to_bytes(&state);
+    bytes[position] ^= 0x01;
+    bytes[135] ^= 0x80;
+    state = to_state(&bytes);
+    keccakf_full(&mut state);
+    to_bytes(&state)
+}
+
+fn matches_address_criteria(d: &[u8; 20], prefix: &[u8], suffix: &[u8], tail: &[u8; 4]) -> bool {
+    if (d[18] & tail[0]) != tail[2] || (d[19] & tail[1]) != tail[3] {
+        return false;
+    }
+
+    let hex_char = |pos: usize| {
+        let byte = d[pos / 2];
+        let nibble = if pos.is_multiple_of(2) { (byte >> 4) & 0xf } else { byte & 0xf };
+        if nibble < 10 { b'0' + nibble } else { b'a' + (nibble - 10) }
+    };
+
+    prefix.iter().enumerate().all(|(i, &c)| hex_char(i) == c)
+        && suffix.iter().enumerate().all(|(i, &c)| hex_char(40 - suffix.len() + i) == c)
+}
+
+fn uniswap_v4_score(d: &[u8; 20]) -> u32 {
+    let nibble = |i: u32| {
+        let byte = d[(i / 2) as usize];
+        if i.is_multiple_of(2) { byte >> 4 } else { byte & 0xf }
+    };
+
+    let mut i = 0;
+    while i < 40 && nibble(i) == 0 {
+        i += 1;
+    }
+    let leading_zeroes = i;
+    while i < 40 && nibble(i) == 4 {
+        i += 1;
+    }
+    let leading_fours = i - leading_zeroes;
+
+    if leading_fours == 0 {
+        return 0;
+    }
+
+    let mut score = leading_zeroes * 10;
+    if leading_fours == 4 {
+        score += 60;
+    } else if leading_fours > 4 {
+        score += 40;
+    }
+
+    for j in 0..40 {
+        if nibble(j) == 4 {
+            score += 1;
+        }
+    }
+
+    if d[18] == 0x44 && d[19] == 0x44 {
+        score += 20;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+
+    use crate::abi::selector;
+    use crate::keccak256;
+    use crate::score::ScoreFunction;
+    use crate::search::tail;
+    use crate::testing::{
+        criteria_for, pattern_config, random_address_scheme, random_identifier_config, random_salt_scheme,
+        random_selector_config, rng, string_match,
+    };
+
+    const CASES: usize = 256;
+
+    #[test]
+    fn hash_message_matches_host_address() {
+        let mut rng = rng();
+        for _ in 0..CASES {
+            let mut deployer = [0u8; 20];
+            let mut init_code_hash = [0u8; 32];
+            rng.fill_bytes(&mut deployer);
+            rng.fill_bytes(&mut init_code_hash);
+            let nonce: u64 = rng.gen();
+            let salt_scheme = random_salt_scheme(&mut rng);
+            let address_scheme = random_address_scheme(&mut rng);
+
+            let expected = address_scheme.address(&deployer, &salt_scheme.salt(nonce), &init_code_hash);
+            assert_eq!(
+                hash_message(&deployer, &init_code_hash, &salt_scheme.kernel_bytes(), &address_scheme.kernel_bytes(), nonce),
+                expected,
+                "deployer 0x{} init code hash 0x{} nonce {} {:?} {:?}",
+                hex::encode(deployer),
+                hex::encode(init_code_hash),
+                nonce,
+                salt_scheme,
+                address_scheme
+            );
+        }
+    }
+
+    #[test]
+    fn matches_address_criteria_agrees_with_string_matching() {
+        let mut rng = rng();
+        for _ in 0..CASES * 4 {
+            let mut address = [0u8; 20];
+            rng.fill_bytes(&mut address);
+            let criteria = criteria_for(&mut rng, &address);
+
+            let config = pattern_config(&criteria.starts_with, &criteria.ends_with, criteria.hook_flags);
+            let (tail_mask, tail_value) = tail(&config);
+            let tail = [tail_mask[0], tail_mask[1], tail_value[0], tail_value[1]];
+            assert_eq!(
+                matches_address_criteria(&address, criteria.starts_with.as_bytes(), criteria.ends_with.as_bytes(), &tail),
+                string_match(&address, &criteria),
+                "0x{} {:?}",
+                hex::encode(address),
+                criteria
+            );
+        }
+    }
+
+    #[test]
+    fn hash_selector_matches_signature() {
+        let mut rng = rng();
+        for _ in 0..CASES {
+            let config = random_selector_config(&mut rng);
+            // small nonces exercise short suffixes, large ones the full 11
+            // digits
+            let nonce = if rng.gen() { rng.gen_range(0, 300_000) } else { rng.gen() };
+
+            let expected = selector(&config.signature(nonce));
+            assert_eq!(hash_selector(&config.kernel_bytes(), nonce), expected, "{}", config.signature(nonce));
+        }
+    }
+
+    #[test]
+    fn hash_identifier_matches_host_hash() {
+        let mut rng = rng();
+        for _ in 0..CASES / 4 {
+            let config = random_identifier_config(&mut rng, 1000);
+            let (words, word_offsets) = config.kernel_words();
+
+            for _ in 0..8 {
+                let nonce = rng.gen_range(0, config.search_space());
+                let expected = keccak256(config.identifier(nonce).as_bytes());
+                assert_eq!(
+                    hash_identifier(&words, &word_offsets, &config.kernel_bytes(), nonce),
+                    expected,
+                    "{}",
+                    config.identifier(nonce)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn uniswap_v4_score_agrees_with_host_score() {
+        let mut rng = rng();
+        for _ in 0..CASES * 4 {
+            // a run of leading zeroes, then a run of 4s, with 4s sprinkled
+            // through the rest and sometimes at the end, so that most
+            // addresses score
+            let zeroes = rng.gen_range(0, 8);
+            let fours = rng.gen_range(0, 7);
+            let digits: String = (0..40)
+                .map(|position| {
+                    let four = position < zeroes + fours || (position >= 36 && rng.gen()) || rng.gen_weighted_bool(4);
+                    if position < zeroes {
+                        '0'
+                    } else if four {
+                        '4'
+                    } else {
+                        std::char::from_digit(rng.gen_range(0, 16), 16).unwrap()
+                    }
+                })
+                .collect();
+            let mut address = [0u8; 20];
+            address.copy_from_slice(&hex::decode(&digits).unwrap());
+
+            assert_eq!(uniswap_v4_score(&address), ScoreFunction::UniswapV4.score(&address), "0x{}", digits);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::abi::{address_word, encode_call, Token};
+use crate::search::SaltScheme;
+use crate::{create2_address, keccak256};
+
+/// A Safe proxy deployed by `SafeProxyFactory.createProxyWithNonce`.
+///
+/// The factory deploys `proxyCreationCode ++ uint256(singleton)` with
+/// CREATE2 and the salt `keccak256(keccak256(initializer) ++ saltNonce)`,
+/// so mining the `saltNonce` means hashing the salt for every attempt.
+#[derive(Clone, Debug)]
+pub struct SafeProxy {
+    /// The Safe singleton (mastercopy) the proxy delegates to.
+    pub singleton: [u8; 20],
+    /// Calldata the factory calls on the new proxy, usually `setup(...)`.
+    pub initializer: Vec<u8>,
+    /// `SafeProxyFactory.proxyCreationCode()` of the factory being used.
+    pub proxy_creation_code: Vec<u8>,
+}
+
+impl SafeProxy {
+    /// The init code the factory deploys.
+    pub fn init_code(&self) -> Vec<u8> {
+        let mut code = self.proxy_creation_code.clone();
+        code.extend_from_slice(&address_word(&self.singleton));
+        code
+    }
+
+    /// The keccak-256 hash of the init code.
+    pub fn init_code_hash(&self) -> [u8; 32] {
+        keccak256(&self.init_code())
+    }
+
+    /// How the factory derives the CREATE2 salt from `saltNonce`.
+    pub fn salt_scheme(&self) -> SaltScheme {
+        SaltScheme::hashed(keccak256(&self.initializer).to_vec())
+    }
+
+    /// The CREATE2 salt for a `saltNonce`, as `createProxyWithNonce`
+    /// computes it.
+    pub fn salt(&self, salt_nonce: &[u8; 32]) -> [u8; 32] {
+        let mut preimage = keccak256(&self.initializer).to_vec();
+        preimage.extend_from_slice(salt_nonce);
+        keccak256(&preimage)
+    }
+
+    /// The `createProxyWithNonce` calldata that deploys the proxy.
+    pub fn create_proxy_calldata(&self, salt_nonce: &[u8; 32]) -> Vec<u8> {
+        encode_call(
+            "createProxyWithNonce(address,bytes,uint256)",
+            &[Token::Address(self.singleton), Token::Bytes(self.initializer.clone()), Token::Uint(*salt_nonce)],
+        )
+    }
+
+    /// The address `factory` deploys the proxy to for a `saltNonce`.
+    pub fn address(&self, factory: &[u8; 20], salt_nonce: &[u8; 32]) -> [u8; 20] {
+        create2_address(factory, &self.salt(salt_nonce), &self.init_code_hash())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy() -> SafeProxy {
+        SafeProxy {
+            singleton: [0x11; 20],
+            initializer: hex::decode("b63e800d").unwrap(),
+            proxy_creation_code: hex::decode("608060405234801561001057600080fd5b50").unwrap(),
+        }
+    }
+
+    #[test]
+    fn init_code_appends_the_singleton_as_a_word() {
+        let code = proxy().init_code();
+        assert_eq!(
+            hex::encode(code),
+            format!("608060405234801561001057600080fd5b50{}{}", "00".repeat(12), "11".repeat(20))
+        );
+    }
+
+    #[test]
+    fn salt_hashes_the_initializer_hash_and_salt_nonce() {
+        let proxy = proxy();
+        let mut salt_nonce = [0u8; 32];
+        salt_nonce[31] = 7;
+        // keccak256(keccak256(0xb63e800d) ++ uint256(7)), laid out by hand
+        let preimage = format!("{}{:064x}", hex::encode(keccak256(&hex::decode("b63e800d").unwrap())), 7);
+        let salt = keccak256(&hex::decode(preimage).unwrap());
+        assert_eq!(proxy.salt(&salt_nonce), salt);
+        assert_eq!(proxy.salt_scheme().hash_salt_nonce(&salt_nonce), Some(salt));
+
+        let factory = [0x22; 20];
+        let mut preimage = vec![0xff];
+        preimage.extend_from_slice(&factory);
+        preimage.extend_from_slice(&salt);
+        preimage.extend_from_slice(&keccak256(&proxy.init_code()));
+        assert_eq!(proxy.address(&factory, &salt_nonce)[..], keccak256(&preimage)[12..]);
+    }
+
+    #[test]
+    fn create_proxy_calldata() {
+        let mut salt_nonce = [0u8; 32];
+        salt_nonce[31] = 7;
+        assert_eq!(
+            hex::encode(proxy().create_proxy_calldata(&salt_nonce)),
+            format!(
+                "1688f0b9{:0>64}{:064x}{:064x}{:064x}{:0<64}",
+                "11".repeat(20),
+                0x60,
+                7,
+                4,
+                "b63e800d"
+            )
+        );
+    }
+}
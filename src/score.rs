@@ -0,0 +1,95 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A function that ranks addresses, for searches that keep the best address
+/// found rather than stopping at the first match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreFunction {
+    /// The scoring of the Uniswap v4 address challenge.
+    UniswapV4,
+}
+
+impl ScoreFunction {
+    /// The value of the kernel's `scoreFunction` argument that selects this
+    /// function in `scoreAddress`.
+    pub fn kernel_id(&self) -> u32 {
+        match self {
+            ScoreFunction::UniswapV4 => 0,
+        }
+    }
+
+    /// Score an address on the host, as the kernel does.
+    pub fn score(&self, address: &[u8; 20]) -> u32 {
+        match self {
+            ScoreFunction::UniswapV4 => uniswap_v4_score(address),
+        }
+    }
+}
+
+impl FromStr for ScoreFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniswap-v4" => Ok(ScoreFunction::UniswapV4),
+            _ => Err(format!("unknown score function '{}' (expected uniswap-v4)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ScoreFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScoreFunction::UniswapV4 => write!(f, "uniswap-v4"),
+        }
+    }
+}
+
+/// Score an address as `VanityAddressLib.score` in Uniswap v4 periphery
+/// does: 10 points per leading zero nibble; nothing at all unless the first
+/// non-zero nibble is a 4; 60 points for exactly four leading 4s, or 40 for
+/// more than four; 1 point for every 4 nibble; and 20 points if the address
+/// ends in 4444.
+pub fn uniswap_v4_score(address: &[u8; 20]) -> u32 {
+    let nibble = |i: usize| {
+        let byte = address[i / 2];
+        if i.is_multiple_of(2) { byte >> 4 } else { byte & 0xf }
+    };
+
+    let leading_zeroes = (0..40).take_while(|&i| nibble(i) == 0).count();
+    let leading_fours = (leading_zeroes..40).take_while(|&i| nibble(i) == 4).count();
+    if leading_fours == 0 {
+        return 0;
+    }
+
+    let mut score = leading_zeroes as u32 * 10;
+    if leading_fours == 4 {
+        score += 60;
+    } else if leading_fours > 4 {
+        score += 40;
+    }
+
+    score += (0..40).filter(|&i| nibble(i) == 4).count() as u32;
+
+    if address[18] == 0x44 && address[19] == 0x44 {
+        score += 20;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniswap_v4_score_of_known_address() {
+        let address = hex::decode("0000444455555555555555555555555555554444").unwrap();
+        let mut bytes = [0u8; 20];
+        bytes.copy_from_slice(&address);
+
+        // 4 leading zeroes, exactly four leading 4s, eight 4s, ends in 4444
+        assert_eq!(uniswap_v4_score(&bytes), 40 + 60 + 8 + 20);
+        assert_eq!(uniswap_v4_score(&[0x55; 20]), 0);
+    }
+}
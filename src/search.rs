@@ -0,0 +1,625 @@
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use byteorder::{ByteOrder, LittleEndian};
+use rand::{thread_rng, Rng};
+use tiny_keccak::keccakf;
+
+use crate::difficulty::Difficulty;
+use crate::hooks::hook_tail;
+use crate::output::{emit, Event, Progress, Solution};
+use crate::score::ScoreFunction;
+use crate::zksync::{create2_prefix, zksync_create2_address};
+use crate::abi::uint_word;
+use crate::{create2_address, keccak256, Config};
+
+/// How often a paused search checks whether it may go on.
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// A nonce that a miner reports as matching, together with the address the
+/// device computed for it.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    pub nonce: u64,
+    pub address: [u8; 20],
+}
+
+/// A backend that tests batches of consecutive nonces.
+pub trait Miner {
+    /// Number of nonces tested by one call to `mine`.
+    fn batch_size(&self) -> u64;
+
+    /// Test the nonces `base..base + batch_size()` and return a match, if any.
+    fn mine(&mut self, base: u64) -> Result<Option<Hit>, Box<dyn Error>>;
+}
+
+/// The best-scoring nonce of a batch and the score the device gave it.
+#[derive(Clone, Copy, Debug)]
+pub struct ScoredHit {
+    pub nonce: u64,
+    pub score: u32,
+}
+
+/// A backend that scores batches of consecutive nonces.
+pub trait Scorer {
+    /// Number of nonces scored by one call to `best`.
+    fn batch_size(&self) -> u64;
+
+    /// Score the nonces `base..base + batch_size()` and return the best one
+    /// scoring above `threshold`, if any. Ties go to the higher nonce.
+    fn best(&mut self, base: u64, threshold: u32) -> Result<Option<ScoredHit>, Box<dyn Error>>;
+}
+
+/// The result of testing a batch for matches and near-miss shares.
+#[derive(Clone, Debug, Default)]
+pub struct ShareBatch {
+    /// A matching nonce, if any.
+    pub hit: Option<u64>,
+    /// The nonces whose address meets the share criteria, in order.
+    pub shares: Vec<u64>,
+}
+
+/// A backend that tests batches of consecutive nonces for matches, and also
+/// collects the near-miss shares that meet the easier criteria of a
+/// `share_config`.
+pub trait ShareMiner {
+    /// Number of nonces tested by one call to `mine_shares`.
+    fn batch_size(&self) -> u64;
+
+    /// Test the nonces `base..base + batch_size()` and return a match, if
+    /// any, and the shares.
+    fn mine_shares(&mut self, base: u64) -> Result<ShareBatch, Box<dyn Error>>;
+}
+
+/// Build the 32-byte salt for a nonce: 24 zero bytes followed by the nonce
+/// in little-endian order, as laid out by the kernel.
+pub fn salt_for_nonce(nonce: u64) -> [u8; 32] {
+    let mut salt = [0u8; 32];
+    salt[24..].copy_from_slice(&nonce.to_le_bytes());
+    salt
+}
+
+/// Bytes absorbed per keccak-256 permutation.
+const KECCAK_RATE: usize = 136;
+
+/// How the CREATE2 salt is derived from a mined nonce.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SaltScheme {
+    /// The nonce itself, laid out by `salt_for_nonce`.
+    #[default]
+    Nonce,
+    /// The caller, four zero bytes and the nonce in little-endian order, for
+    /// factories that only let the address in the first 20 bytes of the
+    /// salt deploy with it.
+    Caller { caller: [u8; 20] },
+    /// `head ++ nonce` with the nonce in little-endian order: `Nonce` or
+    /// `Caller` with their free bytes filled by `with_high_bytes`.
+    Head { head: [u8; 24] },
+    /// `keccak256(prefix ++ head ++ uint64(nonce))`, for factories that hash
+    /// a caller-supplied nonce into the salt. `head ++ uint64(nonce)` is the
+    /// salt nonce that gets passed to the factory; the first `fixed` bytes
+    /// of `head` are zero or hold what the factory reads from them, such as
+    /// the caller and flags.
+    Hashed { prefix: Vec<u8>, head: [u8; 24], fixed: usize },
+}
+
+impl SaltScheme {
+    /// A hashed salt with a zero head, where the salt nonce is a uint256.
+    pub fn hashed(prefix: Vec<u8>) -> Self {
+        SaltScheme::Hashed { prefix, head: [0u8; 24], fixed: 0 }
+    }
+
+    /// The same scheme with the salt bytes ahead of the nonce that the
+    /// factory leaves free set from `high`, so that runs or devices search
+    /// disjoint salts. Bytes the factory fixes are kept.
+    pub fn with_high_bytes(&self, high: &[u8; 24]) -> Self {
+        match self {
+            SaltScheme::Nonce | SaltScheme::Head { .. } => SaltScheme::Head { head: *high },
+            SaltScheme::Caller { caller } => {
+                let mut head = *high;
+                head[..20].copy_from_slice(caller);
+                SaltScheme::Head { head }
+            }
+            SaltScheme::Hashed { prefix, head, fixed } => {
+                let mut head = *head;
+                head[*fixed..].copy_from_slice(&high[*fixed..]);
+                SaltScheme::Hashed { prefix: prefix.clone(), head, fixed: *fixed }
+            }
+        }
+    }
+
+    /// The CREATE2 salt for a nonce.
+    pub fn salt(&self, nonce: u64) -> [u8; 32] {
+        match self {
+            SaltScheme::Nonce => salt_for_nonce(nonce),
+            SaltScheme::Caller { caller } => {
+                let mut salt = salt_for_nonce(nonce);
+                salt[..20].copy_from_slice(caller);
+                salt
+            }
+            SaltScheme::Head { head } => {
+                let mut salt = salt_for_nonce(nonce);
+                salt[..24].copy_from_slice(head);
+                salt
+            }
+            SaltScheme::Hashed { .. } => self.hash_salt_nonce(&self.salt_nonce(nonce).unwrap()).unwrap(),
+        }
+    }
+
+    /// The CREATE2 salt a factory derives from the salt nonce passed to it,
+    /// if it derives the salt itself.
+    pub fn hash_salt_nonce(&self, salt_nonce: &[u8; 32]) -> Option<[u8; 32]> {
+        match self {
+            SaltScheme::Nonce | SaltScheme::Caller { .. } | SaltScheme::Head { .. } => None,
+            SaltScheme::Hashed { prefix, .. } => {
+                let mut preimage = prefix.clone();
+                preimage.extend_from_slice(salt_nonce);
+                Some(keccak256(&preimage))
+            }
+        }
+    }
+
+    /// The value passed to the factory in place of the salt, if the factory
+    /// derives the salt itself.
+    pub fn salt_nonce(&self, nonce: u64) -> Option<[u8; 32]> {
+        match self {
+            SaltScheme::Nonce | SaltScheme::Caller { .. } | SaltScheme::Head { .. } => None,
+            SaltScheme::Hashed { head, .. } => {
+                let mut salt_nonce = uint_word(nonce);
+                salt_nonce[..24].copy_from_slice(head);
+                Some(salt_nonce)
+            }
+        }
+    }
+
+    /// The kernel's encoding of the scheme: a mode byte, then the length of
+    /// the salt's leading bytes and those bytes for `Caller` and `Head`, or
+    /// for hashed
+    /// salts the length of the prefix's last partial block, the keccak state
+    /// after absorbing the prefix's full blocks, the head and that partial
+    /// block.
+    pub fn kernel_bytes(&self) -> Vec<u8> {
+        match self {
+            SaltScheme::Nonce => vec![0, 0],
+            SaltScheme::Caller { caller } => {
+                let mut bytes = vec![2, 20];
+                bytes.extend_from_slice(caller);
+                bytes
+            }
+            SaltScheme::Head { head } => {
+                let mut bytes = vec![0, 24];
+                bytes.extend_from_slice(head);
+                bytes
+            }
+            SaltScheme::Hashed { prefix, head, .. } => {
+                let full = prefix.len() - prefix.len() % KECCAK_RATE;
+                let mut bytes = vec![1, (prefix.len() - full) as u8];
+                bytes.extend_from_slice(&absorb(&prefix[..full]));
+                bytes.extend_from_slice(head);
+                bytes.extend_from_slice(&prefix[full..]);
+                bytes
+            }
+        }
+    }
+}
+
+// The keccak state after absorbing whole blocks of `data`, as little-endian lanes
+fn absorb(data: &[u8]) -> [u8; 200] {
+    let mut state = [0u64; 25];
+    for block in data.chunks(KECCAK_RATE) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= LittleEndian::read_u64(bytes);
+        }
+        keccakf(&mut state);
+    }
+
+    let mut bytes = [0u8; 200];
+    LittleEndian::write_u64_into(&state, &mut bytes);
+    bytes
+}
+
+/// How the salt bytes ahead of the nonce are chosen, where the factory
+/// leaves them free.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HighBytes {
+    /// Zero.
+    #[default]
+    Zero,
+    /// Random, drawn for each device when its search starts, so that salts
+    /// are not predictable.
+    Random,
+    /// A 24-byte big-endian counter that starts at the given value for
+    /// device 0 and is one higher for each further device. Pass the next
+    /// unused value to the next run to keep runs disjoint.
+    Counter([u8; 24]),
+}
+
+impl HighBytes {
+    /// The high bytes for a device.
+    pub fn for_device(&self, device: u32) -> [u8; 24] {
+        match self {
+            HighBytes::Zero => [0u8; 24],
+            HighBytes::Random => {
+                let mut high = [0u8; 24];
+                thread_rng().fill_bytes(&mut high);
+                high
+            }
+            HighBytes::Counter(start) => {
+                let mut high = *start;
+                let mut carry = device as u64;
+                for byte in high.iter_mut().rev() {
+                    let sum = *byte as u64 + (carry & 0xff);
+                    *byte = sum as u8;
+                    carry = (carry >> 8) + (sum >> 8);
+                }
+                high
+            }
+        }
+    }
+}
+
+impl FromStr for HighBytes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zero" => Ok(HighBytes::Zero),
+            "random" => Ok(HighBytes::Random),
+            _ => {
+                let digits = s.strip_prefix("0x").unwrap_or(s);
+                if digits.is_empty() || digits.len() > 48 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("high bytes '{}' must be zero, random or a counter of up to 48 hex digits", s));
+                }
+                let padded = format!("{:0>48}", digits);
+                let mut start = [0u8; 24];
+                start.copy_from_slice(&hex::decode(padded).map_err(|e| e.to_string())?);
+                Ok(HighBytes::Counter(start))
+            }
+        }
+    }
+}
+
+/// How the chain derives a contract address from the deployer, salt and
+/// init code hash.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressScheme {
+    /// `keccak256(0xff ++ deployer ++ salt ++ init code hash)`, as on
+    /// Ethereum and most EVM chains.
+    #[default]
+    Create2,
+    /// zkSync Era's ContractDeployer, where the init code hash is the
+    /// versioned bytecode hash and the constructor input is hashed
+    /// separately.
+    ZkSync { constructor_input_hash: [u8; 32] },
+}
+
+impl AddressScheme {
+    /// The address `deployer` deploys to with `salt`.
+    pub fn address(&self, deployer: &[u8; 20], salt: &[u8; 32], init_code_hash: &[u8; 32]) -> [u8; 20] {
+        match self {
+            AddressScheme::Create2 => create2_address(deployer, salt, init_code_hash),
+            AddressScheme::ZkSync { constructor_input_hash } => {
+                zksync_create2_address(deployer, salt, init_code_hash, constructor_input_hash)
+            }
+        }
+    }
+
+    /// The kernel's encoding of the scheme: a mode byte, then for zkSync the
+    /// `zksyncCreate2` prefix hash and the constructor input hash.
+    pub fn kernel_bytes(&self) -> Vec<u8> {
+        match self {
+            AddressScheme::Create2 => vec![0],
+            AddressScheme::ZkSync { constructor_input_hash } => {
+                let mut bytes = vec![1];
+                bytes.extend_from_slice(&create2_prefix());
+                bytes.extend_from_slice(constructor_input_hash);
+                bytes
+            }
+        }
+    }
+}
+
+/// Whether an address satisfies the prefix, suffix and hook flags in
+/// `config`.
+pub fn matches_criteria(config: &Config, address: &[u8; 20]) -> bool {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    if config.starts_with.len() > 40 || config.ends_with.len() > 40 {
+        return false;
+    }
+
+    // compare nibble by nibble to avoid hex-encoding every address
+    let digit = |position: usize| {
+        let byte = address[position / 2];
+        let nibble = if position.is_multiple_of(2) { byte >> 4 } else { byte & 0xf };
+        HEX_DIGITS[nibble as usize]
+    };
+
+    let (tail_mask, tail_value) = tail(config);
+    let suffix_start = 40 - config.ends_with.len();
+    config.starts_with.bytes().enumerate().all(|(i, c)| digit(i) == c)
+        && config.ends_with.bytes().enumerate().all(|(i, c)| digit(suffix_start + i) == c)
+        && address[18] & tail_mask[0] == tail_value[0]
+        && address[19] & tail_mask[1] == tail_value[1]
+}
+
+/// The criteria of near-miss shares for the search in `config`: the first
+/// `digits` hex digits of its pattern, taken from the prefix and then from
+/// the end of the suffix, without hook flags. Every match is also a share,
+/// and about one address in 16^digits is, so the shares a device finds show
+/// how many nonces it really tested.
+pub fn share_config(config: &Config, digits: usize) -> Result<Config, String> {
+    let pattern_digits = config.starts_with.len() + config.ends_with.len();
+    if digits == 0 || digits >= pattern_digits {
+        return Err(format!(
+            "shares need fewer digits than the {} of the pattern, and at least one",
+            pattern_digits
+        ));
+    }
+
+    let prefix_len = digits.min(config.starts_with.len());
+    let suffix_len = digits - prefix_len;
+    let mut share = config.clone();
+    share.starts_with.truncate(prefix_len);
+    share.ends_with = config.ends_with[config.ends_with.len() - suffix_len..].to_string();
+    share.hook_flags = None;
+    Ok(share)
+}
+
+/// The mask and required value of the last two address bytes, as passed to
+/// the kernel. A zero mask places no constraint.
+pub fn tail(config: &Config) -> ([u8; 2], [u8; 2]) {
+    match config.hook_flags {
+        Some(flags) => hook_tail(flags),
+        None => ([0; 2], [0; 2]),
+    }
+}
+
+/// Block while `config.paused` is set and the search is not stopped, and
+/// return how long that was, so that it is left out of the elapsed time.
+fn wait_while_paused(config: &Config) -> Duration {
+    let start = Instant::now();
+    while config.paused.load(Ordering::SeqCst) && !config.stop.load(Ordering::SeqCst) {
+        thread::sleep(PAUSE_POLL);
+    }
+    start.elapsed()
+}
+
+/// Run `miner` over random batches until it finds a salt whose address
+/// matches `config` or `config.stop` is set, reporting progress about once
+/// per second.
+///
+/// Each hit is recomputed on the host; a device that reports an address
+/// that does not match its salt is counted as an error and its batch
+/// skipped.
+pub fn search<M: Miner>(config: &Config, miner: &mut M) -> Result<Solution, Box<dyn Error>> {
+    let mut start_time = Instant::now();
+    let mut previous_time = 0.0;
+    let mut cumulative_nonce: u64 = 0;
+    let difficulty = Difficulty::of(config);
+    let mut rng = thread_rng();
+
+    loop {
+        if config.stop.load(Ordering::SeqCst) {
+            return Err("search stopped".into());
+        }
+        start_time += wait_while_paused(config);
+
+        // Start each batch at a random 2^32-aligned nonce
+        let search_space = rng.gen::<u32>();
+        let base = (search_space as u64) << 32;
+
+        let batch_start = Instant::now();
+        let hit = miner.mine(base).inspect_err(|_| config.metrics.record_error(config.gpu_device))?;
+        config.metrics.record_batch(config.gpu_device, miner.batch_size(), batch_start.elapsed().as_secs_f64());
+        cumulative_nonce += miner.batch_size();
+        config.attempts.fetch_add(miner.batch_size(), Ordering::SeqCst);
+
+        if let Some(hit) = hit {
+            let salt = config.salt_scheme.salt(hit.nonce);
+
+            // Verify the address using the same method as Foundry
+            let computed_address = config.address_scheme.address(&config.factory_address, &salt, &config.init_code_hash);
+
+            if computed_address != hit.address {
+                config.metrics.record_error(config.gpu_device);
+                emit(config, Event::Error(&format!(
+                    "device {} reported address 0x{} but salt 0x{} gives 0x{}; skipping the batch",
+                    config.gpu_device,
+                    hex::encode(hit.address),
+                    hex::encode(salt),
+                    hex::encode(computed_address)
+                )));
+            } else if matches_criteria(config, &computed_address) {
+                config.metrics.record_solution();
+                return Ok(Solution {
+                    device: config.gpu_device,
+                    salt,
+                    address: computed_address,
+                    init_code_hash: config.init_code_hash,
+                    deployer: config.factory_address,
+                    caller: config.calling_address,
+                    attempts: cumulative_nonce,
+                    elapsed_seconds: start_time.elapsed().as_secs_f64(),
+                    score: None,
+                    salt_nonce: config.salt_scheme.salt_nonce(hit.nonce),
+                });
+            }
+        }
+
+        // Print status update
+        let elapsed = start_time.elapsed().as_secs_f64();
+        if elapsed - previous_time >= 1.0 {
+            previous_time = elapsed;
+
+            emit(config, Event::Progress(&Progress {
+                device: config.gpu_device,
+                elapsed_seconds: elapsed,
+                attempts: cumulative_nonce,
+                work_size: miner.batch_size(),
+                search_space,
+                difficulty,
+                best: None,
+            }));
+        }
+    }
+}
+
+/// Run `scorer` over random batches until `config.stop` is set, reporting
+/// every address that beats the best score found so far by any device
+/// sharing `config.best_score`. Returns the last improvement this device
+/// found.
+///
+/// The best score is read before each batch and passed to the device, so
+/// that only improvements are reported back. Each one is rescored on the
+/// host; a device that reports a score that does not match its salt is
+/// counted as an error and its batch skipped.
+pub fn search_best<S: Scorer>(config: &Config, scorer: &mut S, score_function: ScoreFunction) -> Result<Solution, Box<dyn Error>> {
+    let mut start_time = Instant::now();
+    let mut previous_time = 0.0;
+    let mut cumulative_nonce: u64 = 0;
+    let difficulty = Difficulty::of(config);
+    let mut rng = thread_rng();
+    let mut best: Option<Solution> = None;
+
+    loop {
+        if config.stop.load(Ordering::SeqCst) {
+            return best.ok_or_else(|| "search stopped before this device improved on the best score".into());
+        }
+        start_time += wait_while_paused(config);
+
+        // Start each batch at a random 2^32-aligned nonce
+        let search_space = rng.gen::<u32>();
+        let base = (search_space as u64) << 32;
+
+        let threshold = config.best_score.load(Ordering::SeqCst);
+        let batch_start = Instant::now();
+        let hit = scorer.best(base, threshold).inspect_err(|_| config.metrics.record_error(config.gpu_device))?;
+        config.metrics.record_batch(config.gpu_device, scorer.batch_size(), batch_start.elapsed().as_secs_f64());
+        cumulative_nonce += scorer.batch_size();
+        config.attempts.fetch_add(scorer.batch_size(), Ordering::SeqCst);
+
+        if let Some(hit) = hit {
+            let salt = config.salt_scheme.salt(hit.nonce);
+            let address = config.address_scheme.address(&config.factory_address, &salt, &config.init_code_hash);
+            let score = score_function.score(&address);
+
+            if score != hit.score {
+                config.metrics.record_error(config.gpu_device);
+                emit(config, Event::Error(&format!(
+                    "device {} reported score {} but 0x{} from salt 0x{} scores {}; skipping the batch",
+                    config.gpu_device,
+                    hit.score,
+                    hex::encode(address),
+                    hex::encode(salt),
+                    score
+                )));
+            } else if config.best_score.fetch_max(score, Ordering::SeqCst) < score {
+                // only report it if no other device found a better address
+                // during this batch
+                let solution = Solution {
+                    device: config.gpu_device,
+                    salt,
+                    address,
+                    init_code_hash: config.init_code_hash,
+                    deployer: config.factory_address,
+                    caller: config.calling_address,
+                    attempts: cumulative_nonce,
+                    elapsed_seconds: start_time.elapsed().as_secs_f64(),
+                    score: Some(score),
+                    salt_nonce: config.salt_scheme.salt_nonce(hit.nonce),
+                };
+                config.metrics.record_improvement();
+                emit(config, Event::Improvement(&solution));
+                best = Some(solution);
+            }
+        }
+
+        // Print status update
+        let elapsed = start_time.elapsed().as_secs_f64();
+        if elapsed - previous_time >= 1.0 {
+            previous_time = elapsed;
+
+            emit(config, Event::Progress(&Progress {
+                device: config.gpu_device,
+                elapsed_seconds: elapsed,
+                attempts: cumulative_nonce,
+                work_size: scorer.batch_size(),
+                search_space,
+                difficulty,
+                best: best.clone(),
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    use crate::testing::{criteria_for, pattern_config, rng, string_match};
+
+    #[test]
+    fn matches_criteria_agrees_with_string_matching() {
+        let mut rng = rng();
+        for _ in 0..1024 {
+            let mut address = [0u8; 20];
+            rng.fill_bytes(&mut address);
+            let criteria = criteria_for(&mut rng, &address);
+
+            let config = pattern_config(&criteria.starts_with, &criteria.ends_with, criteria.hook_flags);
+            assert_eq!(
+                matches_criteria(&config, &address),
+                string_match(&address, &criteria),
+                "0x{} {:?}",
+                hex::encode(address),
+                criteria
+            );
+        }
+    }
+
+    /// Reports a wrong score for every batch, and stops the search after
+    /// `batches` of them.
+    struct WrongScorer {
+        stop: Arc<AtomicBool>,
+        batches: u32,
+    }
+
+    impl Scorer for WrongScorer {
+        fn batch_size(&self) -> u64 {
+            1
+        }
+
+        fn best(&mut self, base: u64, _threshold: u32) -> Result<Option<ScoredHit>, Box<dyn Error>> {
+            self.batches -= 1;
+            if self.batches == 0 {
+                self.stop.store(true, Ordering::SeqCst);
+            }
+            Ok(Some(ScoredHit { nonce: base, score: 1000 }))
+        }
+    }
+
+    #[test]
+    fn wrong_scores_are_counted_and_skipped() {
+        let config = pattern_config("", "", None);
+        let mut scorer = WrongScorer { stop: config.stop.clone(), batches: 3 };
+        assert!(search_best(&config, &mut scorer, ScoreFunction::UniswapV4).is_err());
+        assert_eq!(config.best_score.load(Ordering::SeqCst), 0);
+        assert!(config.metrics.render(0).contains("create2gpu_kernel_errors_total{device=\"0\"} 3\n"));
+    }
+
+    #[test]
+    fn shares_take_digits_from_the_prefix_then_the_suffix() {
+        let config = pattern_config("abc", "123", Some(0x0044));
+        let shares = share_config(&config, 4).unwrap();
+        assert_eq!((shares.starts_with.as_str(), shares.ends_with.as_str(), shares.hook_flags), ("abc", "3", None));
+        assert!(share_config(&config, 6).is_err());
+        assert!(share_config(&config, 0).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use serde_json::json;
+use separator::Separatable;
+use tiny_keccak::Keccak;
+
+use crate::abi::selector;
+use crate::identifier::is_identifier;
+use crate::output::OutputFormat;
+
+/// Characters of the suffix appended to the function name.
+pub const SUFFIX_ALPHABET: &[u8; 62] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Longest suffix of a 64-bit nonce.
+pub const MAX_SUFFIX_LEN: usize = 11;
+
+/// Write the suffix for a nonce into `buffer` and return its length. The
+/// suffix is the nonce in bijective base 62, least significant character
+/// first, so nonces count through every one-character suffix, then every
+/// two-character suffix and so on, like `hashSelector`.
+pub fn write_suffix(nonce: u64, buffer: &mut [u8; MAX_SUFFIX_LEN]) -> usize {
+    let mut digits = nonce;
+    let mut len = 0;
+    loop {
+        buffer[len] = SUFFIX_ALPHABET[(digits % 62) as usize];
+        len += 1;
+        digits /= 62;
+        if digits == 0 {
+            return len;
+        }
+        digits -= 1;
+    }
+}
+
+/// The suffix for a nonce, as a string.
+pub fn suffix(nonce: u64) -> String {
+    let mut buffer = [0u8; MAX_SUFFIX_LEN];
+    let len = write_suffix(nonce, &mut buffer);
+    String::from_utf8_lossy(&buffer[..len]).into_owned()
+}
+
+/// A search for function signatures `name_<suffix>(params)` with a cheap
+/// selector: one with enough zero bytes (cheaper calldata) or a low value
+/// (checked first by the dispatcher).
+#[derive(Clone, Debug)]
+pub struct SelectorConfig {
+    /// The function name the suffix is appended to.
+    pub name: String,
+    /// The parameter list, with its parentheses.
+    pub params: String,
+    /// Zero bytes the selector must have, anywhere in it.
+    pub zero_bytes: u8,
+    /// Selectors must be below this value, read as a big-endian integer.
+    pub below: Option<u32>,
+    /// How many signatures to find before stopping.
+    pub count: usize,
+    pub gpu_device: u32,
+    pub format: OutputFormat,
+    pub tuning_file: Option<PathBuf>,
+}
+
+impl SelectorConfig {
+    /// A search for suffixes of the function in `signature`, such as
+    /// `transfer(address,uint256)`, with no criteria set.
+    pub fn new(signature: &str) -> Result<Self, String> {
+        let open = signature
+            .find('(')
+            .ok_or_else(|| format!("function signature '{}' has no parameter list", signature))?;
+        let (name, params) = signature.split_at(open);
+
+        if !is_identifier(name) {
+            return Err(format!("'{}' is not a valid function name", name));
+        }
+        if !params.ends_with(')') || params.chars().any(char::is_whitespace) {
+            return Err(format!(
+                "parameter list '{}' must be canonical, like (address,uint256), without spaces",
+                params
+            ));
+        }
+        if name.len() > u8::MAX as usize || params.len() > u8::MAX as usize {
+            return Err(format!("function name and parameter list must each be at most {} bytes", u8::MAX));
+        }
+
+        Ok(SelectorConfig {
+            name: name.to_string(),
+            params: params.to_string(),
+            zero_bytes: 0,
+            below: None,
+            count: 1,
+            gpu_device: 0,
+            format: OutputFormat::Text,
+            tuning_file: None,
+        })
+    }
+
+    /// The function signature for a nonce.
+    pub fn signature(&self, nonce: u64) -> String {
+        format!("{}_{}{}", self.name, suffix(nonce), self.params)
+    }
+
+    /// The selector of the signature for a nonce, without building the
+    /// signature string.
+    pub fn selector(&self, nonce: u64) -> [u8; 4] {
+        let mut buffer = [0u8; MAX_SUFFIX_LEN];
+        let len = write_suffix(nonce, &mut buffer);
+
+        let mut hasher = Keccak::new_keccak256();
+        hasher.update(self.name.as_bytes());
+        hasher.update(b"_");
+        hasher.update(&buffer[..len]);
+        hasher.update(self.params.as_bytes());
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+
+        let mut selector = [0u8; 4];
+        selector.copy_from_slice(&hash[..4]);
+        selector
+    }
+
+    /// Whether a selector meets the criteria.
+    pub fn matches(&self, selector: &[u8; 4]) -> bool {
+        let zero_bytes = selector.iter().filter(|&&b| b == 0).count();
+        zero_bytes >= self.zero_bytes as usize && (u32::from_be_bytes(*selector) as u64) < self.threshold()
+    }
+
+    /// The kernel's `threshold` argument: selectors must be below it.
+    pub fn threshold(&self) -> u64 {
+        self.below.map_or(1 << 32, u64::from)
+    }
+
+    /// The kernel's `message` argument: the length and bytes of the name,
+    /// then the length and bytes of the parameter list.
+    pub fn kernel_bytes(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(2 + self.name.len() + self.params.len());
+        message.push(self.name.len() as u8);
+        message.extend_from_slice(self.name.as_bytes());
+        message.push(self.params.len() as u8);
+        message.extend_from_slice(self.params.as_bytes());
+        message
+    }
+
+    /// A one-line description of the criteria.
+    pub fn describe(&self) -> String {
+        let mut criteria = Vec::new();
+        if self.zero_bytes > 0 {
+            criteria.push(format!("at least {} zero bytes", self.zero_bytes));
+        }
+        if let Some(below) = self.below {
+            criteria.push(format!("below 0x{:08x}", below));
+        }
+        format!("Searching for selectors of {}_<suffix>{} {}", self.name, self.params, criteria.join(" and "))
+    }
+}
+
+/// A function signature whose selector meets the criteria, verified on the
+/// host.
+#[derive(Clone, Debug)]
+pub struct SelectorSolution {
+    pub signature: String,
+    pub selector: [u8; 4],
+    pub nonce: u64,
+    pub attempts: u64,
+    pub elapsed_seconds: f64,
+}
+
+impl SelectorSolution {
+    /// Print the solution as a `selector` event.
+    pub fn report(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                json!({
+                    "event": "selector",
+                    "signature": self.signature,
+                    "selector": format!("0x{}", hex::encode(self.selector)),
+                    "attempts": self.attempts,
+                    "elapsed_seconds": self.elapsed_seconds,
+                })
+            ),
+            OutputFormat::Text => println!("0x{}  {}", hex::encode(self.selector), self.signature),
+        }
+    }
+}
+
+/// A backend that tests a batch of consecutive suffix nonces.
+pub trait SelectorMiner {
+    /// Number of nonces tested by one call to `mine`.
+    fn batch_size(&self) -> u64;
+
+    /// Test the nonces `base..base + batch_size()` and return the lowest one
+    /// whose selector meets the criteria.
+    fn mine(&mut self, base: u64) -> Result<Option<u64>, Box<dyn Error>>;
+}
+
+/// Run `miner` over consecutive batches from the shortest suffixes up,
+/// reporting each matching signature as it is found, until `config.count`
+/// have been found.
+///
+/// Each hit is recomputed on the host; a device that reports a nonce whose
+/// selector does not match is treated as an error.
+pub fn search_selectors<M: SelectorMiner>(
+    config: &SelectorConfig,
+    miner: &mut M,
+) -> Result<Vec<SelectorSolution>, Box<dyn Error>> {
+    let start_time = Instant::now();
+    let mut previous_time = 0.0;
+    let mut base: u64 = 0;
+    let mut hashes: u64 = 0;
+    let mut solutions = Vec::new();
+
+    while solutions.len() < config.count {
+        let hit = miner.mine(base)?;
+        hashes += miner.batch_size();
+
+        match hit {
+            Some(nonce) => {
+                let signature = config.signature(nonce);
+                let selector = selector(&signature);
+                if !config.matches(&selector) {
+                    return Err(format!(
+                        "device reported {} whose selector 0x{} does not meet the criteria",
+                        signature,
+                        hex::encode(selector)
+                    ).into());
+                }
+
+                let solution = SelectorSolution {
+                    signature,
+                    selector,
+                    nonce,
+                    attempts: nonce + 1,
+                    elapsed_seconds: start_time.elapsed().as_secs_f64(),
+                };
+                solution.report(config.format);
+                solutions.push(solution);
+
+                // carry on right after the hit, as the rest of the batch may
+                // hold more
+                base = nonce + 1;
+            }
+            None => base += miner.batch_size(),
+        }
+
+        let elapsed = start_time.elapsed().as_secs_f64();
+        if elapsed - previous_time >= 1.0 {
+            previous_time = elapsed;
+            config.format.info(&format!(
+                "searched {} signatures, up to suffix '{}' ({:.2} million hashes per second)",
+                base.separated_string(),
+                suffix(base.saturating_sub(1)),
+                hashes as f64 / elapsed / 1_000_000.0
+            ));
+        }
+    }
+
+    Ok(solutions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suffixes_count_up_through_every_length() {
+        assert_eq!(
+            (suffix(0), suffix(61), suffix(62), suffix(3905), suffix(3906)),
+            ("0".to_string(), "Z".to_string(), "00".to_string(), "ZZ".to_string(), "000".to_string())
+        );
+        assert_eq!(suffix(u64::MAX).len(), MAX_SUFFIX_LEN);
+    }
+
+    #[test]
+    fn selector_hashes_the_signature() {
+        // a name long enough for the signature to span two keccak blocks
+        for name in &["transfer", &"x".repeat(130)] {
+            let config = SelectorConfig::new(&format!("{}(address,uint256)", name)).expect("valid signature");
+            for &nonce in &[0, 61, 62, 3906, 1 << 40, u64::MAX] {
+                assert_eq!(config.selector(nonce), selector(&config.signature(nonce)), "{}", config.signature(nonce));
+            }
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::factory::Factory;
+use crate::hooks::{check_hook_prefix, parse_hook_flags};
+use crate::output::{Event, OutputFormat, Solution};
+use crate::parse::{check_patterns, parse_address, parse_hash, parse_pattern};
+use crate::{AddressScheme, Config, HighBytes, SaltScheme, ScoreFunction};
+
+/// Largest request body the server reads.
+const MAX_BODY_LEN: usize = 1 << 20;
+
+/// Longest request line or header line the server reads.
+pub const MAX_LINE_LEN: usize = 8 << 10;
+
+/// How long the server waits on a client that has stopped sending its
+/// request.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A search backend: `cpu` or `gpu`.
+pub type Backend = fn(Config) -> Result<Solution, Box<dyn Error>>;
+
+/// A search submitted to the server as the body of `POST /jobs`. Addresses,
+/// hashes and patterns are hex strings, as on the command line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobSpec {
+    /// The contract that calls CREATE2, unless `factory` is given.
+    pub deployer: Option<String>,
+    /// A well-known factory such as "createx", in place of `deployer`.
+    pub factory: Option<String>,
+    /// The caller of the deployer; the zero address by default.
+    pub caller: Option<String>,
+    pub init_code_hash: String,
+    pub starts_with: Option<String>,
+    pub ends_with: Option<String>,
+    /// Uniswap v4 hook permission names, such as "beforeSwap,afterSwap".
+    pub hook_flags: Option<String>,
+    /// Keep the best address under this score function instead of matching
+    /// a pattern; the job then runs until it is cancelled.
+    pub score: Option<String>,
+    /// "zero", "random" or a hex counter, as for `--high-bytes`.
+    pub high_bytes: Option<String>,
+}
+
+impl JobSpec {
+    /// The search configuration for the job on `device`.
+    pub fn config(&self, device: u32, tuning_file: Option<PathBuf>) -> Result<Config, Box<dyn Error>> {
+        let caller = match self.caller {
+            Some(ref caller) => parse_address("caller", caller)?,
+            None => [0u8; 20],
+        };
+        let (deployer, salt_scheme) = match (&self.deployer, &self.factory) {
+            (Some(deployer), None) => (parse_address("deployer", deployer)?, SaltScheme::Nonce),
+            (None, Some(factory)) => {
+                let factory = factory.parse::<Factory>()?;
+                (factory.address(), factory.salt_scheme(&caller))
+            }
+            _ => return Err("a job needs exactly one of deployer and factory".into()),
+        };
+        let high_bytes = match self.high_bytes {
+            Some(ref high_bytes) => high_bytes.parse::<HighBytes>()?,
+            None => HighBytes::Zero,
+        };
+        let salt_scheme = match high_bytes {
+            HighBytes::Zero => salt_scheme,
+            _ => salt_scheme.with_high_bytes(&high_bytes.for_device(device)),
+        };
+
+        let starts_with = parse_pattern("prefix", self.starts_with.as_deref().unwrap_or_default())?;
+        let ends_with = parse_pattern("suffix", self.ends_with.as_deref().unwrap_or_default())?;
+        let hook_flags = match self.hook_flags {
+            Some(ref names) => {
+                check_hook_prefix(&starts_with)?;
+                Some(parse_hook_flags("hook flags", names)?)
+            }
+            None => None,
+        };
+        let score = match self.score {
+            Some(ref score) => Some(score.parse::<ScoreFunction>()?),
+            None => None,
+        };
+        match score {
+            Some(_) if !starts_with.is_empty() || !ends_with.is_empty() || hook_flags.is_some() => {
+                return Err("a scoring job cannot also have a pattern".into())
+            }
+            None if starts_with.is_empty() && ends_with.is_empty() && hook_flags.is_none() => {
+                return Err("a job needs starts_with, ends_with, hook_flags or score".into())
+            }
+            _ => check_patterns(&starts_with, &ends_with)?,
+        }
+
+        Ok(Config {
+            factory_address: deployer,
+            calling_address: caller,
+            init_code_hash: parse_hash("init code hash", &self.init_code_hash)?,
+            gpu_device: device,
+            leading_zeroes_threshold: 0,
+            total_zeroes_threshold: 0,
+            prefix: None,
+            starts_with,
+            ends_with,
+            case_sensitive: false,
+            // the server's stdout is a log of every job's events
+            format: OutputFormat::Json,
+            tuning_file,
+            hook_flags,
+            score,
+            best_score: Default::default(),
+            stop: Default::default(),
+            paused: Default::default(),
+            attempts: Default::default(),
+            metrics: Default::default(),
+            dashboard: Default::default(),
+            salt_scheme,
+            address_scheme: AddressScheme::Create2,
+        })
+    }
+}
+
+/// Where a job is in its life.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    /// Waiting for the jobs ahead of it.
+    Queued,
+    Running,
+    /// Stopped with a matching address.
+    Found,
+    /// Cancelled before or while running. A cancelled scoring job keeps the
+    /// best address it found.
+    Cancelled,
+    Failed,
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "queued"),
+            JobState::Running => write!(f, "running"),
+            JobState::Found => write!(f, "found"),
+            JobState::Cancelled => write!(f, "cancelled"),
+            JobState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A submitted search and what has come of it.
+pub struct Job {
+    pub id: u64,
+    pub spec: JobSpec,
+    pub config: Config,
+    pub state: JobState,
+    started: Option<Instant>,
+    elapsed_seconds: f64,
+    pub solution: Option<Solution>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    /// Seconds the job has been running, or ran for.
+    pub fn elapsed_seconds(&self) -> f64 {
+        match (self.state, self.started) {
+            (JobState::Running, Some(started)) => started.elapsed().as_secs_f64(),
+            _ => self.elapsed_seconds,
+        }
+    }
+
+    /// The job as returned by the API.
+    pub fn status(&self) -> Value {
+        let attempts = self.config.attempts.load(Ordering::SeqCst);
+        let elapsed = self.elapsed_seconds();
+        let mut status = json!({
+            "id": self.id,
+            "state": self.state.to_string(),
+            "attempts": attempts,
+            "elapsed_seconds": elapsed,
+            "rate": if elapsed > 0.0 { attempts as f64 / elapsed } else { 0.0 },
+            "deployer": format!("0x{}", hex::encode(self.config.factory_address)),
+            "caller": format!("0x{}", hex::encode(self.config.calling_address)),
+            "init_code_hash": format!("0x{}", hex::encode(self.config.init_code_hash)),
+        });
+        if !self.config.starts_with.is_empty() {
+            status["starts_with"] = json!(self.config.starts_with);
+        }
+        if !self.config.ends_with.is_empty() {
+            status["ends_with"] = json!(self.config.ends_with);
+        }
+        if let Some(flags) = self.config.hook_flags {
+            status["hook_flags"] = json!(format!("0x{:04x}", flags));
+        }
+        if let Some(score_function) = self.config.score {
+            status["score"] = json!(score_function.to_string());
+            status["best_score"] = json!(self.config.best_score.load(Ordering::SeqCst));
+        }
+        if let Some(ref solution) = self.solution {
+            status["solution"] = match self.config.score {
+                Some(_) => Event::Improvement(solution).to_json(),
+                None => Event::Solution(solution).to_json(),
+            };
+        }
+        if let Some(ref error) = self.error {
+            status["error"] = json!(error);
+        }
+        status
+    }
+}
+
+/// A queue of jobs run one after another on one device.
+pub struct JobServer {
+    backend: Backend,
+    device: u32,
+    tuning_file: Option<PathBuf>,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    queued: Condvar,
+}
+
+impl JobServer {
+    pub fn new(backend: Backend, device: u32, tuning_file: Option<PathBuf>) -> Self {
+        JobServer { backend, device, tuning_file, jobs: Mutex::new(BTreeMap::new()), queued: Condvar::new() }
+    }
+
+    /// Queue a job and return its status.
+    pub fn submit(&self, spec: JobSpec) -> Result<Value, Box<dyn Error>> {
+        let config = spec.config(self.device, self.tuning_file.clone())?;
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = jobs.keys().next_back().map_or(1, |id| id + 1);
+        let job = Job { id, spec, config, state: JobState::Queued, started: None, elapsed_seconds: 0.0, solution: None, error: None };
+        let status = job.status();
+        jobs.insert(id, job);
+        self.queued.notify_one();
+        Ok(status)
+    }
+
+    /// The status of a job, if it exists.
+    pub fn status(&self, id: u64) -> Option<Value> {
+        self.jobs.lock().unwrap().get(&id).map(Job::status)
+    }
+
+    /// The status of every job, oldest first.
+    pub fn list(&self) -> Vec<Value> {
+        self.jobs.lock().unwrap().values().map(Job::status).collect()
+    }
+
+    /// Cancel a job: a queued job never starts, and a running one stops
+    /// after its current batch. Finished jobs are left as they are.
+    pub fn cancel(&self, id: u64) -> Option<Value> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&id)?;
+        match job.state {
+            JobState::Queued => job.state = JobState::Cancelled,
+            JobState::Running => job.config.stop.store(true, Ordering::SeqCst),
+            _ => {}
+        }
+        Some(job.status())
+    }
+
+    /// Run queued jobs in order, forever.
+    pub fn run_jobs(&self) {
+        loop {
+            let (id, config, factory) = {
+                let mut jobs = self.jobs.lock().unwrap();
+                let id = loop {
+                    match jobs.values().find(|job| job.state == JobState::Queued) {
+                        Some(job) => break job.id,
+                        None => jobs = self.queued.wait(jobs).unwrap(),
+                    }
+                };
+                let job = jobs.get_mut(&id).unwrap();
+                job.state = JobState::Running;
+                job.started = Some(Instant::now());
+                (id, job.config.clone(), job.spec.factory.clone())
+            };
+
+            let result = (self.backend)(config.clone()).and_then(|solution| check_factory(factory.as_deref(), solution));
+
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs.get_mut(&id).unwrap();
+            job.elapsed_seconds = job.started.map_or(0.0, |started| started.elapsed().as_secs_f64());
+            let cancelled = config.stop.load(Ordering::SeqCst);
+            match result {
+                Ok(solution) => {
+                    job.state = if cancelled { JobState::Cancelled } else { JobState::Found };
+                    job.solution = Some(solution);
+                }
+                Err(_) if cancelled => job.state = JobState::Cancelled,
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Answer an API request with a status code and a JSON body.
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, &segments[..]) {
+            ("GET", ["jobs"]) => (200, json!({ "jobs": self.list() })),
+            ("POST", ["jobs"]) => {
+                let submitted = serde_json::from_slice::<JobSpec>(body)
+                    .map_err(|e| format!("invalid job: {}", e).into())
+                    .and_then(|spec| self.submit(spec));
+                match submitted {
+                    Ok(status) => (201, status),
+                    Err(e) => (400, json!({ "error": e.to_string() })),
+                }
+            }
+            (_, ["jobs", id]) => {
+                let id = match id.parse::<u64>() {
+                    Ok(id) => id,
+                    Err(_) => return (404, json!({ "error": format!("no job '{}'", id) })),
+                };
+                let status = match method {
+                    "GET" => self.status(id),
+                    "DELETE" => self.cancel(id),
+                    _ => return (405, json!({ "error": format!("{} is not allowed on a job", method) })),
+                };
+                match status {
+                    Some(status) => (200, status),
+                    None => (404, json!({ "error": format!("no job {}", id) })),
+                }
+            }
+            ("GET", _) | ("POST", _) | ("DELETE", _) => (404, json!({ "error": format!("no route for {}", path) })),
+            _ => (405, json!({ "error": format!("method {} is not allowed", method) })),
+        }
+    }
+}
+
+// Check a solution once more the way the job's factory, if any, derives the salt
+fn check_factory(factory: Option<&str>, solution: Solution) -> Result<Solution, Box<dyn Error>> {
+    let factory = match factory {
+        Some(factory) => factory.parse::<Factory>()?,
+        None => return Ok(solution),
+    };
+    let salt = solution.salt_nonce.unwrap_or(solution.salt);
+    if factory.create2_salt(&salt, Some(&solution.caller))? != solution.salt {
+        return Err(format!("{} would not deploy with the mined salt 0x{}", factory, hex::encode(solution.salt)).into());
+    }
+    Ok(solution)
+}
+
+/// Serve the job API on `addr` and run the submitted jobs. Only returns if
+/// the address cannot be bound.
+///
+/// - `POST /jobs` queues a `JobSpec` and returns its status.
+/// - `GET /jobs` lists every job, and `GET /jobs/<id>` returns one.
+/// - `DELETE /jobs/<id>` cancels a job.
+pub fn serve(addr: &str, server: Arc<JobServer>, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("could not listen on {}: {}", addr, e))?;
+    format.info(&format!("Serving jobs on http://{}", listener.local_addr()?));
+
+    let runner = server.clone();
+    thread::spawn(move || runner.run_jobs());
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                format.error(&format!("could not accept a connection: {}", e));
+                continue;
+            }
+        };
+        let server = server.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(&server, stream) {
+                format.error(&format!("could not answer a request: {}", e));
+            }
+        });
+    }
+    Ok(())
+}
+
+// Read one HTTP/1.1 request, answer it and close the connection
+fn handle_connection(server: &JobServer, stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let too_long = json!({ "error": format!("request line or header is over {} bytes", MAX_LINE_LEN) });
+
+    let request_line = match read_line(&mut reader)? {
+        Some(line) => line,
+        None => return respond(stream, 431, &too_long),
+    };
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return respond(stream, 400, &json!({ "error": "malformed request line" })),
+    };
+
+    let mut content_length = 0;
+    loop {
+        let header = match read_line(&mut reader)? {
+            Some(header) => header,
+            None => return respond(stream, 431, &too_long),
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().map_err(|_| "invalid Content-Length")?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        return respond(stream, 413, &json!({ "error": format!("request body is over {} bytes", MAX_BODY_LEN) }));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (code, response) = server.handle(&method, &path, &body);
+    respond(stream, code, &response)
+}
+
+/// Read a line of at most `MAX_LINE_LEN` bytes, or `None` if it is longer.
+/// The end of the stream reads as an empty line.
+pub fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, Box<dyn Error>> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_LEN as u64 + 1).read_line(&mut line)?;
+    Ok(Some(line).filter(|line| line.len() <= MAX_LINE_LEN))
+}
+
+fn respond(mut stream: TcpStream, code: u16, body: &Value) -> Result<(), Box<dyn Error>> {
+    let reason = match code {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    };
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuning::{Tuning, WorkSize, CPU_DEVICE_NAME};
+    use std::env;
+    use std::process;
+
+    const INIT_CODE_HASH: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    // A server on the CPU backend with batches small enough for a debug
+    // build, running its jobs in the background
+    fn server(name: &str) -> Arc<JobServer> {
+        let tuning_file = env::temp_dir().join(format!("create2gpu-serve-{}-{}.json", name, process::id()));
+        let mut tuning = Tuning::default();
+        tuning.devices.insert(CPU_DEVICE_NAME.to_string(), WorkSize { global: 4096, local: None, iterations: 1 });
+        tuning.save(&tuning_file).unwrap();
+
+        let server = Arc::new(JobServer::new(crate::cpu, 0, Some(tuning_file)));
+        let runner = server.clone();
+        thread::spawn(move || runner.run_jobs());
+        server
+    }
+
+    fn post(server: &JobServer, job: Value) -> (u16, Value) {
+        server.handle("POST", "/jobs", job.to_string().as_bytes())
+    }
+
+    // Poll a job until `done` holds for its status
+    fn poll<F: Fn(&Value) -> bool>(server: &JobServer, id: u64, done: F) -> Value {
+        let start = Instant::now();
+        loop {
+            let (code, status) = server.handle("GET", &format!("/jobs/{}", id), b"");
+            assert_eq!(code, 200);
+            if done(&status) {
+                return status;
+            }
+            assert!(start.elapsed() < Duration::from_secs(120), "job {} stuck at {}", id, status);
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn posted_job_runs_until_found() {
+        let server = server("found");
+        let (code, status) = post(&server, json!({
+            "deployer": "0x0000000000000000000000000000000000000002",
+            "init_code_hash": INIT_CODE_HASH,
+            "starts_with": "a",
+        }));
+        assert_eq!(code, 201, "{}", status);
+        assert_eq!(status["state"], "queued");
+
+        let id = status["id"].as_u64().unwrap();
+        let status = poll(&server, id, |status| status["state"] != "queued" && status["state"] != "running");
+        assert_eq!(status["state"], "found", "{}", status);
+        assert!(status["solution"]["address"].as_str().unwrap().starts_with("0xa"), "{}", status);
+
+        let (code, list) = server.handle("GET", "/jobs", b"");
+        assert_eq!(code, 200);
+        assert_eq!(list["jobs"][0]["id"], id);
+    }
+
+    #[test]
+    fn delete_cancels_a_running_scoring_job() {
+        let server = server("cancel");
+        let (code, status) = post(&server, json!({
+            "deployer": "0x0000000000000000000000000000000000000002",
+            "init_code_hash": INIT_CODE_HASH,
+            "score": "uniswap-v4",
+        }));
+        assert_eq!(code, 201, "{}", status);
+        let id = status["id"].as_u64().unwrap();
+
+        // a scoring job only ends when it is cancelled
+        poll(&server, id, |status| status["attempts"].as_u64().unwrap_or(0) > 0);
+        let (code, status) = server.handle("DELETE", &format!("/jobs/{}", id), b"");
+        assert_eq!(code, 200);
+        assert_eq!(status["state"], "running");
+
+        let status = poll(&server, id, |status| status["state"] != "running");
+        assert_eq!(status["state"], "cancelled", "{}", status);
+    }
+
+    #[test]
+    fn bad_requests_are_rejected() {
+        let server = server("errors");
+
+        let (code, status) = server.handle("POST", "/jobs", b"{");
+        assert_eq!(code, 400);
+        assert!(status["error"].as_str().unwrap().starts_with("invalid job"), "{}", status);
+
+        let (code, status) = post(&server, json!({
+            "deployer": "0x0000000000000000000000000000000000000002",
+            "init_code_hash": INIT_CODE_HASH,
+        }));
+        assert_eq!(code, 400);
+        assert_eq!(status["error"], "a job needs starts_with, ends_with, hook_flags or score");
+
+        let (code, _) = post(&server, json!({
+            "deployer": "0x0000000000000000000000000000000000000002",
+            "init_code_hash": INIT_CODE_HASH,
+            "starts_with": "xyz",
+        }));
+        assert_eq!(code, 400);
+
+        assert_eq!(server.handle("GET", "/jobs/7", b"").0, 404);
+        assert_eq!(server.handle("GET", "/jobs/seven", b"").0, 404);
+        assert_eq!(server.handle("DELETE", "/jobs/7", b"").0, 404);
+        assert_eq!(server.handle("GET", "/status", b"").0, 404);
+        assert_eq!(server.handle("PUT", "/jobs/1", b"").0, 405);
+        assert_eq!(server.handle("PATCH", "/jobs", b"").0, 405);
+    }
+
+    #[test]
+    fn overlong_lines_are_cut_off() {
+        let mut short = "GET /jobs HTTP/1.1\r\n".as_bytes();
+        assert_eq!(read_line(&mut short).unwrap().as_deref(), Some("GET /jobs HTTP/1.1\r\n"));
+
+        let long = format!("GET /{} HTTP/1.1\r\n", "a".repeat(MAX_LINE_LEN));
+        assert_eq!(read_line(&mut long.as_bytes()).unwrap(), None);
+    }
+}
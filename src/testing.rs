@@ -0,0 +1,177 @@
+//! Random inputs shared by the unit tests and the kernel tests in
+//! `tests/kernel.rs`. Built for `cargo test` and with the `testing` feature;
+//! not part of the public API.
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use crate::hooks::HOOK_FLAG_MASK;
+use crate::identifier::{IdentifierConfig, IdentifierKind};
+use crate::search::{AddressScheme, SaltScheme};
+use crate::selector::SelectorConfig;
+use crate::{keccak256, Config, OutputFormat};
+
+/// A generator with a fixed seed, so that failures can be reproduced.
+pub fn rng() -> StdRng {
+    let seed: &[usize] = &[0x6372_6561, 0x7465_3267, 0x7075];
+    SeedableRng::from_seed(seed)
+}
+
+/// A prefix, suffix and hook flags to search for.
+#[derive(Clone, Debug, Default)]
+pub struct Criteria {
+    pub starts_with: String,
+    pub ends_with: String,
+    pub hook_flags: Option<u16>,
+}
+
+/// A search of `deployer` and `init_code_hash` for `criteria`, with JSON
+/// output and CREATE2 addresses.
+pub fn config(deployer: [u8; 20], init_code_hash: [u8; 32], criteria: Criteria, salt_scheme: SaltScheme) -> Config {
+    let Criteria { starts_with, ends_with, hook_flags } = criteria;
+    Config {
+        factory_address: deployer,
+        calling_address: [0u8; 20],
+        init_code_hash,
+        gpu_device: 0,
+        leading_zeroes_threshold: 0,
+        total_zeroes_threshold: 0,
+        prefix: None,
+        starts_with,
+        ends_with,
+        case_sensitive: false,
+        format: OutputFormat::Json,
+        tuning_file: None,
+        hook_flags,
+        score: None,
+        best_score: Default::default(),
+        stop: Default::default(),
+        paused: Default::default(),
+        attempts: Default::default(),
+        metrics: Default::default(),
+        dashboard: Default::default(),
+        salt_scheme,
+        address_scheme: AddressScheme::Create2,
+    }
+}
+
+/// A search of the zero deployer and init code hash for a pattern.
+pub fn pattern_config(starts_with: &str, ends_with: &str, hook_flags: Option<u16>) -> Config {
+    let criteria = Criteria { starts_with: starts_with.to_string(), ends_with: ends_with.to_string(), hook_flags };
+    config([0u8; 20], [0u8; 32], criteria, SaltScheme::Nonce)
+}
+
+/// The plain nonce salt, a caller-prefixed salt, either with random high
+/// bytes, or a hashed salt with a random head and a random prefix spanning up
+/// to a few keccak blocks.
+pub fn random_salt_scheme<R: Rng>(rng: &mut R) -> SaltScheme {
+    let mut high = [0u8; 24];
+    rng.fill_bytes(&mut high);
+
+    match rng.gen_range(0, 6) {
+        0 => SaltScheme::Nonce,
+        1 => SaltScheme::Nonce.with_high_bytes(&high),
+        2 => {
+            let mut caller = [0u8; 20];
+            rng.fill_bytes(&mut caller);
+            if rng.gen() {
+                SaltScheme::Caller { caller }
+            } else {
+                SaltScheme::Caller { caller }.with_high_bytes(&high)
+            }
+        }
+        _ => {
+            let mut prefix = vec![0u8; rng.gen_range(0, 400)];
+            let mut head = [0u8; 24];
+            rng.fill_bytes(&mut prefix);
+            rng.fill_bytes(&mut head);
+            SaltScheme::Hashed { prefix, head, fixed: rng.gen_range(0, 25) }.with_high_bytes(&high)
+        }
+    }
+}
+
+/// Ethereum CREATE2 half of the time, otherwise zkSync Era with a random
+/// constructor input hash.
+pub fn random_address_scheme<R: Rng>(rng: &mut R) -> AddressScheme {
+    if rng.gen() {
+        return AddressScheme::Create2;
+    }
+    let mut constructor_input_hash = [0u8; 32];
+    rng.fill_bytes(&mut constructor_input_hash);
+    AddressScheme::ZkSync { constructor_input_hash }
+}
+
+/// A prefix, suffix and (a third of the time) hook flags cut from
+/// `address`, with one digit or flag changed half of the time so that both
+/// matching and non-matching criteria are covered.
+pub fn criteria_for<R: Rng>(rng: &mut R, address: &[u8; 20]) -> Criteria {
+    let mut digits = hex::encode(address).into_bytes();
+    let mut flags = u16::from_be_bytes([address[18], address[19]]) & HOOK_FLAG_MASK;
+    if rng.gen() {
+        let position = rng.gen_range(0, 40);
+        digits[position] = b"0123456789abcdef"[rng.gen_range(0, 16)];
+        flags ^= 1 << rng.gen_range(0, 14);
+    }
+
+    let prefix_len = rng.gen_range(0, 9);
+    let suffix_len = rng.gen_range(0, 9);
+    Criteria {
+        starts_with: String::from_utf8(digits[..prefix_len].to_vec()).unwrap(),
+        ends_with: String::from_utf8(digits[40 - suffix_len..].to_vec()).unwrap(),
+        hook_flags: if rng.gen_weighted_bool(3) { Some(flags) } else { None },
+    }
+}
+
+/// Match with string comparisons on the hex address and the flags read as
+/// an integer, independently of `matches_criteria`.
+pub fn string_match(address: &[u8; 20], criteria: &Criteria) -> bool {
+    let digits = hex::encode(address);
+    let low_bits = u16::from_str_radix(&digits[36..], 16).unwrap() & HOOK_FLAG_MASK;
+    digits.starts_with(&criteria.starts_with)
+        && digits.ends_with(&criteria.ends_with)
+        && criteria.hook_flags.is_none_or(|flags| flags == low_bits)
+}
+
+/// A selector search for a random function name and parameter list, long
+/// enough at times for the signature to span several keccak blocks.
+pub fn random_selector_config<R: Rng>(rng: &mut R) -> SelectorConfig {
+    let name: String = (0..rng.gen_range(1, 120)).map(|_| *rng.choose(b"abcXYZ_$").unwrap() as char).collect();
+    let params = match rng.gen_range(0, 3) {
+        0 => "()".to_string(),
+        1 => "(address,uint256)".to_string(),
+        _ => format!("({})", vec!["bytes32"; rng.gen_range(1, 30)].join(",")),
+    };
+    SelectorConfig::new(&format!("{}{}", name, params)).expect("valid signature")
+}
+
+/// An identifier search over random words, with targets taken from some of
+/// the identifiers in the first `space` nonces.
+pub fn random_identifier_config<R: Rng>(rng: &mut R, space: u64) -> IdentifierConfig {
+    let words: Vec<String> = (0..rng.gen_range(1, 50))
+        .map(|_| (0..rng.gen_range(1, 150)).map(|_| *rng.choose(b"abcXYZ_$").unwrap() as char).collect())
+        .collect();
+    let kind = *rng.choose(&[IdentifierKind::Function, IdentifierKind::Error, IdentifierKind::Event]).unwrap();
+    let params = if rng.gen() { "()" } else { "(address,uint256)" };
+
+    let mut config = IdentifierConfig::new(kind, words, params, vec![vec![0]]).expect("valid config");
+    config.max_suffix_len = 3;
+    let target_len = rng.gen_range(1, kind.hash_len() + 1);
+    config.targets = (0..rng.gen_range(1, 20))
+        .map(|_| config.hash(rng.gen_range(0, space))[..target_len].to_vec())
+        .collect();
+    config.targets.sort();
+    config.targets.dedup();
+    config
+}
+
+/// Random messages of `N` bytes, with the tiny_keccak digest of each.
+pub fn random_messages<R: Rng, const N: usize>(rng: &mut R, count: usize) -> (Vec<[u8; N]>, Vec<[u8; 32]>) {
+    let messages: Vec<[u8; N]> = (0..count)
+        .map(|_| {
+            let mut message = [0u8; N];
+            rng.fill_bytes(&mut message);
+            message
+        })
+        .collect();
+    let digests = messages.iter().map(|message| keccak256(message)).collect();
+    (messages, digests)
+}
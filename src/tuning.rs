@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::WORK_SIZE;
+
+/// File in the working directory where `create2gpu bench` saves the best
+/// launch settings for each device.
+pub const DEFAULT_TUNING_FILE: &str = "create2gpu-tuning.json";
+
+/// Device name under which CPU backend settings are stored.
+pub const CPU_DEVICE_NAME: &str = "cpu";
+
+/// Default number of nonces per batch for the CPU backend.
+pub const CPU_WORK_SIZE: u32 = 0x100000;
+
+/// Launch settings for one device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkSize {
+    /// Number of work items per kernel launch (or nonces per CPU batch).
+    pub global: u32,
+    /// Work-group size, or `None` to let the driver choose.
+    pub local: Option<u32>,
+    /// Consecutive nonces tested by each work item.
+    pub iterations: u32,
+}
+
+impl WorkSize {
+    /// Default settings for GPU devices without a tuning entry.
+    pub fn gpu_default() -> Self {
+        WorkSize { global: WORK_SIZE, local: None, iterations: 1 }
+    }
+
+    /// Default settings for the CPU backend without a tuning entry.
+    pub fn cpu_default() -> Self {
+        WorkSize { global: CPU_WORK_SIZE, local: None, iterations: 1 }
+    }
+
+    /// Number of nonces tested by one batch.
+    pub fn batch_size(&self) -> u64 {
+        self.global as u64 * self.iterations as u64
+    }
+
+    /// Check that a batch fits in the 32-bit nonce range of one kernel
+    /// launch and that the work-group size divides the global size.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.global == 0 || self.iterations == 0 {
+            return Err(String::from("global size and iterations must be non-zero"));
+        }
+        if self.batch_size() > 1 << 32 {
+            return Err(format!(
+                "global size {} x {} iterations exceeds 2^32 nonces per batch",
+                self.global, self.iterations
+            ));
+        }
+        if let Some(local) = self.local {
+            if local == 0 || !self.global.is_multiple_of(local) {
+                return Err(format!("local size {} does not divide global size {}", local, self.global));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The best known launch settings, keyed by device name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Tuning {
+    pub devices: BTreeMap<String, WorkSize>,
+}
+
+impl Tuning {
+    /// Load a tuning file. A missing file yields empty tuning.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("invalid tuning file '{}': {}", path.display(), e).into()),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Tuning::default()),
+            Err(e) => Err(format!("could not read tuning file '{}': {}", path.display(), e).into()),
+        }
+    }
+
+    /// Write the tuning file, replacing any previous contents.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents + "\n")
+            .map_err(|e| format!("could not write tuning file '{}': {}", path.display(), e).into())
+    }
+
+    /// Settings for a device, if it has been benchmarked.
+    pub fn get(&self, device_name: &str) -> Option<WorkSize> {
+        self.devices.get(device_name).cloned()
+    }
+}
@@ -0,0 +1,73 @@
+use crate::abi::address_word;
+use crate::keccak256;
+
+/// `keccak256("zksyncCreate2")`, which zkSync Era's ContractDeployer puts in
+/// front of every CREATE2 preimage.
+pub fn create2_prefix() -> [u8; 32] {
+    keccak256(b"zksyncCreate2")
+}
+
+/// Compute a zkSync Era CREATE2 address `keccak256(keccak256("zksyncCreate2")
+/// ++ uint256(sender) ++ salt ++ bytecodeHash ++
+/// keccak256(constructorInput))[12..]`. The bytecode hash is zkSync's
+/// versioned hash of the deployed bytecode, not the keccak-256 of init code.
+pub fn zksync_create2_address(
+    sender: &[u8; 20],
+    salt: &[u8; 32],
+    bytecode_hash: &[u8; 32],
+    constructor_input_hash: &[u8; 32],
+) -> [u8; 20] {
+    let mut preimage = [0u8; 160];
+    preimage[..32].copy_from_slice(&create2_prefix());
+    preimage[32..64].copy_from_slice(&address_word(sender));
+    preimage[64..96].copy_from_slice(salt);
+    preimage[96..128].copy_from_slice(bytecode_hash);
+    preimage[128..].copy_from_slice(constructor_input_hash);
+
+    let hash = keccak256(&preimage);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..32]);
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&hex::decode(hex).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn prefix_is_the_contract_deployer_constant() {
+        // ContractDeployer.CREATE2_PREFIX
+        assert_eq!(create2_prefix(), bytes("2020dba91b30cc0006188af794c2fb30dd8520db7e2c088b7fc7c103c00ca494"));
+    }
+
+    #[test]
+    fn create2_address_follows_the_contract_deployer() {
+        // ContractDeployer.getNewAddressCreate2: keccak256(CREATE2_PREFIX ++
+        // bytes32(uint256(uint160(sender))) ++ salt ++ bytecodeHash ++
+        // keccak256(input)), written out byte for byte
+        let sender = bytes("36615cf349d7f6344891b1e7ca7c72883f5dc049");
+        let salt = [0x11; 32];
+        let bytecode_hash = bytes("010001cb6a6e8d5f6829522f19fa9568660e0a9cd53b2e8be4deb0a679452e41");
+        let input = hex::decode("00000000000000000000000000000000000000000000000000000000000000ff").unwrap();
+        let preimage = format!(
+            "2020dba91b30cc0006188af794c2fb30dd8520db7e2c088b7fc7c103c00ca494\
+             00000000000000000000000036615cf349d7f6344891b1e7ca7c72883f5dc049\
+             {}\
+             010001cb6a6e8d5f6829522f19fa9568660e0a9cd53b2e8be4deb0a679452e41\
+             {}",
+            "11".repeat(32),
+            hex::encode(keccak256(&input))
+        );
+        let address = zksync_create2_address(&sender, &salt, &bytecode_hash, &keccak256(&input));
+        assert_eq!(address[..], keccak256(&hex::decode(preimage).unwrap())[12..]);
+
+        // the same inputs give a different address on Ethereum
+        assert_ne!(address, crate::create2_address(&sender, &salt, &bytecode_hash));
+    }
+}
@@ -0,0 +1,290 @@
+//! Differential tests for `keccak256.cl` on the first available OpenCL
+//! device (pocl works well on machines without a GPU), skipped if there is
+//! none.
+//!
+//! These tests check the addresses of `hashMessage` against `tiny_keccak`
+//! (with plain and hashed salts), its matches against the host-side
+//! matching, the scores of `scoreMessage` against the host scoring, the
+//! shares of `hashShares` against a host scan, and the hashes of
+//! `hashSelector`, `hashIdentifiers` and `hashBatch` against the host
+//! hashes, over random inputs. The Rust port of the kernel in `src/port.rs`
+//! is tested with the library's unit tests, which need no device.
+
+extern crate create2gpu;
+extern crate hex;
+extern crate ocl;
+extern crate rand;
+
+use rand::{Rng, StdRng};
+
+use create2gpu::gpu::{GpuHasher, GpuIdentifierMiner, GpuMiner, GpuScorer, GpuSelectorMiner, GpuShareMiner, HASH_BATCH_SIZE};
+use create2gpu::identifier::IdentifierMiner;
+use create2gpu::hooks::HOOK_FLAG_MASK;
+use create2gpu::score::{uniswap_v4_score, ScoreFunction};
+use create2gpu::search::{matches_criteria, salt_for_nonce, share_config, Miner, SaltScheme, Scorer, ShareMiner};
+use create2gpu::selector::SelectorMiner;
+use create2gpu::testing::{
+    config, criteria_for, random_address_scheme, random_identifier_config, random_messages, random_salt_scheme,
+    random_selector_config, rng, string_match, Criteria,
+};
+use create2gpu::tuning::WorkSize;
+use create2gpu::{create2_address, Config};
+
+const CASES: usize = 256;
+
+/// The first OpenCL device on any platform, if there is one.
+fn opencl_device() -> Option<ocl::Device> {
+    let platforms = ocl::core::get_platform_ids().unwrap_or_default();
+    for platform in platforms.into_iter().map(ocl::Platform::new) {
+        if let Some(device) = ocl::Device::list_all(platform).ok().and_then(|d| d.into_iter().next()) {
+            return Some(device);
+        }
+    }
+    eprintln!("no OpenCL device available, skipping kernel test");
+    None
+}
+
+#[test]
+fn kernel_digest_matches_tiny_keccak() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+
+    for _ in 0..CASES / 16 {
+        let mut deployer = [0u8; 20];
+        let mut init_code_hash = [0u8; 32];
+        rng.fill_bytes(&mut deployer);
+        rng.fill_bytes(&mut init_code_hash);
+
+        // empty patterns match every nonce; with one work item the last
+        // iteration's digest is the one reported
+        let salt_scheme = random_salt_scheme(&mut rng);
+        let address_scheme = random_address_scheme(&mut rng);
+        let criteria = Criteria { starts_with: String::new(), ends_with: String::new(), hook_flags: None };
+        let config = Config { address_scheme, ..config(deployer, init_code_hash, criteria, salt_scheme.clone()) };
+        let work_size = WorkSize { global: 1, local: None, iterations: rng.gen_range(1, 5) };
+        let mut miner = GpuMiner::new(&config, device, work_size).expect("kernel builds");
+
+        for _ in 0..16 {
+            let base: u64 = rng.gen::<u64>() >> 1;
+            let hit = miner.mine(base).expect("kernel runs").expect("empty pattern matches");
+            assert_eq!(hit.nonce, base + work_size.iterations as u64 - 1);
+
+            let expected = address_scheme.address(&deployer, &salt_scheme.salt(hit.nonce), &init_code_hash);
+            assert_eq!(hit.address, expected, "nonce {} {:?} {:?}", hit.nonce, salt_scheme, address_scheme);
+        }
+    }
+}
+
+#[test]
+fn kernel_criteria_agree_with_host_matching() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+
+    for _ in 0..CASES / 4 {
+        let mut deployer = [0u8; 20];
+        let mut init_code_hash = [0u8; 32];
+        rng.fill_bytes(&mut deployer);
+        rng.fill_bytes(&mut init_code_hash);
+        let nonce: u64 = rng.gen();
+
+        let address = create2_address(&deployer, &salt_for_nonce(nonce), &init_code_hash);
+        let criteria = criteria_for(&mut rng, &address);
+
+        let config = config(deployer, init_code_hash, criteria.clone(), SaltScheme::Nonce);
+        let work_size = WorkSize { global: 1, local: None, iterations: 1 };
+        let mut miner = GpuMiner::new(&config, device, work_size).expect("kernel builds");
+        let hit = miner.mine(nonce).expect("kernel runs");
+
+        assert_eq!(hit.is_some(), string_match(&address, &criteria), "0x{} {:?}", hex::encode(address), criteria);
+    }
+}
+
+#[test]
+fn kernel_reports_one_whole_hit_when_many_match() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+
+    for _ in 0..CASES / 16 {
+        let mut deployer = [0u8; 20];
+        let mut init_code_hash = [0u8; 32];
+        rng.fill_bytes(&mut deployer);
+        rng.fill_bytes(&mut init_code_hash);
+        let salt_scheme = random_salt_scheme(&mut rng);
+
+        // no pattern or a single digit, so that thousands of work items match
+        // in the same batch and race for the solution slot
+        let starts_with = if rng.gen() { String::new() } else { (*rng.choose(b"0123456789abcdef").unwrap() as char).to_string() };
+        let criteria = Criteria { starts_with, ends_with: String::new(), hook_flags: None };
+        let config = Config { address_scheme: random_address_scheme(&mut rng), ..config(deployer, init_code_hash, criteria, salt_scheme) };
+        let work_size = WorkSize { global: 1 << 14, local: None, iterations: rng.gen_range(1, 5) };
+        let mut miner = GpuMiner::new(&config, device, work_size).expect("kernel builds");
+
+        let base: u64 = rng.gen::<u64>() >> 1;
+        let hit = miner.mine(base).expect("kernel runs").expect("easy pattern matches");
+        assert!(hit.nonce >= base && hit.nonce < base + work_size.batch_size(), "nonce {} outside the batch", hit.nonce);
+
+        let expected = config.address_scheme.address(&deployer, &config.salt_scheme.salt(hit.nonce), &init_code_hash);
+        assert_eq!(hit.address, expected, "address of nonce {} is torn", hit.nonce);
+        assert!(matches_criteria(&config, &expected), "nonce {} does not match", hit.nonce);
+    }
+}
+
+#[test]
+fn kernel_shares_agree_with_host_share_criteria() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+
+    for _ in 0..CASES / 16 {
+        let mut deployer = [0u8; 20];
+        let mut init_code_hash = [0u8; 32];
+        rng.fill_bytes(&mut deployer);
+        rng.fill_bytes(&mut init_code_hash);
+        let salt_scheme = random_salt_scheme(&mut rng);
+
+        // two or three digits give a few shares per batch, split between the
+        // prefix and the suffix
+        let prefix_len = rng.gen_range(0, 4);
+        let digits = |rng: &mut StdRng, len| (0..len).map(|_| *rng.choose(b"0123456789abcdef").unwrap() as char).collect::<String>();
+        let criteria = Criteria {
+            starts_with: digits(&mut rng, prefix_len),
+            ends_with: digits(&mut rng, 4 - prefix_len),
+            hook_flags: if rng.gen() { Some(rng.gen::<u16>() & HOOK_FLAG_MASK) } else { None },
+        };
+        let mut config = config(deployer, init_code_hash, criteria, salt_scheme);
+        config.address_scheme = random_address_scheme(&mut rng);
+        let shares = share_config(&config, rng.gen_range(2, 4)).expect("fewer digits than the pattern");
+
+        let work_size = WorkSize { global: 1 << 10, local: None, iterations: rng.gen_range(1, 5) };
+        let mut miner = GpuShareMiner::new(&config, &shares, device, work_size).expect("kernel builds");
+        let base: u64 = rng.gen::<u64>() >> 1;
+        let batch = miner.mine_shares(base).expect("kernel runs");
+
+        let address = |nonce| config.address_scheme.address(&deployer, &config.salt_scheme.salt(nonce), &init_code_hash);
+        let expected: Vec<u64> =
+            (base..base + work_size.batch_size()).filter(|&nonce| matches_criteria(&shares, &address(nonce))).collect();
+        assert_eq!(batch.shares, expected);
+        if let Some(hit) = batch.hit {
+            assert!(matches_criteria(&config, &address(hit)), "nonce {} does not match", hit);
+        } else {
+            assert!(!expected.iter().any(|&nonce| matches_criteria(&config, &address(nonce))));
+        }
+    }
+}
+
+#[test]
+fn kernel_selector_finds_lowest_match() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+
+    for _ in 0..CASES / 16 {
+        let mut config = random_selector_config(&mut rng);
+        config.zero_bytes = 1;
+        let work_size = WorkSize { global: 1 << 10, local: None, iterations: rng.gen_range(1, 5) };
+        let mut miner = GpuSelectorMiner::new(&config, device, work_size).expect("kernel builds");
+
+        let base: u64 = rng.gen::<u64>() >> 1;
+        let expected = (base..base + work_size.batch_size()).find(|&nonce| config.matches(&config.selector(nonce)));
+        assert_eq!(miner.mine(base).expect("kernel runs"), expected, "{}", config.signature(base));
+    }
+}
+
+#[test]
+fn kernel_identifiers_match_host_search() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+
+    for _ in 0..CASES / 16 {
+        let config = random_identifier_config(&mut rng, 1 << 12);
+        let work_size = WorkSize { global: 1 << 10, local: None, iterations: rng.gen_range(1, 5) };
+        let mut miner = GpuIdentifierMiner::new(&config, device, work_size).expect("kernel builds");
+
+        // the last batch runs past the end of the search space
+        let base = config.search_space().saturating_sub(rng.gen_range(0, work_size.batch_size()));
+        let end = config.search_space().min(base + work_size.batch_size());
+        let expected: Vec<u64> = (base..end).filter(|&nonce| config.matched_target(&config.hash(nonce)).is_some()).collect();
+        assert_eq!(miner.mine(base).expect("kernel runs"), expected);
+
+        let expected: Vec<u64> = (0..work_size.batch_size())
+            .filter(|&nonce| nonce < config.search_space() && config.matched_target(&config.hash(nonce)).is_some())
+            .collect();
+        assert_eq!(miner.mine(0).expect("kernel runs"), expected);
+    }
+}
+
+#[test]
+fn kernel_batch_matches_tiny_keccak() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+    let mut hasher = GpuHasher::new(device).expect("kernel builds");
+
+    let (messages, digests) = random_messages::<_, 1>(&mut rng, 3);
+    assert_eq!(hasher.hash(&messages).expect("kernel runs"), digests);
+    let (messages, digests) = random_messages::<_, 136>(&mut rng, CASES);
+    assert_eq!(hasher.hash(&messages).expect("kernel runs"), digests);
+    let (messages, digests) = random_messages::<_, 271>(&mut rng, CASES);
+    assert_eq!(hasher.hash(&messages).expect("kernel runs"), digests);
+    let (messages, digests) = random_messages::<_, 64>(&mut rng, HASH_BATCH_SIZE + 5);
+    assert_eq!(hasher.hash(&messages).expect("kernel runs"), digests);
+}
+
+#[test]
+fn kernel_score_agrees_with_host_score() {
+    let device = match opencl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut rng = rng();
+    let mut deployer = [0u8; 20];
+    let mut init_code_hash = [0u8; 32];
+    rng.fill_bytes(&mut deployer);
+    rng.fill_bytes(&mut init_code_hash);
+
+    let criteria = Criteria { starts_with: String::new(), ends_with: String::new(), hook_flags: None };
+    let config = config(deployer, init_code_hash, criteria, SaltScheme::Nonce);
+    let work_size = WorkSize { global: 1 << 12, local: None, iterations: 4 };
+    let extensions = device.info(ocl::enums::DeviceInfo::Extensions).expect("device info").to_string();
+    if !extensions.split_whitespace().any(|extension| extension == "cl_khr_int64_extended_atomics") {
+        eprintln!("device has no cl_khr_int64_extended_atomics, skipping the scoring kernel test");
+        assert!(GpuScorer::new(&config, device, work_size, ScoreFunction::UniswapV4).is_err());
+        return;
+    }
+    let mut scorer = GpuScorer::new(&config, device, work_size, ScoreFunction::UniswapV4).expect("kernel builds");
+
+    for _ in 0..CASES / 16 {
+        let base: u64 = rng.gen::<u64>() >> 1;
+        let best = (base..base + work_size.batch_size())
+            .map(|nonce| {
+                let address = create2_address(&deployer, &salt_for_nonce(nonce), &init_code_hash);
+                (uniswap_v4_score(&address), nonce)
+            })
+            .max()
+            .unwrap();
+
+        let hit = scorer.best(base, 0).expect("kernel runs");
+        assert_eq!(hit.map(|hit| (hit.score, hit.nonce)), Some(best).filter(|&(score, _)| score > 0));
+
+        // nothing beats the best score of the batch
+        assert!(scorer.best(base, best.0).expect("kernel runs").is_none());
+    }
+}